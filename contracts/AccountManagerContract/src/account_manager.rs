@@ -9,22 +9,37 @@ use soroban_sdk::{
 
 use crate::types::{
     AccountCreationEvent, AccountDeletionEvent, AccountManagerError, AccountManagerKey,
-    ExternalProtocolCall, TraderBorrowEvent, TraderLiquidateEvent, TraderRepayEvent,
-    TraderSettleAccountEvent,
+    AccountReport, AllowedExternalCall, AuctionBidEvent, AuctionConfig, BatchCall,
+    CollateralHistory, ConditionalOrder, DebtHistory, DeleverageConfig, DeleverageEvent, ExternalProtocolCall, ExternalRewardsClaimEvent, IsolatedDebtEntry,
+    LeveragedPosition,
+    LeveragedPositionEvent, LiquidationAuction, OpenPositionRequest, OperatorGrant,
+    OperatorGrantEvent, OperatorPermission, OperatorRevokeEvent, OrderAction, OrderCondition,
+    OrderTriggeredEvent, OwnershipTransferEvent, RepayForEvent, TraderBorrowEvent,
+    TraderLiquidateEvent, TraderRepayEvent, TraderSettleAccountEvent,
 };
 
+use registry_contract::BlendPositionKind;
 use smart_account_contract::SmartAccExternalAction;
 
 const TLL_LEDGERS_YEAR: u32 = 6307200;
 const TLL_LEDGERS_10YEAR: u32 = 6307200 * 10;
-pub const WAD_U128: u128 = 10000_0000_00000_00000; // 1e18
+pub const WAD_U128: u128 = 1_000_000_000_000_000_000; // 1e18
 const XLM_SYMBOL: Symbol = symbol_short!("XLM");
 const USDC_SYMBOL: Symbol = symbol_short!("USDC");
 const EURC_SYMBOL: Symbol = symbol_short!("EURC");
-const BLEND_XLM: &str = "BLEND_XLM";
-const BLEND_USDC: &str = "BLEND_USDC";
-const BLEND_EURC: &str = "BLEND_EURC";
 const AQUARIUS_XLM_USDC: &str = "AQ_XLM_USDC"; // Aquarius XLM-USDC LP token tracking
+const SOROSWAP_XLM_USDC: &str = "SS_XLM_USDC"; // Soroswap XLM-USDC LP token tracking
+const SOROSWAP_XLM_EURC: &str = "SS_XLM_EURC"; // Soroswap XLM-EURC LP token tracking
+const SOROSWAP_USDC_EURC: &str = "SS_USDC_EURC"; // Soroswap USDC-EURC LP token tracking
+const BPS_DENOMINATOR: u32 = 10_000;
+const DEFAULT_KEEPER_FEE_BPS: u32 = 10; // 0.1% of the amount an order moves
+const MAX_KEEPER_FEE_BPS: u32 = 500;
+const MAX_OPEN_ORDERS: u32 = 10;
+const DEFAULT_DELEVERAGE_SHARE_BPS: u32 = 2_000; // Share of each debt a single deleverage call repays
+const DEFAULT_DELEVERAGE_MAX_SLIPPAGE_BPS: u32 = 100; // Allowed swap loss against oracle prices
+const DEFAULT_AUCTION_DISCOUNT_PER_LEDGER_BPS: u32 = 5;
+const DEFAULT_AUCTION_MAX_DISCOUNT_BPS: u32 = 1_500;
+pub const REPAY_ALL_WAD: u128 = u128::MAX; // Repay amount that closes the whole debt
 
 pub mod smart_account_contract {
     soroban_sdk::contractimport!(
@@ -62,12 +77,26 @@ pub mod lending_protocol_eurc {
     );
 }
 
+pub mod oracle_contract {
+    soroban_sdk::contractimport!(file = "../../target/wasm32v1-none/release/oracle_contract.wasm");
+}
+
 pub mod tracking_token_contract {
     soroban_sdk::contractimport!(
         file = "../../target/wasm32v1-none/release/tracking_token_contract.wasm"
     );
 }
 
+// Valuation subset of the protocol adapter interface (see SmartAccountContract)
+pub mod protocol_adapter_contract {
+    use soroban_sdk::{contractclient, Address, Env, Symbol, Vec, U256};
+
+    #[contractclient(name = "Client")]
+    pub trait ProtocolAdapterTrait {
+        fn value_position(env: Env, smart_account: Address) -> Vec<(Symbol, U256)>;
+    }
+}
+
 #[contract]
 pub struct AccountManagerContract;
 
@@ -141,10 +170,14 @@ impl AccountManagerContract {
             smart_account_contract::Client::new(&env, &smart_account_address);
 
         if smart_account_client.has_debt() {
-            panic!("Cannot delete account with debt, please repay debt first");
+            return Err(AccountManagerError::AccountHasDebt);
         }
 
+        let collateral_tokens = smart_account_client.get_all_collateral_tokens();
         smart_account_client.sweep_to(&trader_address);
+        for token_symbol in collateral_tokens.iter() {
+            Self::sync_collateral_holder(env, &smart_account_client, &token_symbol);
+        }
 
         let registry_contract_address: Address = Self::get_registry_address(env);
         let registry_client = registry_contract::Client::new(env, &registry_contract_address);
@@ -174,66 +207,158 @@ impl AccountManagerContract {
         Ok(true)
     }
 
-    pub fn deposit_collateral_tokens(
-        env: Env,
+    /// First step of an ownership transfer: the current owner proposes `new_owner`.
+    /// Positions and debt stay in place; nothing changes until the new owner accepts.
+    pub fn transfer_account_ownership(
+        env: &Env,
         smart_account: Address,
-        token_symbol: Symbol,
-        token_amount_wad: U256,
+        new_owner: Address,
     ) -> Result<(), AccountManagerError> {
-        let trader_address = Self::get_trader_address(&env, &smart_account);
+        let trader_address = Self::get_trader_address(env, &smart_account);
         trader_address.require_auth();
 
-        if token_amount_wad.eq(&U256::from_u128(&env, 0)) {
-            panic!("Cannot deposit a zero amount");
+        if new_owner == trader_address {
+            return Err(AccountManagerError::InvalidNewOwner);
+        }
+        let smart_account_client = smart_account_contract::Client::new(env, &smart_account);
+        if !smart_account_client.is_account_active() {
+            return Err(AccountManagerError::AccountInactive);
         }
 
-        if !Self::get_iscollateral_allowed(&env, token_symbol.clone()) {
-            panic!("This token is not allowed as collateral");
+        let key = AccountManagerKey::PendingOwner(smart_account.clone());
+        env.storage().persistent().set(&key, &new_owner);
+        Self::extend_ttl_account_manager(env, key);
+
+        env.events().publish(
+            (Symbol::new(env, "Ownership_Transfer_Started"), smart_account),
+            (trader_address, new_owner),
+        );
+        Ok(())
+    }
+
+    pub fn cancel_ownership_transfer(
+        env: &Env,
+        smart_account: Address,
+    ) -> Result<(), AccountManagerError> {
+        let trader_address = Self::get_trader_address(env, &smart_account);
+        trader_address.require_auth();
+
+        let key = AccountManagerKey::PendingOwner(smart_account.clone());
+        if !env.storage().persistent().has(&key) {
+            return Err(AccountManagerError::NoPendingOwnershipTransfer);
         }
+        env.storage().persistent().remove(&key);
 
-        let smart_account_client = smart_account_contract::Client::new(&env, &smart_account);
-        let collateral_tokens = smart_account_client.get_all_collateral_tokens();
+        env.events().publish(
+            (Symbol::new(env, "Ownership_Transfer_Cancelled"), smart_account),
+            trader_address,
+        );
+        Ok(())
+    }
 
-        if U256::from_u32(&env, collateral_tokens.len()) >= Self::get_max_asset_cap(&env) {
-            panic!("Max asset cap crossed!");
+    /// Second step of an ownership transfer, signed by the proposed owner. Moves the account
+    /// to the new owner in the account manager, registry and smart account and drops all
+    /// operator grants made by the previous owner.
+    pub fn accept_account_ownership(
+        env: &Env,
+        smart_account: Address,
+    ) -> Result<(), AccountManagerError> {
+        let new_owner = Self::get_pending_owner(env, smart_account.clone())
+            .ok_or(AccountManagerError::NoPendingOwnershipTransfer)?;
+        new_owner.require_auth();
+
+        let previous_owner = Self::get_trader_address(env, &smart_account);
+
+        let mut previous_accounts: Vec<Address> = env
+            .storage()
+            .persistent()
+            .get(&AccountManagerKey::SmartAccounts(previous_owner.clone()))
+            .unwrap_or(Vec::new(env));
+        if let Some(index) = previous_accounts.first_index_of(&smart_account) {
+            previous_accounts.remove(index);
         }
+        Self::set_smart_accounts(env, &previous_owner, previous_accounts);
 
-        if !collateral_tokens.contains(token_symbol.clone()) {
-            smart_account_client.add_collateral_token(&token_symbol);
+        let mut new_owner_accounts: Vec<Address> = env
+            .storage()
+            .persistent()
+            .get(&AccountManagerKey::SmartAccounts(new_owner.clone()))
+            .unwrap_or(Vec::new(env));
+        new_owner_accounts.push_back(smart_account.clone());
+        Self::set_smart_accounts(env, &new_owner, new_owner_accounts);
+
+        let users_key = AccountManagerKey::UsersList;
+        let mut users: Vec<Address> = env
+            .storage()
+            .persistent()
+            .get(&users_key)
+            .unwrap_or(Vec::new(env));
+        if !users.contains(&new_owner) {
+            users.push_back(new_owner.clone());
+            env.storage().persistent().set(&users_key, &users);
+            Self::extend_ttl_account_manager(env, users_key);
         }
 
-        let amount_wad_u128 = Self::convert_u256_to_u128(&env, &token_amount_wad);
-        let registry_address = Self::get_registry_address(&env);
-        let registry_client = registry_contract::Client::new(&env, &registry_address);
+        let trader_key = AccountManagerKey::TraderAddress(smart_account.clone());
+        env.storage().persistent().set(&trader_key, &new_owner);
+        Self::extend_ttl_account_manager(env, trader_key);
 
-        // Transfer tokens based on type
-        if token_symbol == XLM_SYMBOL {
-            let token_client = token::Client::new(&env, &registry_client.get_xlm_contract_adddress());
-            let amount_scaled = Self::scale_for_operation(amount_wad_u128, token_client.decimals());
-            token_client.transfer(&trader_address, &smart_account, &amount_scaled);
-        } else if token_symbol == USDC_SYMBOL {
-            let token_client = token::Client::new(&env, &registry_client.get_usdc_contract_address());
-            let amount_scaled = Self::scale_for_operation(amount_wad_u128, token_client.decimals());
-            token_client.transfer(&trader_address, &smart_account, &amount_scaled);
-        } else if token_symbol == EURC_SYMBOL {
-            let token_client = token::Client::new(&env, &registry_client.get_eurc_contract_address());
-            let amount_scaled = Self::scale_for_operation(amount_wad_u128, token_client.decimals());
-            token_client.transfer(&trader_address, &smart_account, &amount_scaled);
-        } else {
-            panic!("Collateral not allowed for this token symbol");
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        registry_client.update_account(&new_owner, &smart_account);
+        smart_account_contract::Client::new(env, &smart_account).set_owner_address(&new_owner);
+
+        for operator in Self::get_operators(env, smart_account.clone()).iter() {
+            env.storage()
+                .persistent()
+                .remove(&AccountManagerKey::Operator(smart_account.clone(), operator));
         }
+        env.storage()
+            .persistent()
+            .remove(&AccountManagerKey::OperatorsList(smart_account.clone()));
+        env.storage()
+            .persistent()
+            .remove(&AccountManagerKey::PendingOwner(smart_account.clone()));
 
-        // Update balance
-        let existing_bal = smart_account_client.get_collateral_token_balance(&token_symbol);
-        smart_account_client.set_collateral_token_balance(
-            &token_symbol, 
-            &existing_bal.add(&token_amount_wad)
-        );
+        // Orders, position bookkeeping and auctions were set up by the previous owner
+        env.storage()
+            .persistent()
+            .remove(&AccountManagerKey::ConditionalOrders(smart_account.clone()));
+        for venue in Self::get_leveraged_venues(env, &smart_account).iter() {
+            env.storage()
+                .persistent()
+                .remove(&AccountManagerKey::LeveragedPosition(smart_account.clone(), venue));
+        }
+        env.storage()
+            .persistent()
+            .remove(&AccountManagerKey::LeveragedVenues(smart_account.clone()));
+        if env
+            .storage()
+            .persistent()
+            .has(&AccountManagerKey::LiquidationAuction(smart_account.clone()))
+        {
+            Self::end_auction(env, &smart_account);
+        }
 
+        env.events().publish(
+            (Symbol::new(env, "Ownership_Transferred"), smart_account.clone()),
+            OwnershipTransferEvent {
+                smart_account,
+                previous_owner,
+                new_owner,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
         Ok(())
     }
 
-    pub fn withdraw_collateral_balance(
+    pub fn get_pending_owner(env: &Env, smart_account: Address) -> Option<Address> {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::PendingOwner(smart_account))
+    }
+
+    pub fn deposit_collateral_tokens(
         env: Env,
         smart_account: Address,
         token_symbol: Symbol,
@@ -242,33 +367,44 @@ impl AccountManagerContract {
         let trader_address = Self::get_trader_address(&env, &smart_account);
         trader_address.require_auth();
 
-        if token_amount_wad.eq(&U256::from_u128(&env, 0)) {
-            panic!("Cannot withdraw a zero amount");
-        }
+        Self::deposit_collateral_internal(
+            &env,
+            &trader_address,
+            &smart_account,
+            token_symbol,
+            token_amount_wad,
+        );
 
-        let smart_account_client = smart_account_contract::Client::new(&env, &smart_account);
+        Ok(())
+    }
 
-        let collateral_tokens_list = smart_account_client.get_all_collateral_tokens();
-        if !collateral_tokens_list.contains(token_symbol.clone()) {
-            panic!("User doesn't have collateral in this token");
-        }
+    pub fn withdraw_collateral_balance(
+        env: Env,
+        smart_account: Address,
+        token_symbol: Symbol,
+        token_amount_wad: U256,
+    ) -> Result<(), AccountManagerError> {
+        let trader_address = Self::get_trader_address(&env, &smart_account);
+        trader_address.require_auth();
 
         let registry_address = Self::get_registry_address(&env);
         let registry_client = registry_contract::Client::new(&env, &registry_address);
         let risk_engine_address = registry_client.get_risk_engine_address();
         let risk_engine_client = risk_engine_contract::Client::new(&env, &risk_engine_address);
 
+        Self::check_collateral_withdrawal(&env, &smart_account, &token_symbol, &token_amount_wad);
+
         if !risk_engine_client.is_withdraw_allowed(&token_symbol, &token_amount_wad, &smart_account)
         {
-            panic!("Account is unhealthy! withdraw is not allowed");
+            return Err(AccountManagerError::WithdrawNotAllowed);
         }
 
-        let amount_u128: u128 = Self::convert_u256_to_u128(&env, &token_amount_wad);
-
-        smart_account_client.remove_collateral_token_balance(
+        Self::withdraw_collateral_internal(
+            &env,
             &trader_address,
-            &token_symbol,
-            &amount_u128,
+            &smart_account,
+            token_symbol,
+            token_amount_wad,
         );
 
         Ok(())
@@ -284,57 +420,32 @@ impl AccountManagerContract {
         trader_address.require_auth();
 
         if borrow_amount_wad.eq(&U256::from_u128(&env, 0)) {
-            panic!("Cannot borrow a zero amount");
+            return Err(AccountManagerError::ZeroAmount);
         }
 
         let registry_address = Self::get_registry_address(env);
         let registry_client = registry_contract::Client::new(&env, &registry_address);
-        let risk_engine_address = registry_client.get_risk_engine_address();
-        let risk_engine_client = risk_engine_contract::Client::new(&env, &risk_engine_address);
-
-        // Check borrow allowance
-        if !risk_engine_client.is_borrow_allowed(
+        Self::check_borrow_allowed(
+            env,
+            &registry_client,
+            &smart_account,
             &token_symbol,
             &borrow_amount_wad,
-            &smart_account,
-        ) {
-            panic!("Borrowing is not allowed for this user");
-        }
-
-        let smart_account_client = smart_account_contract::Client::new(&env, &smart_account);
-
-        // Execute lending based on token type
-        if token_symbol == XLM_SYMBOL {
-            let pool_xlm = registry_client.get_lendingpool_xlm();
-            lending_protocol_xlm::Client::new(&env, &pool_xlm)
-                .lend_to(&smart_account, &borrow_amount_wad);
-            smart_account_client.add_borrowed_token(&XLM_SYMBOL);
-            smart_account_client.set_has_debt(&true);
-        } else if token_symbol == USDC_SYMBOL {
-            let pool_usdc = registry_client.get_lendingpool_usdc();
-            lending_protocol_usdc::Client::new(&env, &pool_usdc)
-                .lend_to(&smart_account, &borrow_amount_wad);
-            smart_account_client.add_borrowed_token(&USDC_SYMBOL);
-            smart_account_client.set_has_debt(&true);
-        } else if token_symbol == EURC_SYMBOL {
-            let pool_eurc = registry_client.get_lendingpool_eurc();
-            lending_protocol_eurc::Client::new(&env, &pool_eurc)
-                .lend_to(&smart_account, &borrow_amount_wad);
-            smart_account_client.add_borrowed_token(&EURC_SYMBOL);
-            smart_account_client.set_has_debt(&true);
-        } else {
-            panic!("No lending pool available for given token_symbol");
-        }
+        )?;
 
-        // Publish simplified event
-        env.events().publish(
-            (Symbol::new(&env, "Trader_Borrow"), smart_account.clone()),
+        Self::borrow_internal(
+            env,
+            &registry_client,
+            &smart_account,
+            borrow_amount_wad,
             token_symbol,
         );
 
         Ok(())
     }
 
+    /// Repays lending pool debt from the account's balance. Amounts above the accrued debt
+    /// are capped, and `REPAY_ALL_WAD` closes the position in full.
     pub fn repay(
         env: Env,
         repay_amount_wad: U256,
@@ -344,67 +455,64 @@ impl AccountManagerContract {
         let trader_address = Self::get_trader_address(&env, &smart_account);
         trader_address.require_auth();
 
-        if repay_amount_wad.eq(&U256::from_u128(&env, 0)) {
-            panic!("Cannot repay a zero amount");
-        }
+        Self::repay_internal(&env, &smart_account, repay_amount_wad, token_symbol);
+        Ok(())
+    }
 
-        let registry_address: Address = Self::get_registry_address(&env);
-        let registry_client = registry_contract::Client::new(&env, &registry_address);
+    /// Repays the account's lending pool debt with `payer`'s own tokens. Anyone may pay; only
+    /// the capped amount is taken from the payer, and `REPAY_ALL_WAD` clears the whole debt.
+    pub fn repay_for(
+        env: Env,
+        payer: Address,
+        smart_account: Address,
+        token_symbol: Symbol,
+        repay_amount_wad: U256,
+    ) -> Result<U256, AccountManagerError> {
+        payer.require_auth();
 
+        if repay_amount_wad == U256::from_u128(&env, 0) {
+            return Err(AccountManagerError::ZeroAmount);
+        }
         let smart_account_client = smart_account_contract::Client::new(&env, &smart_account);
-
-        let borrowed_tokens = smart_account_client.get_all_borrowed_tokens();
-
-        if !borrowed_tokens.contains(token_symbol.clone()) {
-            panic!("User doen't have debt in the token symbol passed");
+        if Self::is_blend_debt_symbol(&env, &token_symbol)
+            || !smart_account_client
+                .get_all_borrowed_tokens()
+                .contains(token_symbol.clone())
+        {
+            return Err(AccountManagerError::BorrowedTokenNotFound);
         }
 
-        let amount_wad_u128: u128 = Self::convert_u256_to_u128(&env, &repay_amount_wad);
-
-        let _debt = smart_account_client.get_borrowed_token_debt(&token_symbol.clone());
-        // !! Should we check if the repay amount is greater than the debt amount?
+        let registry_address = Self::get_registry_address(&env);
+        let registry_client = registry_contract::Client::new(&env, &registry_address);
+        let repay_amount = Self::min_u256(
+            repay_amount_wad,
+            Self::accrued_debt(&env, &registry_client, &smart_account, &token_symbol),
+        );
 
-        if token_symbol == XLM_SYMBOL {
-            let pool_xlm_contract = registry_client.get_lendingpool_xlm();
-            let xlm_client = lending_protocol_xlm::Client::new(&env, &pool_xlm_contract);
-            let bool = xlm_client.collect_from(&repay_amount_wad, &smart_account);
-            smart_account_client.remove_borrowed_token_balance(&XLM_SYMBOL, &amount_wad_u128);
-            if bool {
-                smart_account_client.remove_borrowed_token(&XLM_SYMBOL);
-            }
-        } else if token_symbol == USDC_SYMBOL {
-            let pool_usdc_contract = registry_client.get_lendingpool_usdc();
-            let usdc_client = lending_protocol_usdc::Client::new(&env, &pool_usdc_contract);
-            let bool = usdc_client.collect_from(&repay_amount_wad, &smart_account);
-            smart_account_client.remove_borrowed_token_balance(&USDC_SYMBOL, &amount_wad_u128);
-            if bool {
-                smart_account_client.remove_borrowed_token(&USDC_SYMBOL);
-            }
-        } else if token_symbol == EURC_SYMBOL {
-            let pool_eurc_contract = registry_client.get_lendingpool_eurc();
-            let eurc_client = lending_protocol_eurc::Client::new(&env, &pool_eurc_contract);
-            let bool = eurc_client.collect_from(&repay_amount_wad, &smart_account);
-            smart_account_client.remove_borrowed_token_balance(&EURC_SYMBOL, &amount_wad_u128);
-            if bool {
-                smart_account_client.remove_borrowed_token(&EURC_SYMBOL);
-            }
-        } else {
-            panic!("No lending pool available for given token_symbol");
-        }
+        let token_client = token::Client::new(
+            &env,
+            &Self::token_contract_address(&registry_client, &token_symbol),
+        );
+        let repay_u128 = Self::convert_u256_to_u128(&env, &repay_amount);
+        token_client.transfer(
+            &payer,
+            &smart_account,
+            &Self::scale_for_operation(repay_u128, token_client.decimals()),
+        );
+        let repaid_wad =
+            Self::repay_internal(&env, &smart_account, repay_amount, token_symbol.clone());
 
         env.events().publish(
-            (
-                Symbol::new(&env, "Trader_Repay_Event"),
-                smart_account.clone(),
-            ),
-            TraderRepayEvent {
-                smart_account: smart_account,
-                token_amount: repay_amount_wad,
-                timestamp: env.ledger().timestamp(),
+            (Symbol::new(&env, "Repay_For_Event"), smart_account.clone()),
+            RepayForEvent {
+                payer,
+                smart_account,
                 token_symbol,
+                token_amount: repaid_wad.clone(),
+                timestamp: env.ledger().timestamp(),
             },
         );
-        Ok(())
+        Ok(repaid_wad)
     }
 
     pub fn liquidate(env: Env, smart_account: Address) -> Result<(), AccountManagerError> {
@@ -420,56 +528,53 @@ impl AccountManagerContract {
             &risk_engine_client.get_current_total_balance(&smart_account),
             &risk_engine_client.get_current_total_borrows(&smart_account),
         ) {
-            panic!("Cannot liquidate when account is healthy!!");
+            return Err(AccountManagerError::AccountHealthy);
         }
 
+        Self::unwind_external_positions(&env, &registry_client, &trader_address, &smart_account);
+
         let smart_account_client = smart_account_contract::Client::new(&env, &smart_account);
         let all_borrowed_tokens = smart_account_client.get_all_borrowed_tokens();
 
         for tokenx in all_borrowed_tokens.iter() {
-            if tokenx == XLM_SYMBOL {
-                let pool_xlm_contract = registry_client.get_lendingpool_xlm();
-                let xlm_client: lending_protocol_xlm::Client<'_> =
-                    lending_protocol_xlm::Client::new(&env, &pool_xlm_contract);
-                let liquidate_amount = xlm_client.get_borrow_balance(&smart_account);
-                let amount_wad_u128: u128 = Self::convert_u256_to_u128(&env, &liquidate_amount);
-                let bool = xlm_client.collect_from(&liquidate_amount, &smart_account);
-                smart_account_client.remove_borrowed_token_balance(&XLM_SYMBOL, &amount_wad_u128);
-                if bool {
-                    smart_account_client.remove_borrowed_token(&XLM_SYMBOL);
-                }
-            } else if tokenx == USDC_SYMBOL {
-                let pool_usdc_contract = registry_client.get_lendingpool_usdc();
-                let usdc_client: lending_protocol_usdc::Client<'_> =
-                    lending_protocol_usdc::Client::new(&env, &pool_usdc_contract);
-                let liquidate_amount = usdc_client.get_borrow_balance(&smart_account);
-
-                let amount_wad_u128: u128 = Self::convert_u256_to_u128(&env, &liquidate_amount);
-
-                let bool = usdc_client.collect_from(&liquidate_amount, &smart_account);
-                smart_account_client.remove_borrowed_token_balance(&USDC_SYMBOL, &amount_wad_u128);
-                if bool {
-                    smart_account_client.remove_borrowed_token(&USDC_SYMBOL);
-                }
-            } else if tokenx == EURC_SYMBOL {
-                let pool_eurc_contract = registry_client.get_lendingpool_eurc();
-                let eurc_client: lending_protocol_eurc::Client<'_> =
-                    lending_protocol_eurc::Client::new(&env, &pool_eurc_contract);
-                let liquidate_amount = eurc_client.get_borrow_balance(&smart_account);
-
-                let amount_wad_u128: u128 = Self::convert_u256_to_u128(&env, &liquidate_amount);
-
-                let bool = eurc_client.collect_from(&liquidate_amount, &smart_account);
-                smart_account_client.remove_borrowed_token_balance(&EURC_SYMBOL, &amount_wad_u128);
-                if bool {
-                    smart_account_client.remove_borrowed_token(&EURC_SYMBOL);
-                }
-            } else {
-                panic!("This token pool doesn't exist")
+            if Self::is_blend_debt_symbol(&env, &tokenx) {
+                // Blend liabilities are owed to the Blend pool, not the lending pools
+                Self::repay_blend_liability(&env, &registry_client, &smart_account_client, &tokenx);
+                continue;
+            }
+            if tokenx != XLM_SYMBOL && tokenx != USDC_SYMBOL && tokenx != EURC_SYMBOL {
+                return Err(AccountManagerError::LendingPoolNotFound);
+            }
+            // Repaid in full through the normal path so isolated debt is released too
+            let accrued_wad = Self::accrued_debt(&env, &registry_client, &smart_account, &tokenx);
+            if accrued_wad > U256::from_u128(&env, 0) {
+                Self::repay_internal(&env, &smart_account, accrued_wad, tokenx);
             }
         }
 
+        // The swept collateral leaves the account like a withdrawal
+        let collateral_tokens = smart_account_client.get_all_collateral_tokens();
+        for token_symbol in collateral_tokens.iter() {
+            Self::record_collateral_flow(
+                &env,
+                &registry_client,
+                &smart_account,
+                &token_symbol,
+                &smart_account_client.get_collateral_token_balance(&token_symbol),
+                false,
+            );
+        }
         smart_account_client.sweep_to(&trader_address);
+        for token_symbol in collateral_tokens.iter() {
+            Self::sync_collateral_holder(&env, &smart_account_client, &token_symbol);
+        }
+        if env
+            .storage()
+            .persistent()
+            .has(&AccountManagerKey::LiquidationAuction(smart_account.clone()))
+        {
+            Self::end_auction(&env, &smart_account);
+        }
 
         env.events().publish(
             (
@@ -484,64 +589,265 @@ impl AccountManagerContract {
         Ok(())
     }
 
-    pub fn settle_account(env: Env, smart_account: Address) -> Result<bool, AccountManagerError> {
-        let trader_address = Self::get_trader_address(&env, &smart_account);
-        trader_address.require_auth();
+    /// Soft liquidation. While the account is in the risk engine's deleverage zone anyone may
+    /// call this to sell a bounded share of its collateral for the borrowed asset on Aquarius
+    /// or Soroswap and repay with the proceeds.
+    pub fn deleverage(env: &Env, smart_account: Address) -> Result<(), AccountManagerError> {
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
 
-        let smart_account_contract_client =
-            smart_account_contract::Client::new(&env, &smart_account);
-        let borrowed_tokens = smart_account_contract_client.get_all_borrowed_tokens();
-        for tokenx in borrowed_tokens.iter() {
-            let token_debt = smart_account_contract_client.get_borrowed_token_debt(&tokenx.clone());
-            Self::repay(env.clone(), token_debt, tokenx, smart_account.clone())
-                .expect("Failed to repay while settling the account");
+        let risk_engine_address = registry_client.get_risk_engine_address();
+        let risk_engine_client = risk_engine_contract::Client::new(env, &risk_engine_address);
+        if !risk_engine_client.is_deleverage_allowed(
+            &risk_engine_client.get_current_total_balance(&smart_account),
+            &risk_engine_client.get_current_total_borrows(&smart_account),
+        ) {
+            return Err(AccountManagerError::DeleverageNotAllowed);
         }
-        env.events().publish(
-            (
-                Symbol::new(&env, "Trader_SettleAccount_Event"),
-                smart_account.clone(),
-            ),
-            TraderSettleAccountEvent {
-                smart_account,
-                timestamp: env.ledger().timestamp(),
+
+        let venues = Self::swap_venues(&registry_client);
+        let config = Self::get_deleverage_config(env);
+        let smart_account_client = smart_account_contract::Client::new(env, &smart_account);
+        let mut deleveraged = false;
+
+        for debt_symbol in smart_account_client.get_all_borrowed_tokens().iter() {
+            if Self::is_blend_debt_symbol(env, &debt_symbol) {
+                continue;
+            }
+            let debt = smart_account_client.get_borrowed_token_debt(&debt_symbol);
+            let target_repay = debt
+                .mul(&U256::from_u32(env, config.share_bps))
+                .div(&U256::from_u32(env, BPS_DENOMINATOR));
+            if target_repay == U256::from_u128(env, 0) {
+                continue;
+            }
+
+            let Some((collateral_symbol, amount_in, amount_out)) = Self::swap_collateral_into(
+                env,
+                &registry_client,
+                &smart_account_client,
+                &venues,
+                &debt_symbol,
+                &target_repay,
+                config.max_slippage_bps,
+            )?
+            else {
+                continue;
+            };
+            let repay_amount = if amount_out < debt { amount_out } else { debt.clone() };
+            Self::repay_from_collateral(
+                env,
+                &smart_account_client,
+                &smart_account,
+                &debt_symbol,
+                repay_amount.clone(),
+            );
+
+            env.events().publish(
+                (Symbol::new(env, "Account_Deleveraged"), smart_account.clone()),
+                DeleverageEvent {
+                    smart_account: smart_account.clone(),
+                    collateral_symbol,
+                    debt_symbol: debt_symbol.clone(),
+                    collateral_sold: amount_in,
+                    debt_repaid: repay_amount,
+                    timestamp: env.ledger().timestamp(),
+                },
+            );
+            deleveraged = true;
+        }
+
+        if !deleveraged {
+            return Err(AccountManagerError::NoSwappableCollateral);
+        }
+        Ok(())
+    }
+
+    /// Opens a Dutch auction for an unhealthy account's collateral. Anyone may start it;
+    /// the discount offered to bidders then grows every ledger up to the configured cap.
+    /// An auction left over from an earlier shortfall is restarted from zero discount.
+    pub fn start_liquidation_auction(
+        env: &Env,
+        smart_account: Address,
+    ) -> Result<(), AccountManagerError> {
+        let key = AccountManagerKey::LiquidationAuction(smart_account.clone());
+
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        if Self::is_account_healthy_now(env, &registry_client, &smart_account) {
+            return Err(AccountManagerError::AccountHealthy);
+        }
+
+        let auction = LiquidationAuction {
+            start_ledger: env.ledger().sequence(),
+        };
+        env.storage().persistent().set(&key, &auction);
+        Self::extend_ttl_account_manager(env, key);
+
+        env.events().publish(
+            (Symbol::new(env, "Liquidation_Auction_Started"), smart_account),
+            auction,
+        );
+        Ok(())
+    }
+
+    /// Repays `repay_amount_wad` of the account's `debt_symbol` lending pool debt with the
+    /// bidder's tokens and pays the bidder collateral worth the repaid value at oracle prices
+    /// plus the current auction discount. The auction ends once the account is healthy again.
+    pub fn bid_on_liquidation_auction(
+        env: &Env,
+        bidder: Address,
+        smart_account: Address,
+        debt_symbol: Symbol,
+        repay_amount_wad: U256,
+        collateral_symbol: Symbol,
+        min_collateral_out_wad: U256,
+    ) -> Result<U256, AccountManagerError> {
+        bidder.require_auth();
+
+        let discount_bps = Self::get_auction_discount_bps(env, smart_account.clone())?;
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        if Self::is_account_healthy_now(env, &registry_client, &smart_account) {
+            return Err(AccountManagerError::AccountHealthy);
+        }
+        let smart_account_client = smart_account_contract::Client::new(env, &smart_account);
+
+        if Self::is_blend_debt_symbol(env, &debt_symbol)
+            || !smart_account_client
+                .get_all_borrowed_tokens()
+                .contains(debt_symbol.clone())
+        {
+            return Err(AccountManagerError::BorrowedTokenNotFound);
+        }
+        // Tracking symbols stand for positions held elsewhere and cannot be paid out
+        if !Self::is_plain_asset(&registry_client, &collateral_symbol) {
+            return Err(AccountManagerError::UnsupportedToken);
+        }
+        let debt = smart_account_client.get_borrowed_token_debt(&debt_symbol);
+        let repay_amount = if repay_amount_wad < debt {
+            repay_amount_wad
+        } else {
+            debt
+        };
+
+        let debt_price = Self::get_oracle_price_wad(env, &registry_client, &debt_symbol);
+        let collateral_price = Self::get_oracle_price_wad(env, &registry_client, &collateral_symbol);
+        let collateral_out = repay_amount
+            .mul(&debt_price)
+            .mul(&U256::from_u32(env, BPS_DENOMINATOR))
+            .div(&collateral_price.mul(&U256::from_u32(env, BPS_DENOMINATOR - discount_bps)));
+        if collateral_out > smart_account_client.get_collateral_token_balance(&collateral_symbol) {
+            return Err(AccountManagerError::InsufficientCollateral);
+        }
+        if collateral_out < min_collateral_out_wad {
+            return Err(AccountManagerError::SlippageExceeded);
+        }
+
+        let debt_token = token::Client::new(
+            env,
+            &Self::token_contract_address(&registry_client, &debt_symbol),
+        );
+        let repay_u128 = Self::convert_u256_to_u128(env, &repay_amount);
+        debt_token.transfer(
+            &bidder,
+            &smart_account,
+            &Self::scale_for_operation(repay_u128, debt_token.decimals()),
+        );
+        Self::repay_internal(env, &smart_account, repay_amount.clone(), debt_symbol.clone());
+        smart_account_client.remove_collateral_token_balance(
+            &bidder,
+            &collateral_symbol,
+            &Self::convert_u256_to_u128(env, &collateral_out),
+        );
+        Self::sync_collateral_holder(env, &smart_account_client, &collateral_symbol);
+
+        env.events().publish(
+            (Symbol::new(env, "Liquidation_Auction_Bid"), smart_account.clone()),
+            AuctionBidEvent {
+                smart_account: smart_account.clone(),
+                bidder,
+                debt_symbol,
+                debt_repaid: repay_amount,
+                collateral_symbol,
+                collateral_out: collateral_out.clone(),
+                discount_bps,
+                timestamp: env.ledger().timestamp(),
             },
         );
-        Ok(true)
+
+        if Self::is_account_healthy_now(env, &registry_client, &smart_account) {
+            Self::end_auction(env, &smart_account);
+        }
+        Ok(collateral_out)
     }
 
-    fn extend_ttl_account_manager(env: &Env, key: AccountManagerKey) {
+    /// Closes an auction whose account has recovered, e.g. after the trader added collateral.
+    pub fn end_liquidation_auction(
+        env: &Env,
+        smart_account: Address,
+    ) -> Result<(), AccountManagerError> {
+        if Self::get_liquidation_auction(env, smart_account.clone()).is_none() {
+            return Err(AccountManagerError::AuctionNotActive);
+        }
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        if !Self::is_account_healthy_now(env, &registry_client, &smart_account) {
+            return Err(AccountManagerError::AccountUnhealthy);
+        }
+        Self::end_auction(env, &smart_account);
+        Ok(())
+    }
+
+    pub fn get_liquidation_auction(
+        env: &Env,
+        smart_account: Address,
+    ) -> Option<LiquidationAuction> {
         env.storage()
             .persistent()
-            .extend_ttl(&key, TLL_LEDGERS_YEAR, TLL_LEDGERS_10YEAR);
+            .get(&AccountManagerKey::LiquidationAuction(smart_account))
     }
 
-    pub fn set_max_asset_cap(env: &Env, cap: U256) {
+    pub fn get_auction_discount_bps(
+        env: &Env,
+        smart_account: Address,
+    ) -> Result<u32, AccountManagerError> {
+        let auction = Self::get_liquidation_auction(env, smart_account)
+            .ok_or(AccountManagerError::AuctionNotActive)?;
+        let config = Self::get_auction_config(env);
+        let elapsed = env.ledger().sequence().saturating_sub(auction.start_ledger);
+        Ok(elapsed
+            .saturating_mul(config.discount_per_ledger_bps)
+            .min(config.max_discount_bps))
+    }
+
+    pub fn set_auction_config(env: &Env, config: AuctionConfig) {
         let admin: Address = env
             .storage()
             .persistent()
             .get(&AccountManagerKey::Admin)
-            .unwrap_or_else(|| panic!("Failed to fetch admin address n1"));
+            .unwrap_or_else(|| panic!("Admin key not set!"));
         admin.require_auth();
 
-        let key = AccountManagerKey::AssetCap;
-        env.storage().persistent().set(&key, &cap);
+        if config.max_discount_bps >= BPS_DENOMINATOR {
+            panic_with_error!(env, AccountManagerError::InvalidParameter);
+        }
+        let key = AccountManagerKey::AuctionConfig;
+        env.storage().persistent().set(&key, &config);
         Self::extend_ttl_account_manager(env, key);
     }
 
-    pub fn get_max_asset_cap(env: &Env) -> U256 {
-        let key = AccountManagerKey::AssetCap;
+    pub fn get_auction_config(env: &Env) -> AuctionConfig {
         env.storage()
             .persistent()
-            .get(&key)
-            .unwrap_or_else(|| panic!("Asset cap not set"))
-    }
-
-    pub fn get_iscollateral_allowed(env: &Env, token_symbol: Symbol) -> bool {
-        let key = AccountManagerKey::IsCollateralAllowed(token_symbol);
-        env.storage().persistent().get(&key).unwrap_or(false)
+            .get(&AccountManagerKey::AuctionConfig)
+            .unwrap_or(AuctionConfig {
+                discount_per_ledger_bps: DEFAULT_AUCTION_DISCOUNT_PER_LEDGER_BPS,
+                max_discount_bps: DEFAULT_AUCTION_MAX_DISCOUNT_BPS,
+            })
     }
 
-    pub fn set_iscollateral_allowed(env: &Env, token_symbol: Symbol) {
+    pub fn set_deleverage_config(env: &Env, config: DeleverageConfig) {
         let admin: Address = env
             .storage()
             .persistent()
@@ -549,146 +855,2434 @@ impl AccountManagerContract {
             .unwrap_or_else(|| panic!("Admin key not set!"));
         admin.require_auth();
 
-        let key = AccountManagerKey::IsCollateralAllowed(token_symbol);
-        env.storage().persistent().set(&key, &true);
+        if config.share_bps == 0
+            || config.share_bps > BPS_DENOMINATOR
+            || config.max_slippage_bps >= BPS_DENOMINATOR
+        {
+            panic_with_error!(env, AccountManagerError::InvalidParameter);
+        }
+        let key = AccountManagerKey::DeleverageConfig;
+        env.storage().persistent().set(&key, &config);
+        Self::extend_ttl_account_manager(env, key);
     }
 
-    fn get_registry_address(env: &Env) -> Address {
+    pub fn get_deleverage_config(env: &Env) -> DeleverageConfig {
         env.storage()
             .persistent()
-            .get(&AccountManagerKey::RegistryContract)
-            .unwrap_or_else(|| panic!("Failed to fetch registry contract address n1"))
+            .get(&AccountManagerKey::DeleverageConfig)
+            .unwrap_or(DeleverageConfig {
+                share_bps: DEFAULT_DELEVERAGE_SHARE_BPS,
+                max_slippage_bps: DEFAULT_DELEVERAGE_MAX_SLIPPAGE_BPS,
+            })
     }
 
-    fn get_trader_address(env: &Env, smart_account: &Address) -> Address {
+    fn end_auction(env: &Env, smart_account: &Address) {
         env.storage()
             .persistent()
-            .get(&AccountManagerKey::TraderAddress(smart_account.clone()))
-            .expect("Failed to fetch Traders address")
+            .remove(&AccountManagerKey::LiquidationAuction(smart_account.clone()));
+        env.events().publish(
+            (Symbol::new(env, "Liquidation_Auction_Ended"), smart_account.clone()),
+            env.ledger().timestamp(),
+        );
+    }
+
+    fn is_account_healthy_now(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+    ) -> bool {
+        let risk_engine_address = registry_client.get_risk_engine_address();
+        let risk_engine_client = risk_engine_contract::Client::new(env, &risk_engine_address);
+        risk_engine_client.is_account_healthy(
+            &risk_engine_client.get_current_total_balance(smart_account),
+            &risk_engine_client.get_current_total_borrows(smart_account),
+        )
+    }
+
+    /// Brings everything the account holds in external protocols back into the account so
+    /// liquidation can seize it. Blend supply and collateral are withdrawn pool by pool and
+    /// liquidity is removed from every LP position; tracking tokens are burned and the net
+    /// token flows are booked against the account's collateral balances. Blend liabilities
+    /// are left for `repay_blend_liability`, which can then pay them from what came back.
+    fn unwind_external_positions(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        trader_address: &Address,
+        smart_account: &Address,
+    ) {
+        // Without a tracking token no external position can have been opened
+        if !registry_client.has_tracking_token_contract_addr() {
+            return;
+        }
+        let smart_account_client = smart_account_contract::Client::new(env, smart_account);
+        let tracking_client = tracking_token_contract::Client::new(
+            env,
+            &registry_client.get_tracking_token_contract_addr(),
+        );
+
+        // (pool, tracking symbol, underlying, kind) for every open Blend position
+        let mut blend_positions = Vec::new(env);
+        let mut blend_pools: Vec<Address> = Vec::new(env);
+        let mut held_symbols = smart_account_client.get_all_collateral_tokens();
+        held_symbols.append(&smart_account_client.get_all_borrowed_tokens());
+        for symbol in held_symbols.iter() {
+            let Some(info) = registry_client.get_blend_tracking_info(&symbol) else {
+                continue;
+            };
+            if info.kind == BlendPositionKind::Liability
+                || tracking_client.balance(smart_account, &symbol) <= 0
+            {
+                continue;
+            }
+            if !blend_pools.contains(&info.pool) {
+                blend_pools.push_back(info.pool.clone());
+            }
+            blend_positions.push_back((info.pool, symbol, info.underlying, info.kind));
+        }
+
+        for pool in blend_pools.iter() {
+            let mut requests = Vec::new(env);
+            let mut underlyings: Vec<Symbol> = Vec::new(env);
+            for (position_pool, _, underlying, kind) in blend_positions.iter() {
+                if position_pool == pool {
+                    let request_type: u32 = match kind {
+                        BlendPositionKind::Collateral => 3,
+                        _ => 1,
+                    };
+                    requests.push_back((underlying.clone(), request_type));
+                    if !underlyings.contains(&underlying) {
+                        underlyings.push_back(underlying);
+                    }
+                }
+            }
+
+            let balances_before =
+                Self::token_balances_wad(env, registry_client, &underlyings, smart_account);
+            smart_account_client.unwind_blend_positions(&pool, &requests);
+            Self::book_token_flows(
+                env,
+                registry_client,
+                &smart_account_client,
+                &underlyings,
+                &balances_before,
+            );
+
+            for (position_pool, symbol, _, _) in blend_positions.iter() {
+                if position_pool != pool {
+                    continue;
+                }
+                let balance = tracking_client.balance(smart_account, &symbol);
+                tracking_client.burn(&symbol, smart_account, &balance);
+            }
+        }
+
+        // (router, token pair) of every LP position the account may hold
+        let mut lp_positions = Vec::new(env);
+        if registry_client.has_aquarius_router_address() {
+            lp_positions.push_back((
+                registry_client.get_aquarius_router_address(),
+                soroban_sdk::vec![env, XLM_SYMBOL, USDC_SYMBOL],
+            ));
+        }
+        if registry_client.has_soroswap_router_address() {
+            let router = registry_client.get_soroswap_router_address();
+            for (token_a, token_b) in [
+                (XLM_SYMBOL, USDC_SYMBOL),
+                (XLM_SYMBOL, EURC_SYMBOL),
+                (USDC_SYMBOL, EURC_SYMBOL),
+            ] {
+                lp_positions.push_back((router.clone(), soroban_sdk::vec![env, token_a, token_b]));
+            }
+        }
+
+        for (router, tokens) in lp_positions.iter() {
+            let lp_symbol = Self::lp_tracking_symbol(env, registry_client, &router, &tokens).unwrap();
+            let liquidity = tracking_client.balance(smart_account, &lp_symbol);
+            if liquidity <= 0 {
+                continue;
+            }
+            let balances_before = Self::token_balances_wad(env, registry_client, &tokens, smart_account);
+            let amount = Self::lp_call_amount(env, registry_client, &router, &tokens, liquidity);
+            // Called directly so a later change to the whitelist cannot block liquidation
+            smart_account_client.execute(
+                &router,
+                &SmartAccExternalAction::RemoveLiquidity,
+                trader_address,
+                &tokens,
+                &soroban_sdk::vec![env, Self::convert_u256_to_u128(env, &amount)],
+                &Vec::new(env),
+            );
+            Self::book_token_flows(
+                env,
+                registry_client,
+                &smart_account_client,
+                &tokens,
+                &balances_before,
+            );
+            tracking_client.burn(&lp_symbol, smart_account, &liquidity);
+            Self::remove_leveraged_position(env, smart_account, &router);
+        }
+
+        // Adapter positions are withdrawn in full at the amounts the adapter values them at
+        let mut adapters: Vec<Address> = Vec::new(env);
+        for symbol in smart_account_client.get_all_collateral_tokens().iter() {
+            if !registry_client.has_tracking_symbol_adapter(&symbol)
+                || tracking_client.balance(smart_account, &symbol) <= 0
+            {
+                continue;
+            }
+            let adapter = registry_client.get_tracking_symbol_adapter(&symbol);
+            if !adapters.contains(&adapter) {
+                adapters.push_back(adapter);
+            }
+        }
+        for adapter in adapters.iter() {
+            let mut tokens = Vec::new(env);
+            let mut amounts = Vec::new(env);
+            for (symbol, amount_wad) in protocol_adapter_contract::Client::new(env, &adapter)
+                .value_position(smart_account)
+                .iter()
+            {
+                tokens.push_back(symbol);
+                amounts.push_back(Self::convert_u256_to_u128(env, &amount_wad));
+            }
+            if tokens.is_empty() {
+                continue;
+            }
+            let balances_before = Self::token_balances_wad(env, registry_client, &tokens, smart_account);
+            let deltas = smart_account_client.execute_via_adapter(
+                &adapter,
+                &SmartAccExternalAction::Withdraw,
+                &tokens,
+                &amounts,
+            );
+            Self::book_token_flows(
+                env,
+                registry_client,
+                &smart_account_client,
+                &tokens,
+                &balances_before,
+            );
+            Self::apply_tracking_deltas(
+                env,
+                registry_client,
+                &smart_account_client,
+                smart_account,
+                &deltas,
+            );
+        }
+    }
+
+    /// Repays the whole Blend liability behind `debt_symbol` from the account's balance,
+    /// burning its tracking tokens and booking the tokens spent against its collateral.
+    /// Other collateral is swapped into the borrowed asset first when the account holds
+    /// too little of it.
+    fn repay_blend_liability(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account_client: &smart_account_contract::Client,
+        debt_symbol: &Symbol,
+    ) {
+        let smart_account = &smart_account_client.address;
+        let info = registry_client.get_blend_tracking_info(debt_symbol).unwrap();
+        let liability_wad = smart_account_client.get_blend_liability(&info.pool, &info.underlying);
+        Self::cover_shortfall(
+            env,
+            registry_client,
+            smart_account_client,
+            &info.underlying,
+            liability_wad,
+        );
+        let underlyings = soroban_sdk::vec![env, info.underlying.clone()];
+        let balances_before =
+            Self::token_balances_wad(env, registry_client, &underlyings, smart_account);
+        smart_account_client.unwind_blend_positions(
+            &info.pool,
+            &soroban_sdk::vec![env, (info.underlying, 5u32)],
+        );
+        Self::book_token_flows(
+            env,
+            registry_client,
+            smart_account_client,
+            &underlyings,
+            &balances_before,
+        );
+
+        let tracking_client = tracking_token_contract::Client::new(
+            env,
+            &registry_client.get_tracking_token_contract_addr(),
+        );
+        let balance = tracking_client.balance(smart_account, debt_symbol);
+        if balance > 0 {
+            tracking_client.burn(debt_symbol, smart_account, &balance);
+        }
+        smart_account_client.remove_borrowed_token(debt_symbol);
+    }
+
+    /// Sells other plain collateral on the Aquarius (or else Soroswap) router until the
+    /// account holds `amount_wad` of `token_symbol` or runs out of collateral to sell.
+    fn cover_shortfall(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account_client: &smart_account_contract::Client,
+        token_symbol: &Symbol,
+        amount_wad: u128,
+    ) {
+        let smart_account = &smart_account_client.address;
+        let held_wad = Self::convert_u256_to_u128(
+            env,
+            &Self::token_balance_wad(env, registry_client, token_symbol, smart_account),
+        );
+        if held_wad >= amount_wad {
+            return;
+        }
+        let router = if registry_client.has_aquarius_router_address() {
+            registry_client.get_aquarius_router_address()
+        } else if registry_client.has_soroswap_router_address() {
+            registry_client.get_soroswap_router_address()
+        } else {
+            return;
+        };
+
+        let max_slippage_bps = Self::get_deleverage_config(env).max_slippage_bps;
+        let token_price = Self::get_oracle_price_wad(env, registry_client, token_symbol);
+        let mut shortfall = U256::from_u128(env, amount_wad - held_wad);
+        for collateral_symbol in smart_account_client.get_all_collateral_tokens().iter() {
+            if shortfall == U256::from_u128(env, 0) {
+                break;
+            }
+            if collateral_symbol == *token_symbol
+                || !Self::is_plain_asset(registry_client, &collateral_symbol)
+            {
+                continue;
+            }
+            let collateral_balance =
+                smart_account_client.get_collateral_token_balance(&collateral_symbol);
+            if collateral_balance == U256::from_u128(env, 0) {
+                continue;
+            }
+
+            // Sell enough to cover the shortfall even at the worst accepted price
+            let collateral_price = Self::get_oracle_price_wad(env, registry_client, &collateral_symbol);
+            let needed = shortfall
+                .mul(&token_price)
+                .mul(&U256::from_u32(env, BPS_DENOMINATOR))
+                .div(&collateral_price)
+                .div(&U256::from_u32(env, BPS_DENOMINATOR - max_slippage_bps));
+            let amount_in = if needed < collateral_balance {
+                needed
+            } else {
+                collateral_balance
+            };
+            let min_amount_out = amount_in
+                .mul(&collateral_price)
+                .mul(&U256::from_u32(env, BPS_DENOMINATOR - max_slippage_bps))
+                .div(&token_price)
+                .div(&U256::from_u32(env, BPS_DENOMINATOR));
+
+            let amount_out_wad = smart_account_client.swap_collateral(
+                &router,
+                &collateral_symbol,
+                token_symbol,
+                &Self::convert_u256_to_u128(env, &amount_in),
+                &Self::convert_u256_to_u128(env, &min_amount_out),
+            );
+            Self::sync_collateral_holder(env, smart_account_client, &collateral_symbol);
+            Self::sync_collateral_holder(env, smart_account_client, token_symbol);
+            let amount_out = U256::from_u128(env, amount_out_wad);
+            shortfall = if amount_out < shortfall {
+                shortfall.sub(&amount_out)
+            } else {
+                U256::from_u128(env, 0)
+            };
+        }
+    }
+
+    fn token_balances_wad(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        token_symbols: &Vec<Symbol>,
+        owner: &Address,
+    ) -> Vec<U256> {
+        let mut balances = Vec::new(env);
+        for token_symbol in token_symbols.iter() {
+            balances.push_back(Self::token_balance_wad(env, registry_client, &token_symbol, owner));
+        }
+        balances
+    }
+
+    /// Adds tokens that arrived in the account since `balances_before` to its collateral and
+    /// deducts tokens that left it, never going below zero.
+    fn book_token_flows(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account_client: &smart_account_contract::Client,
+        token_symbols: &Vec<Symbol>,
+        balances_before: &Vec<U256>,
+    ) {
+        for (i, token_symbol) in token_symbols.iter().enumerate() {
+            let before = balances_before.get_unchecked(i as u32);
+            let after = Self::token_balance_wad(
+                env,
+                registry_client,
+                &token_symbol,
+                &smart_account_client.address,
+            );
+            if after == before {
+                continue;
+            }
+            let collateral = smart_account_client.get_collateral_token_balance(&token_symbol);
+            let updated = if after > before {
+                smart_account_client.add_collateral_token(&token_symbol);
+                collateral.add(&after.sub(&before))
+            } else if collateral > before.sub(&after) {
+                collateral.sub(&before.sub(&after))
+            } else {
+                U256::from_u128(env, 0)
+            };
+            smart_account_client.set_collateral_token_balance(&token_symbol, &updated);
+            Self::sync_collateral_holder(env, smart_account_client, &token_symbol);
+        }
+    }
+
+    pub fn settle_account(env: Env, smart_account: Address) -> Result<bool, AccountManagerError> {
+        let trader_address = Self::get_trader_address(&env, &smart_account);
+        trader_address.require_auth();
+
+        let smart_account_contract_client =
+            smart_account_contract::Client::new(&env, &smart_account);
+        let registry_client = registry_contract::Client::new(&env, &Self::get_registry_address(&env));
+        let borrowed_tokens = smart_account_contract_client.get_all_borrowed_tokens();
+        for tokenx in borrowed_tokens.iter() {
+            if Self::is_blend_debt_symbol(&env, &tokenx) {
+                Self::repay_blend_liability(
+                    &env,
+                    &registry_client,
+                    &smart_account_contract_client,
+                    &tokenx,
+                );
+                continue;
+            }
+            let repay_all = U256::from_u128(&env, REPAY_ALL_WAD);
+            Self::repay(env.clone(), repay_all, tokenx, smart_account.clone())
+                .expect("Failed to repay while settling the account");
+        }
+        env.events().publish(
+            (
+                Symbol::new(&env, "Trader_SettleAccount_Event"),
+                smart_account.clone(),
+            ),
+            TraderSettleAccountEvent {
+                smart_account,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
+        Ok(true)
+    }
+
+    fn deposit_collateral_internal(
+        env: &Env,
+        trader_address: &Address,
+        smart_account: &Address,
+        token_symbol: Symbol,
+        token_amount_wad: U256,
+    ) {
+        if token_amount_wad.eq(&U256::from_u128(&env, 0)) {
+            panic_with_error!(env, AccountManagerError::ZeroAmount);
+        }
+
+        if !Self::get_iscollateral_allowed(&env, token_symbol.clone()) {
+            panic_with_error!(env, AccountManagerError::CollateralNotAllowed);
+        }
+
+        let smart_account_client = smart_account_contract::Client::new(&env, &smart_account);
+        let collateral_tokens = smart_account_client.get_all_collateral_tokens();
+
+        if U256::from_u32(&env, collateral_tokens.len()) >= Self::get_max_asset_cap(&env) {
+            panic_with_error!(env, AccountManagerError::AssetCapExceeded);
+        }
+
+        if !collateral_tokens.contains(token_symbol.clone()) {
+            smart_account_client.add_collateral_token(&token_symbol);
+        }
+
+        let amount_wad_u128 = Self::convert_u256_to_u128(&env, &token_amount_wad);
+        let registry_address = Self::get_registry_address(&env);
+        let registry_client = registry_contract::Client::new(&env, &registry_address);
+
+        // Transfer tokens based on type
+        if token_symbol == XLM_SYMBOL {
+            let token_client = token::Client::new(&env, &registry_client.get_xlm_contract_adddress());
+            let amount_scaled = Self::scale_for_operation(amount_wad_u128, token_client.decimals());
+            token_client.transfer(trader_address, smart_account, &amount_scaled);
+        } else if token_symbol == USDC_SYMBOL {
+            let token_client = token::Client::new(&env, &registry_client.get_usdc_contract_address());
+            let amount_scaled = Self::scale_for_operation(amount_wad_u128, token_client.decimals());
+            token_client.transfer(trader_address, smart_account, &amount_scaled);
+        } else if token_symbol == EURC_SYMBOL {
+            let token_client = token::Client::new(&env, &registry_client.get_eurc_contract_address());
+            let amount_scaled = Self::scale_for_operation(amount_wad_u128, token_client.decimals());
+            token_client.transfer(trader_address, smart_account, &amount_scaled);
+        } else if let Some(asset) = registry_client.get_collateral_asset(&token_symbol) {
+            let token_client = token::Client::new(&env, &asset.token_address);
+            let amount_scaled = Self::scale_for_operation(amount_wad_u128, token_client.decimals());
+            token_client.transfer(trader_address, smart_account, &amount_scaled);
+        } else {
+            panic_with_error!(env, AccountManagerError::CollateralNotAllowed);
+        }
+
+        // Update balance
+        let existing_bal = smart_account_client.get_collateral_token_balance(&token_symbol);
+        smart_account_client.set_collateral_token_balance(
+            &token_symbol, 
+            &existing_bal.add(&token_amount_wad)
+        );
+        Self::add_collateral_holder(env, smart_account, &token_symbol);
+        Self::record_collateral_flow(
+            env,
+            &registry_client,
+            smart_account,
+            &token_symbol,
+            &token_amount_wad,
+            true,
+        );
+    }
+
+    fn check_collateral_withdrawal(
+        env: &Env,
+        smart_account: &Address,
+        token_symbol: &Symbol,
+        token_amount_wad: &U256,
+    ) {
+        if token_amount_wad.eq(&U256::from_u128(&env, 0)) {
+            panic_with_error!(env, AccountManagerError::ZeroAmount);
+        }
+
+        let smart_account_client = smart_account_contract::Client::new(&env, &smart_account);
+        let collateral_tokens_list = smart_account_client.get_all_collateral_tokens();
+        if !collateral_tokens_list.contains(token_symbol.clone()) {
+            panic_with_error!(env, AccountManagerError::UserDoesntHaveCollateralToken);
+        }
+    }
+
+    fn withdraw_collateral_internal(
+        env: &Env,
+        trader_address: &Address,
+        smart_account: &Address,
+        token_symbol: Symbol,
+        token_amount_wad: U256,
+    ) {
+        let smart_account_client = smart_account_contract::Client::new(&env, &smart_account);
+        let amount_u128: u128 = Self::convert_u256_to_u128(&env, &token_amount_wad);
+
+        smart_account_client.remove_collateral_token_balance(
+            &trader_address,
+            &token_symbol,
+            &amount_u128,
+        );
+        Self::sync_collateral_holder(env, &smart_account_client, &token_symbol);
+
+        let registry_client = registry_contract::Client::new(env, &Self::get_registry_address(env));
+        Self::record_collateral_flow(
+            env,
+            &registry_client,
+            smart_account,
+            &token_symbol,
+            &token_amount_wad,
+            false,
+        );
+    }
+
+    fn borrow_internal(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+        borrow_amount_wad: U256,
+        token_symbol: Symbol,
+    ) {
+        Self::track_isolated_borrow(
+            env,
+            registry_client,
+            smart_account,
+            &token_symbol,
+            &borrow_amount_wad,
+        );
+        let smart_account_client = smart_account_contract::Client::new(&env, smart_account);
+
+        // Execute lending based on token type
+        if token_symbol == XLM_SYMBOL {
+            let pool_xlm = registry_client.get_lendingpool_xlm();
+            lending_protocol_xlm::Client::new(&env, &pool_xlm)
+                .lend_to(&smart_account, &borrow_amount_wad);
+            smart_account_client.add_borrowed_token(&XLM_SYMBOL);
+            smart_account_client.set_has_debt(&true);
+        } else if token_symbol == USDC_SYMBOL {
+            let pool_usdc = registry_client.get_lendingpool_usdc();
+            lending_protocol_usdc::Client::new(&env, &pool_usdc)
+                .lend_to(&smart_account, &borrow_amount_wad);
+            smart_account_client.add_borrowed_token(&USDC_SYMBOL);
+            smart_account_client.set_has_debt(&true);
+        } else if token_symbol == EURC_SYMBOL {
+            let pool_eurc = registry_client.get_lendingpool_eurc();
+            lending_protocol_eurc::Client::new(&env, &pool_eurc)
+                .lend_to(&smart_account, &borrow_amount_wad);
+            smart_account_client.add_borrowed_token(&EURC_SYMBOL);
+            smart_account_client.set_has_debt(&true);
+        } else {
+            panic_with_error!(env, AccountManagerError::LendingPoolNotFound);
+        }
+        Self::record_borrow(env, smart_account, &token_symbol, &borrow_amount_wad);
+
+        // Publish simplified event
+        env.events().publish(
+            (Symbol::new(&env, "Trader_Borrow"), smart_account.clone()),
+            token_symbol,
+        );
+    }
+
+    /// Repays up to `repay_amount_wad` of the account's lending pool debt, capped at the
+    /// interest-accrued debt so the excess never leaves the account. Returns the amount repaid.
+    fn repay_internal(
+        env: &Env,
+        smart_account: &Address,
+        repay_amount_wad: U256,
+        token_symbol: Symbol,
+    ) -> U256 {
+        if repay_amount_wad.eq(&U256::from_u128(&env, 0)) {
+            panic_with_error!(env, AccountManagerError::ZeroAmount);
+        }
+
+        let registry_address: Address = Self::get_registry_address(&env);
+        let registry_client = registry_contract::Client::new(&env, &registry_address);
+
+        let smart_account_client = smart_account_contract::Client::new(&env, smart_account);
+
+        let borrowed_tokens = smart_account_client.get_all_borrowed_tokens();
+
+        if !borrowed_tokens.contains(token_symbol.clone()) {
+            panic_with_error!(env, AccountManagerError::BorrowedTokenNotFound);
+        }
+
+        let accrued_wad = Self::accrued_debt(env, &registry_client, smart_account, &token_symbol);
+        let repaid_wad = Self::min_u256(repay_amount_wad, accrued_wad.clone());
+        if token_symbol == XLM_SYMBOL {
+            let pool_xlm_contract = registry_client.get_lendingpool_xlm();
+            let xlm_client = lending_protocol_xlm::Client::new(&env, &pool_xlm_contract);
+            let bool = xlm_client.collect_from(&repaid_wad, smart_account);
+            smart_account_client.remove_borrowed_token_balance(
+                &XLM_SYMBOL,
+                &Self::convert_u256_to_u128(env, &repaid_wad),
+            );
+            if bool {
+                smart_account_client.remove_borrowed_token(&XLM_SYMBOL);
+            }
+        } else if token_symbol == USDC_SYMBOL {
+            let pool_usdc_contract = registry_client.get_lendingpool_usdc();
+            let usdc_client = lending_protocol_usdc::Client::new(&env, &pool_usdc_contract);
+            let bool = usdc_client.collect_from(&repaid_wad, smart_account);
+            smart_account_client.remove_borrowed_token_balance(
+                &USDC_SYMBOL,
+                &Self::convert_u256_to_u128(env, &repaid_wad),
+            );
+            if bool {
+                smart_account_client.remove_borrowed_token(&USDC_SYMBOL);
+            }
+        } else if token_symbol == EURC_SYMBOL {
+            let pool_eurc_contract = registry_client.get_lendingpool_eurc();
+            let eurc_client = lending_protocol_eurc::Client::new(&env, &pool_eurc_contract);
+            let bool = eurc_client.collect_from(&repaid_wad, smart_account);
+            smart_account_client.remove_borrowed_token_balance(
+                &EURC_SYMBOL,
+                &Self::convert_u256_to_u128(env, &repaid_wad),
+            );
+            if bool {
+                smart_account_client.remove_borrowed_token(&EURC_SYMBOL);
+            }
+        } else {
+            panic_with_error!(env, AccountManagerError::LendingPoolNotFound);
+        }
+        Self::release_isolated_debt(
+            env,
+            &registry_client,
+            smart_account,
+            &token_symbol,
+            repaid_wad.clone(),
+        );
+        Self::record_repayment(env, smart_account, &token_symbol, &accrued_wad, &repaid_wad);
+
+        env.events().publish(
+            (
+                Symbol::new(&env, "Trader_Repay_Event"),
+                smart_account.clone(),
+            ),
+            TraderRepayEvent {
+                smart_account: smart_account.clone(),
+                token_amount: repaid_wad.clone(),
+                timestamp: env.ledger().timestamp(),
+                token_symbol,
+            },
+        );
+        repaid_wad
+    }
+
+    /// Isolated collateral the borrow would count against. Panics if the account holds
+    /// isolated collateral and the token is not isolation-borrowable or the ceiling would be
+    /// exceeded.
+    fn check_isolated_borrow(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+        token_symbol: &Symbol,
+        borrow_amount_wad: &U256,
+    ) -> Option<Symbol> {
+        let risk_engine_client =
+            risk_engine_contract::Client::new(env, &registry_client.get_risk_engine_address());
+        let isolated_asset = risk_engine_client.get_isolated_collateral(smart_account)?;
+
+        if !registry_client
+            .get_isolation_borrowable_assets()
+            .contains(token_symbol)
+        {
+            panic_with_error!(env, AccountManagerError::IsolationBorrowNotAllowed);
+        }
+        let ceiling_wad = registry_client
+            .get_isolation_debt_ceiling(&isolated_asset)
+            .unwrap_or(U256::from_u128(env, 0));
+        if registry_client
+            .get_isolated_debt(&isolated_asset)
+            .add(borrow_amount_wad)
+            > ceiling_wad
+        {
+            panic_with_error!(env, AccountManagerError::IsolatedDebtCeilingExceeded);
+        }
+        Some(isolated_asset)
+    }
+
+    /// Counts a borrow against the debt ceiling of the isolated collateral the account holds.
+    fn track_isolated_borrow(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+        token_symbol: &Symbol,
+        borrow_amount_wad: &U256,
+    ) {
+        let Some(isolated_asset) = Self::check_isolated_borrow(
+            env,
+            registry_client,
+            smart_account,
+            token_symbol,
+            borrow_amount_wad,
+        ) else {
+            return;
+        };
+        registry_client.increase_isolated_debt(&isolated_asset, borrow_amount_wad);
+
+        let mut entries = Self::get_account_isolated_debt(env, smart_account.clone());
+        let existing = entries.iter().position(|entry| {
+            entry.isolated_asset == isolated_asset && entry.borrowed_token == *token_symbol
+        });
+        match existing {
+            Some(index) => {
+                let mut entry = entries.get(index as u32).unwrap();
+                entry.amount_wad = entry.amount_wad.add(borrow_amount_wad);
+                entries.set(index as u32, entry);
+            }
+            None => entries.push_back(IsolatedDebtEntry {
+                isolated_asset,
+                borrowed_token: token_symbol.clone(),
+                amount_wad: borrow_amount_wad.clone(),
+            }),
+        }
+        Self::set_account_isolated_debt(env, smart_account, entries);
+    }
+
+    /// Frees `repaid_wad` of isolated debt taken in `token_symbol`, oldest borrows first.
+    fn release_isolated_debt(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+        token_symbol: &Symbol,
+        repaid_wad: U256,
+    ) {
+        let entries = Self::get_account_isolated_debt(env, smart_account.clone());
+        if entries.is_empty() {
+            return;
+        }
+
+        let zero = U256::from_u128(env, 0);
+        let mut remaining_wad = repaid_wad;
+        let mut kept: Vec<IsolatedDebtEntry> = Vec::new(env);
+        for mut entry in entries.iter() {
+            if entry.borrowed_token != *token_symbol || remaining_wad == zero {
+                kept.push_back(entry);
+                continue;
+            }
+            let released_wad = Self::min_u256(remaining_wad.clone(), entry.amount_wad.clone());
+            registry_client.decrease_isolated_debt(&entry.isolated_asset, &released_wad);
+            remaining_wad = remaining_wad.sub(&released_wad);
+            if released_wad < entry.amount_wad {
+                entry.amount_wad = entry.amount_wad.sub(&released_wad);
+                kept.push_back(entry);
+            }
+        }
+        Self::set_account_isolated_debt(env, smart_account, kept);
+    }
+
+    pub fn get_account_isolated_debt(env: &Env, smart_account: Address) -> Vec<IsolatedDebtEntry> {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::IsolatedDebt(smart_account))
+            .unwrap_or(Vec::new(env))
+    }
+
+    fn set_account_isolated_debt(env: &Env, smart_account: &Address, entries: Vec<IsolatedDebtEntry>) {
+        let key = AccountManagerKey::IsolatedDebt(smart_account.clone());
+        if entries.is_empty() {
+            env.storage().persistent().remove(&key);
+            return;
+        }
+        env.storage().persistent().set(&key, &entries);
+        Self::extend_ttl_account_manager(env, key);
+    }
+
+    /// Collateral and debt at current oracle prices alongside the account's cumulative
+    /// collateral transfers and lending pool flows.
+    pub fn get_account_report(env: &Env, smart_account: Address) -> AccountReport {
+        let registry_client = registry_contract::Client::new(env, &Self::get_registry_address(env));
+        let risk_engine_client =
+            risk_engine_contract::Client::new(env, &registry_client.get_risk_engine_address());
+        let wad = U256::from_u128(env, WAD_U128);
+        let zero = U256::from_u128(env, 0);
+
+        let mut collateral_value_wad = zero.clone();
+        let mut debt_value_wad = zero.clone();
+        for asset in risk_engine_client
+            .get_account_breakdown(&smart_account)
+            .assets
+            .iter()
+        {
+            if asset.is_debt {
+                debt_value_wad = debt_value_wad.add(&asset.value);
+            } else {
+                collateral_value_wad = collateral_value_wad.add(&asset.value);
+            }
+        }
+        let equity_wad = if collateral_value_wad > debt_value_wad {
+            collateral_value_wad.sub(&debt_value_wad)
+        } else {
+            zero.clone()
+        };
+        let leverage_wad = if equity_wad != zero {
+            collateral_value_wad.mul(&wad).div(&equity_wad)
+        } else if collateral_value_wad == zero {
+            zero.clone()
+        } else {
+            // All collateral is owed
+            U256::from_u128(env, u128::MAX)
+        };
+
+        let debts = Self::get_debt_history(env, smart_account.clone());
+        let mut interest_paid_value_wad = zero;
+        for history in debts.iter() {
+            let price_wad = Self::get_oracle_price_wad(env, &registry_client, &history.symbol);
+            interest_paid_value_wad =
+                interest_paid_value_wad.add(&history.interest_paid_wad.mul(&price_wad).div(&wad));
+        }
+
+        let collateral = Self::get_collateral_history(env, smart_account);
+        let net_deposits_wad = Self::convert_u256_to_i128(env, &collateral.deposited_value_wad)
+            - Self::convert_u256_to_i128(env, &collateral.withdrawn_value_wad);
+
+        AccountReport {
+            collateral_value_wad,
+            debt_value_wad,
+            equity_wad,
+            leverage_wad,
+            interest_paid_value_wad,
+            net_deposits_wad,
+            collateral,
+            debts,
+        }
+    }
+
+    pub fn get_debt_history(env: &Env, smart_account: Address) -> Vec<DebtHistory> {
+        let symbols: Vec<Symbol> = env
+            .storage()
+            .persistent()
+            .get(&AccountManagerKey::DebtHistorySymbols(smart_account.clone()))
+            .unwrap_or(Vec::new(env));
+        let mut histories = Vec::new(env);
+        for symbol in symbols.iter() {
+            histories.push_back(Self::debt_history(env, &smart_account, &symbol));
+        }
+        histories
+    }
+
+    pub fn get_collateral_history(env: &Env, smart_account: Address) -> CollateralHistory {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::CollateralHistory(smart_account))
+            .unwrap_or(CollateralHistory {
+                deposited_value_wad: U256::from_u128(env, 0),
+                withdrawn_value_wad: U256::from_u128(env, 0),
+            })
+    }
+
+    fn debt_history(env: &Env, smart_account: &Address, token_symbol: &Symbol) -> DebtHistory {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::DebtHistory(
+                smart_account.clone(),
+                token_symbol.clone(),
+            ))
+            .unwrap_or(DebtHistory {
+                symbol: token_symbol.clone(),
+                principal_borrowed_wad: U256::from_u128(env, 0),
+                principal_repaid_wad: U256::from_u128(env, 0),
+                interest_paid_wad: U256::from_u128(env, 0),
+            })
+    }
+
+    fn set_debt_history(env: &Env, smart_account: &Address, history: &DebtHistory) {
+        let symbols_key = AccountManagerKey::DebtHistorySymbols(smart_account.clone());
+        let mut symbols: Vec<Symbol> = env
+            .storage()
+            .persistent()
+            .get(&symbols_key)
+            .unwrap_or(Vec::new(env));
+        if !symbols.contains(&history.symbol) {
+            symbols.push_back(history.symbol.clone());
+            env.storage().persistent().set(&symbols_key, &symbols);
+            Self::extend_ttl_account_manager(env, symbols_key);
+        }
+
+        let key = AccountManagerKey::DebtHistory(smart_account.clone(), history.symbol.clone());
+        env.storage().persistent().set(&key, history);
+        Self::extend_ttl_account_manager(env, key);
+    }
+
+    fn record_borrow(env: &Env, smart_account: &Address, token_symbol: &Symbol, amount_wad: &U256) {
+        let mut history = Self::debt_history(env, smart_account, token_symbol);
+        history.principal_borrowed_wad = history.principal_borrowed_wad.add(amount_wad);
+        Self::set_debt_history(env, smart_account, &history);
+    }
+
+    /// Splits a repayment of `repaid_wad` against `accrued_wad` of debt into interest,
+    /// everything owed above the outstanding principal, and principal.
+    fn record_repayment(
+        env: &Env,
+        smart_account: &Address,
+        token_symbol: &Symbol,
+        accrued_wad: &U256,
+        repaid_wad: &U256,
+    ) {
+        let mut history = Self::debt_history(env, smart_account, token_symbol);
+        let zero = U256::from_u128(env, 0);
+        let outstanding_wad = if history.principal_borrowed_wad > history.principal_repaid_wad {
+            history.principal_borrowed_wad.sub(&history.principal_repaid_wad)
+        } else {
+            zero.clone()
+        };
+        let interest_due_wad = if *accrued_wad > outstanding_wad {
+            accrued_wad.sub(&outstanding_wad)
+        } else {
+            zero
+        };
+        let interest_wad = Self::min_u256(repaid_wad.clone(), interest_due_wad);
+        history.interest_paid_wad = history.interest_paid_wad.add(&interest_wad);
+        history.principal_repaid_wad = history.principal_repaid_wad.add(&repaid_wad.sub(&interest_wad));
+        Self::set_debt_history(env, smart_account, &history);
+    }
+
+    fn record_collateral_flow(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+        token_symbol: &Symbol,
+        amount_wad: &U256,
+        is_deposit: bool,
+    ) {
+        // Assets without a usable price are left out of the history rather than blocking
+        // the deposit or withdrawal itself
+        let risk_engine_client =
+            risk_engine_contract::Client::new(env, &registry_client.get_risk_engine_address());
+        let price_wad = match risk_engine_client.try_get_asset_price(token_symbol) {
+            Ok(Ok(price_wad)) if price_wad != U256::from_u128(env, 0) => price_wad,
+            _ => return,
+        };
+        let value_wad = amount_wad
+            .mul(&price_wad)
+            .div(&U256::from_u128(env, WAD_U128));
+        let mut history = Self::get_collateral_history(env, smart_account.clone());
+        if is_deposit {
+            history.deposited_value_wad = history.deposited_value_wad.add(&value_wad);
+        } else {
+            history.withdrawn_value_wad = history.withdrawn_value_wad.add(&value_wad);
+        }
+        let key = AccountManagerKey::CollateralHistory(smart_account.clone());
+        env.storage().persistent().set(&key, &history);
+        Self::extend_ttl_account_manager(env, key);
+    }
+
+    /// Lending pool debt of the account in `token_symbol` including interest up to now.
+    fn accrued_debt(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+        token_symbol: &Symbol,
+    ) -> U256 {
+        if *token_symbol == XLM_SYMBOL {
+            let xlm_client =
+                lending_protocol_xlm::Client::new(env, &registry_client.get_lendingpool_xlm());
+            xlm_client.update_state();
+            xlm_client.get_borrow_balance(smart_account)
+        } else if *token_symbol == USDC_SYMBOL {
+            let usdc_client =
+                lending_protocol_usdc::Client::new(env, &registry_client.get_lendingpool_usdc());
+            usdc_client.update_state();
+            usdc_client.get_borrow_balance(smart_account)
+        } else if *token_symbol == EURC_SYMBOL {
+            let eurc_client =
+                lending_protocol_eurc::Client::new(env, &registry_client.get_lendingpool_eurc());
+            eurc_client.update_state();
+            eurc_client.get_borrow_balance(smart_account)
+        } else {
+            panic_with_error!(env, AccountManagerError::LendingPoolNotFound);
+        }
+    }
+
+    fn min_u256(a: U256, b: U256) -> U256 {
+        if a < b { a } else { b }
+    }
+
+    fn extend_ttl_account_manager(env: &Env, key: AccountManagerKey) {
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, TLL_LEDGERS_YEAR, TLL_LEDGERS_10YEAR);
+    }
+
+    pub fn set_max_asset_cap(env: &Env, cap: U256) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&AccountManagerKey::Admin)
+            .unwrap_or_else(|| panic!("Failed to fetch admin address n1"));
+        admin.require_auth();
+
+        let key = AccountManagerKey::AssetCap;
+        env.storage().persistent().set(&key, &cap);
+        Self::extend_ttl_account_manager(env, key);
+    }
+
+    pub fn get_max_asset_cap(env: &Env) -> U256 {
+        let key = AccountManagerKey::AssetCap;
+        env.storage()
+            .persistent()
+            .get(&key)
+            .unwrap_or_else(|| panic!("Asset cap not set"))
+    }
+
+    pub fn get_iscollateral_allowed(env: &Env, token_symbol: Symbol) -> bool {
+        let key = AccountManagerKey::IsCollateralAllowed(token_symbol);
+        env.storage().persistent().get(&key).unwrap_or(false)
+    }
+
+    pub fn set_iscollateral_allowed(env: &Env, token_symbol: Symbol) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&AccountManagerKey::Admin)
+            .unwrap_or_else(|| panic!("Admin key not set!"));
+        admin.require_auth();
+
+        let key = AccountManagerKey::IsCollateralAllowed(token_symbol);
+        env.storage().persistent().set(&key, &true);
+    }
+
+    /// Stops new deposits of `token_symbol` as collateral. Accounts already holding it keep
+    /// it and can still withdraw; wind its value down with the risk engine's collateral
+    /// factor ramp.
+    pub fn set_iscollateral_disallowed(env: &Env, token_symbol: Symbol) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&AccountManagerKey::Admin)
+            .unwrap_or_else(|| panic!("Admin key not set!"));
+        admin.require_auth();
+
+        let key = AccountManagerKey::IsCollateralAllowed(token_symbol);
+        env.storage().persistent().set(&key, &false);
+    }
+
+    /// Smart accounts holding a non-zero collateral balance of `token_symbol`.
+    pub fn get_collateral_holders(env: &Env, token_symbol: Symbol) -> Vec<Address> {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::CollateralHolders(token_symbol))
+            .unwrap_or(Vec::new(env))
+    }
+
+    fn add_collateral_holder(env: &Env, smart_account: &Address, token_symbol: &Symbol) {
+        let mut holders = Self::get_collateral_holders(env, token_symbol.clone());
+        if !holders.contains(smart_account) {
+            holders.push_back(smart_account.clone());
+            let key = AccountManagerKey::CollateralHolders(token_symbol.clone());
+            env.storage().persistent().set(&key, &holders);
+            Self::extend_ttl_account_manager(env, key);
+        }
+    }
+
+    /// Lists the account among the holders of `token_symbol` while it has a balance and
+    /// drops it once the balance is spent.
+    fn sync_collateral_holder(
+        env: &Env,
+        smart_account_client: &smart_account_contract::Client,
+        token_symbol: &Symbol,
+    ) {
+        if smart_account_client.get_collateral_token_balance(token_symbol) > U256::from_u128(env, 0) {
+            Self::add_collateral_holder(env, &smart_account_client.address, token_symbol);
+            return;
+        }
+        let mut holders = Self::get_collateral_holders(env, token_symbol.clone());
+        if let Some(index) = holders.first_index_of(&smart_account_client.address) {
+            holders.remove(index);
+            let key = AccountManagerKey::CollateralHolders(token_symbol.clone());
+            env.storage().persistent().set(&key, &holders);
+            Self::extend_ttl_account_manager(env, key);
+        }
+    }
+
+    fn get_registry_address(env: &Env) -> Address {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::RegistryContract)
+            .unwrap_or_else(|| panic!("Failed to fetch registry contract address n1"))
+    }
+
+    fn get_trader_address(env: &Env, smart_account: &Address) -> Address {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::TraderAddress(smart_account.clone()))
+            .expect("Failed to fetch Traders address")
+    }
+
+    pub fn generate_salt(
+        env: &Env,
+        trader_address: Address,
+        account_manager: Address,
+        smart_account_num: u32,
+    ) -> BytesN<32> {
+        // Convert addresses to XDR for consistent serialization
+        // Make sure empty addresses are not sent
+        assert!(trader_address.to_string().len() != 0);
+        assert!(account_manager.to_string().len() != 0);
+
+        let trader_xdr = trader_address.to_xdr(env);
+        let manager_xdr = account_manager.to_xdr(env);
+        let num_xdr = smart_account_num.to_le_bytes();
+
+        // Create a combined buffer to hash both addresses together
+        let mut combined = Bytes::new(env);
+
+        // Append trader address bytes
+        for i in 0..trader_xdr.len() {
+            combined.push_back(trader_xdr.get(i).unwrap());
+        }
+
+        // Append account manager bytes
+        for i in 0..manager_xdr.len() {
+            combined.push_back(manager_xdr.get(i).unwrap());
+        }
+
+        for i in 0..num_xdr.len() {
+            combined.push_back(*num_xdr.get(i).unwrap());
+        }
+
+        // Use Soroban's built-in SHA256 hash function
+        // This ensures a unique 32-byte output for any unique input combination
+        env.crypto().sha256(&combined).into()
+    }
+
+    pub fn get_inactive_accounts(env: &Env, trader_address: Address) -> Vec<Address> {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::InactiveAccountOf(trader_address))
+            .unwrap_or(Vec::new(env))
+    }
+
+    fn set_smart_accounts(env: &Env, trader_address: &Address, smart_accounts: Vec<Address>) {
+        let key = AccountManagerKey::SmartAccounts(trader_address.clone());
+        env.storage().persistent().set(&key, &smart_accounts);
+        Self::extend_ttl_account_manager(env, key);
+    }
+
+    fn set_inactive_accounts(env: &Env, trader_address: Address, inactive_accounts: Vec<Address>) {
+        let keyx = AccountManagerKey::InactiveAccountOf(trader_address);
+        env.storage().persistent().set(&keyx, &inactive_accounts);
+        Self::extend_ttl_account_manager(env, keyx);
+    }
+
+    fn convert_u256_to_u128(env: &Env, x: &U256) -> u128 {
+        x.to_u128()
+            .unwrap_or_else(|| panic_with_error!(&env, AccountManagerError::IntegerConversionError))
+    }
+
+    fn convert_u256_to_i128(env: &Env, x: &U256) -> i128 {
+        i128::try_from(Self::convert_u256_to_u128(env, x))
+            .unwrap_or_else(|_| panic_with_error!(&env, AccountManagerError::IntegerConversionError))
+    }
+
+    fn create_smart_account(
+        env: &Env,
+        trader_address: &Address,
+        smart_account_hash: BytesN<32>,
+    ) -> Address {
+        let mut trader_smart_accounts = env
+            .storage()
+            .persistent()
+            .get(&AccountManagerKey::SmartAccounts(trader_address.clone()))
+            .unwrap_or(Vec::new(env));
+
+        let salt = Self::generate_salt(
+            &env,
+            trader_address.clone(),
+            env.current_contract_address(),
+            trader_smart_accounts.len(),
+        );
+
+        let mut constructor_args = Vec::new(&env);
+        constructor_args.push_back(env.current_contract_address().to_val());
+        constructor_args.push_back(Self::get_registry_address(env).to_val());
+        constructor_args.push_back(trader_address.to_val());
+
+        let smart_account = env
+            .deployer()
+            .with_address(env.current_contract_address(), salt)
+            .deploy_v2(smart_account_hash, constructor_args);
+
+        trader_smart_accounts.push_back(smart_account.clone());
+
+        env.storage().persistent().set(
+            &AccountManagerKey::SmartAccounts(trader_address.clone()),
+            &trader_smart_accounts,
+        );
+
+        env.storage().persistent().set(
+            &AccountManagerKey::TraderAddress(smart_account.clone()),
+            &trader_address,
+        );
+
+        env.events().publish(
+            (Symbol::new(&env, "Smart_account_creation"), trader_address),
+            AccountCreationEvent {
+                smart_account: smart_account.clone(),
+                creation_time: env.ledger().timestamp(),
+            },
+        );
+        smart_account
+    }
+
+    fn scale_for_operation(amount_wad: u128, xlm_decimals: u32) -> i128 {
+        ((amount_wad * 10u128.pow(xlm_decimals)) / WAD_U128) as i128
+    }
+    /// To be implemented
+    pub fn approve() {}
+
+    pub fn execute(env_x: &Env, smart_account: Address, extern_proto_call_bytes: Bytes) {
+        let trader_address = Self::get_trader_address(&env_x, &smart_account);
+        trader_address.require_auth();
+
+        let call: ExternalProtocolCall =
+            ExternalProtocolCall::from_xdr(env_x, &extern_proto_call_bytes)
+                .expect("deserialize failed");
+
+        let protocol_address = call.protocol_address.clone();
+        Self::execute_internal(env_x, trader_address, smart_account.clone(), call);
+
+        let registry_client =
+            registry_contract::Client::new(env_x, &Self::get_registry_address(env_x));
+        Self::assert_execute_allowed(env_x, &registry_client, &smart_account, &protocol_address);
+    }
+
+    /// Executes a list of XDR encoded `BatchCall`s atomically. Individual steps skip their
+    /// own risk checks; the account must be healthy once the whole batch has run.
+    pub fn execute_batch(
+        env: &Env,
+        smart_account: Address,
+        batch_call_bytes: Vec<Bytes>,
+    ) -> Result<(), AccountManagerError> {
+        let trader_address = Self::get_trader_address(env, &smart_account);
+        trader_address.require_auth();
+
+        Self::run_batch(env, &trader_address, &smart_account, &batch_call_bytes, None)?;
+
+        env.events().publish(
+            (Symbol::new(env, "Trader_Batch_Execute"), smart_account.clone()),
+            batch_call_bytes.len(),
+        );
+
+        Ok(())
+    }
+
+    /// Lets an operator run a batch on behalf of the trader. Every step must be covered
+    /// by the operator's unexpired grant; collateral deposits are paid by the operator
+    /// and withdrawals always go to the trader. This is the only entry point that honours
+    /// operator grants.
+    pub fn execute_as_operator(
+        env: &Env,
+        operator: Address,
+        smart_account: Address,
+        batch_call_bytes: Vec<Bytes>,
+    ) -> Result<(), AccountManagerError> {
+        operator.require_auth();
+
+        let grant = Self::get_operator_grant(env, smart_account.clone(), operator.clone())
+            .filter(|grant| grant.expiry_ledger >= env.ledger().sequence())
+            .ok_or(AccountManagerError::OperatorNotAuthorized)?;
+
+        Self::run_batch(env, &operator, &smart_account, &batch_call_bytes, Some(&grant))?;
+
+        env.events().publish(
+            (Symbol::new(env, "Operator_Batch_Execute"), smart_account.clone()),
+            (operator, batch_call_bytes.len()),
+        );
+
+        Ok(())
+    }
+
+    /// Grants (or replaces) an operator's permissions on a smart account until `expiry_ledger`.
+    /// The grant is used by `execute_as_operator` only.
+    pub fn grant_operator(
+        env: &Env,
+        smart_account: Address,
+        operator: Address,
+        permissions: Vec<OperatorPermission>,
+        expiry_ledger: u32,
+    ) -> Result<(), AccountManagerError> {
+        let trader_address = Self::get_trader_address(env, &smart_account);
+        trader_address.require_auth();
+
+        if expiry_ledger < env.ledger().sequence() {
+            return Err(AccountManagerError::InvalidExpiry);
+        }
+
+        let key = AccountManagerKey::Operator(smart_account.clone(), operator.clone());
+        env.storage().persistent().set(
+            &key,
+            &OperatorGrant {
+                permissions: permissions.clone(),
+                expiry_ledger,
+            },
+        );
+        Self::extend_ttl_account_manager(env, key);
+
+        let mut operators = Self::get_operators(env, smart_account.clone());
+        if !operators.contains(&operator) {
+            operators.push_back(operator.clone());
+            Self::set_operators(env, &smart_account, operators);
+        }
+
+        env.events().publish(
+            (Symbol::new(env, "Operator_Granted"), smart_account.clone()),
+            OperatorGrantEvent {
+                smart_account,
+                operator,
+                permissions,
+                expiry_ledger,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn revoke_operator(
+        env: &Env,
+        smart_account: Address,
+        operator: Address,
+    ) -> Result<(), AccountManagerError> {
+        let trader_address = Self::get_trader_address(env, &smart_account);
+        trader_address.require_auth();
+
+        let mut operators = Self::get_operators(env, smart_account.clone());
+        let index = operators
+            .first_index_of(&operator)
+            .ok_or(AccountManagerError::OperatorNotFound)?;
+        operators.remove(index);
+        Self::set_operators(env, &smart_account, operators);
+        env.storage()
+            .persistent()
+            .remove(&AccountManagerKey::Operator(smart_account.clone(), operator.clone()));
+
+        env.events().publish(
+            (Symbol::new(env, "Operator_Revoked"), smart_account.clone()),
+            OperatorRevokeEvent {
+                smart_account,
+                operator,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn get_operator_grant(
+        env: &Env,
+        smart_account: Address,
+        operator: Address,
+    ) -> Option<OperatorGrant> {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::Operator(smart_account, operator))
+    }
+
+    pub fn get_operators(env: &Env, smart_account: Address) -> Vec<Address> {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::OperatorsList(smart_account))
+            .unwrap_or(Vec::new(env))
+    }
+
+    fn set_operators(env: &Env, smart_account: &Address, operators: Vec<Address>) {
+        let key = AccountManagerKey::OperatorsList(smart_account.clone());
+        env.storage().persistent().set(&key, &operators);
+        Self::extend_ttl_account_manager(env, key);
+    }
+
+    /// Runs each decoded step and checks account health once at the end. `payer` funds
+    /// collateral deposits; when a `grant` is given every step must be permitted by it.
+    fn run_batch(
+        env: &Env,
+        payer: &Address,
+        smart_account: &Address,
+        batch_call_bytes: &Vec<Bytes>,
+        grant: Option<&OperatorGrant>,
+    ) -> Result<(), AccountManagerError> {
+        if batch_call_bytes.is_empty() {
+            return Err(AccountManagerError::EmptyBatch);
+        }
+
+        let trader_address = Self::get_trader_address(env, smart_account);
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        let mut called_protocols: Vec<Address> = Vec::new(env);
+
+        for call_bytes in batch_call_bytes.iter() {
+            let call: BatchCall = BatchCall::from_xdr(env, &call_bytes)
+                .map_err(|_| AccountManagerError::InvalidBatchCall)?;
+
+            if let Some(grant) = grant {
+                let required = match &call {
+                    BatchCall::DepositCollateral(..) => OperatorPermission::DepositCollateral,
+                    BatchCall::WithdrawCollateral(..) => OperatorPermission::WithdrawCollateral,
+                    BatchCall::Borrow(..) => OperatorPermission::Borrow,
+                    BatchCall::Repay(..) => OperatorPermission::Repay,
+                    BatchCall::External(external_call) => {
+                        OperatorPermission::Execute(external_call.type_action.clone())
+                    }
+                };
+                if !grant.permissions.contains(&required) {
+                    return Err(AccountManagerError::OperatorNotAuthorized);
+                }
+            }
+
+            match call {
+                BatchCall::DepositCollateral(token_symbol, amount_wad) => {
+                    Self::deposit_collateral_internal(
+                        env,
+                        payer,
+                        smart_account,
+                        token_symbol,
+                        amount_wad,
+                    );
+                }
+                BatchCall::WithdrawCollateral(token_symbol, amount_wad) => {
+                    Self::check_collateral_withdrawal(env, smart_account, &token_symbol, &amount_wad);
+                    Self::withdraw_collateral_internal(
+                        env,
+                        &trader_address,
+                        smart_account,
+                        token_symbol,
+                        amount_wad,
+                    );
+                }
+                BatchCall::Borrow(token_symbol, amount_wad) => {
+                    if amount_wad.eq(&U256::from_u128(env, 0)) {
+                        return Err(AccountManagerError::ZeroAmount);
+                    }
+                    Self::check_borrow_allowed(
+                        env,
+                        &registry_client,
+                        smart_account,
+                        &token_symbol,
+                        &amount_wad,
+                    )?;
+                    Self::borrow_internal(
+                        env,
+                        &registry_client,
+                        smart_account,
+                        amount_wad,
+                        token_symbol,
+                    );
+                }
+                BatchCall::Repay(token_symbol, amount_wad) => {
+                    Self::repay_internal(env, smart_account, amount_wad, token_symbol);
+                }
+                BatchCall::External(external_call) => {
+                    if !called_protocols.contains(&external_call.protocol_address) {
+                        called_protocols.push_back(external_call.protocol_address.clone());
+                    }
+                    Self::execute_internal(
+                        env,
+                        trader_address.clone(),
+                        smart_account.clone(),
+                        external_call,
+                    );
+                }
+            }
+        }
+
+        for protocol in called_protocols.iter() {
+            Self::assert_execute_allowed(env, &registry_client, smart_account, &protocol);
+        }
+        Self::assert_account_healthy(env, &registry_client, smart_account);
+        Ok(())
+    }
+
+    /// Deposits `collateral_amount_wad` of `collateral_symbol`, borrows `borrow_symbol` so that
+    /// the position is worth `target_leverage_wad` times the collateral (priced via the oracle),
+    /// and adds both legs as liquidity at `venue`. Reverts if fewer than `min_liquidity_out`
+    /// LP units are received or the account ends up unhealthy.
+    pub fn open_leveraged_position(
+        env: &Env,
+        smart_account: Address,
+        venue: Address,
+        request: OpenPositionRequest,
+    ) -> Result<i128, AccountManagerError> {
+        let trader_address = Self::get_trader_address(env, &smart_account);
+        trader_address.require_auth();
+
+        let OpenPositionRequest {
+            collateral_symbol,
+            collateral_amount_wad,
+            borrow_symbol,
+            target_leverage_wad,
+            min_liquidity_out,
+        } = request;
+        if collateral_symbol == borrow_symbol {
+            return Err(AccountManagerError::InvalidTokenPair);
+        }
+        if target_leverage_wad <= U256::from_u128(env, WAD_U128) {
+            return Err(AccountManagerError::InvalidLeverage);
+        }
+        if min_liquidity_out < 0 {
+            return Err(AccountManagerError::InvalidParameter);
+        }
+
+        let position_key = AccountManagerKey::LeveragedPosition(smart_account.clone(), venue.clone());
+        let existing: Option<LeveragedPosition> = env.storage().persistent().get(&position_key);
+        if let Some(position) = &existing
+            && (position.collateral_symbol != collateral_symbol
+                || position.borrow_symbol != borrow_symbol)
+        {
+            return Err(AccountManagerError::PositionPairMismatch);
+        }
+
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+
+        Self::deposit_collateral_internal(
+            env,
+            &trader_address,
+            &smart_account,
+            collateral_symbol.clone(),
+            collateral_amount_wad.clone(),
+        );
+
+        // borrow_value = collateral_value * (leverage - 1)
+        let wad = U256::from_u128(env, WAD_U128);
+        let collateral_price_wad = Self::get_oracle_price_wad(env, &registry_client, &collateral_symbol);
+        let borrow_price_wad = Self::get_oracle_price_wad(env, &registry_client, &borrow_symbol);
+        let collateral_value_wad = collateral_amount_wad.mul(&collateral_price_wad).div(&wad);
+        let borrow_value_wad = collateral_value_wad
+            .mul(&target_leverage_wad.sub(&wad))
+            .div(&wad);
+        let borrow_amount_wad = borrow_value_wad.mul(&wad).div(&borrow_price_wad);
+
+        if borrow_amount_wad.eq(&U256::from_u128(env, 0)) {
+            return Err(AccountManagerError::ZeroAmount);
+        }
+        Self::check_borrow_allowed(
+            env,
+            &registry_client,
+            &smart_account,
+            &borrow_symbol,
+            &borrow_amount_wad,
+        )?;
+
+        Self::borrow_internal(
+            env,
+            &registry_client,
+            &smart_account,
+            borrow_amount_wad.clone(),
+            borrow_symbol.clone(),
+        );
+
+        let add_liquidity_call = ExternalProtocolCall {
+            protocol_address: venue.clone(),
+            type_action: SmartAccExternalAction::AddLiquidity,
+            tokens_out: soroban_sdk::vec![env, collateral_symbol.clone(), borrow_symbol.clone()],
+            tokens_in: Vec::new(env),
+            amount_out: soroban_sdk::vec![
+                env,
+                collateral_amount_wad.clone(),
+                borrow_amount_wad.clone()
+            ],
+            amount_in: Vec::new(env),
+            is_token_pair: true,
+            token_pair_ratio: 0,
+            margin_account: smart_account.clone(),
+            fee_fraction: 0,
+            min_liquidity_out: U256::from_u128(env, 0),
+            min_amounts_out: Vec::new(env),
+        };
+        let liquidity = Self::execute_internal(
+            env,
+            trader_address.clone(),
+            smart_account.clone(),
+            add_liquidity_call,
+        );
+
+        if liquidity < min_liquidity_out {
+            panic_with_error!(env, AccountManagerError::SlippageExceeded);
+        }
+        Self::assert_execute_allowed(env, &registry_client, &smart_account, &venue);
+        Self::assert_account_healthy(env, &registry_client, &smart_account);
+
+        let total_liquidity = existing.map(|p| p.liquidity).unwrap_or(0) + liquidity;
+        env.storage().persistent().set(
+            &position_key,
+            &LeveragedPosition {
+                collateral_symbol,
+                borrow_symbol,
+                liquidity: total_liquidity,
+            },
+        );
+        Self::extend_ttl_account_manager(env, position_key);
+
+        let venues_key = AccountManagerKey::LeveragedVenues(smart_account.clone());
+        let mut venues = Self::get_leveraged_venues(env, &smart_account);
+        if !venues.contains(&venue) {
+            venues.push_back(venue.clone());
+            env.storage().persistent().set(&venues_key, &venues);
+            Self::extend_ttl_account_manager(env, venues_key);
+        }
+
+        env.events().publish(
+            (Symbol::new(env, "Leveraged_Position_Opened"), smart_account.clone()),
+            LeveragedPositionEvent {
+                smart_account,
+                venue,
+                collateral_amount: collateral_amount_wad,
+                borrow_amount: borrow_amount_wad,
+                liquidity,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
+
+        Ok(liquidity)
+    }
+
+    /// Removes the liquidity opened through `open_leveraged_position` at `venue` and repays the
+    /// borrowed leg in full. `min_amounts_out_wad` bounds the (collateral, borrow) tokens that
+    /// must come back from the venue.
+    pub fn close_position(
+        env: &Env,
+        smart_account: Address,
+        venue: Address,
+        min_amounts_out_wad: Vec<U256>,
+    ) -> Result<(), AccountManagerError> {
+        let trader_address = Self::get_trader_address(env, &smart_account);
+        trader_address.require_auth();
+
+        let position_key = AccountManagerKey::LeveragedPosition(smart_account.clone(), venue.clone());
+        let position: LeveragedPosition = env
+            .storage()
+            .persistent()
+            .get(&position_key)
+            .unwrap_or_else(|| panic_with_error!(env, AccountManagerError::PositionNotFound));
+
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        let tokens = soroban_sdk::vec![
+            env,
+            position.collateral_symbol.clone(),
+            position.borrow_symbol.clone()
+        ];
+
+        // LP units may have left the account since opening (manual removals, unwinds), so
+        // never remove more than it still tracks
+        let liquidity = match Self::lp_tracking_symbol(env, &registry_client, &venue, &tokens) {
+            Some(lp_symbol) => tracking_token_contract::Client::new(
+                env,
+                &registry_client.get_tracking_token_contract_addr(),
+            )
+            .balance(&smart_account, &lp_symbol)
+            .min(position.liquidity),
+            None => position.liquidity,
+        };
+
+        let mut balances_before = Vec::new(env);
+        for token_symbol in tokens.iter() {
+            balances_before.push_back(Self::token_balance_wad(
+                env,
+                &registry_client,
+                &token_symbol,
+                &smart_account,
+            ));
+        }
+
+        if liquidity > 0 {
+            let remove_liquidity_call = ExternalProtocolCall {
+                protocol_address: venue.clone(),
+                type_action: SmartAccExternalAction::RemoveLiquidity,
+                tokens_out: tokens.clone(),
+                tokens_in: Vec::new(env),
+                amount_out: soroban_sdk::vec![
+                    env,
+                    Self::lp_call_amount(env, &registry_client, &venue, &tokens, liquidity)
+                ],
+                amount_in: Vec::new(env),
+                is_token_pair: true,
+                token_pair_ratio: 0,
+                margin_account: smart_account.clone(),
+                fee_fraction: 0,
+                min_liquidity_out: U256::from_u128(env, 0),
+                min_amounts_out: Vec::new(env),
+            };
+            Self::execute_internal(
+                env,
+                trader_address,
+                smart_account.clone(),
+                remove_liquidity_call,
+            );
+        }
+
+        for (i, token_symbol) in tokens.iter().enumerate() {
+            let min_out = min_amounts_out_wad
+                .get(i as u32)
+                .unwrap_or(U256::from_u128(env, 0));
+            let received = Self::token_balance_wad(env, &registry_client, &token_symbol, &smart_account)
+                .sub(&balances_before.get_unchecked(i as u32));
+            if received < min_out {
+                panic_with_error!(env, AccountManagerError::SlippageExceeded);
+            }
+        }
+
+        let smart_account_client = smart_account_contract::Client::new(env, &smart_account);
+        if smart_account_client
+            .get_all_borrowed_tokens()
+            .contains(position.borrow_symbol.clone())
+        {
+            Self::repay_internal(
+                env,
+                &smart_account,
+                U256::from_u128(env, REPAY_ALL_WAD),
+                position.borrow_symbol.clone(),
+            );
+        }
+
+        Self::remove_leveraged_position(env, &smart_account, &venue);
+
+        env.events().publish(
+            (Symbol::new(env, "Leveraged_Position_Closed"), smart_account.clone()),
+            venue,
+        );
+
+        Ok(())
+    }
+
+    /// Claims the emissions earned by the account's positions on `protocol` (currently
+    /// the Blend pool). Rewards are paid to the account owner unless `sell_to` is given, in
+    /// which case they are sold on Aquarius and the proceeds are credited as collateral.
+    pub fn claim_external_rewards(
+        env: &Env,
+        smart_account: Address,
+        protocol: Address,
+        sell_to: Option<Symbol>,
+        min_amount_out_wad: U256,
+    ) -> Result<i128, AccountManagerError> {
+        let trader_address = Self::get_trader_address(env, &smart_account);
+        trader_address.require_auth();
+
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+
+        if !Self::is_blend_venue(&registry_client, &protocol) {
+            return Err(AccountManagerError::UnsupportedProtocol);
+        }
+
+        let smart_account_client = smart_account_contract::Client::new(env, &smart_account);
+        let reward_token = registry_client.get_blend_reward_token_address();
+        if let Some(token_symbol) = &sell_to
+            && !Self::get_iscollateral_allowed(env, token_symbol.clone())
+        {
+            return Err(AccountManagerError::CollateralNotAllowed);
+        }
+        // Unsold rewards are not collateral, so they go straight to the owner
+        let recipient = if sell_to.is_some() {
+            smart_account.clone()
+        } else {
+            trader_address.clone()
+        };
+        let amount_claimed = smart_account_client.claim_blend_emissions(&protocol, &recipient);
+
+        let mut amount_out = U256::from_u128(env, 0);
+        if let Some(token_symbol) = sell_to.clone()
+            && amount_claimed > 0
+        {
+            // The router enforces the minimum
+            let amount_out_u128 = smart_account_client.sell_reward_tokens(
+                &reward_token,
+                &token_symbol,
+                &amount_claimed,
+                &Self::convert_u256_to_u128(env, &min_amount_out_wad),
+            );
+            amount_out = U256::from_u128(env, amount_out_u128);
+
+            if !smart_account_client
+                .get_all_collateral_tokens()
+                .contains(token_symbol.clone())
+            {
+                smart_account_client.add_collateral_token(&token_symbol);
+            }
+            let existing_bal = smart_account_client.get_collateral_token_balance(&token_symbol);
+            smart_account_client
+                .set_collateral_token_balance(&token_symbol, &existing_bal.add(&amount_out));
+            Self::sync_collateral_holder(env, &smart_account_client, &token_symbol);
+        }
+
+        env.events().publish(
+            (
+                Symbol::new(env, "Trader_Claim_Rewards"),
+                smart_account.clone(),
+            ),
+            ExternalRewardsClaimEvent {
+                smart_account,
+                protocol,
+                reward_token,
+                amount_claimed,
+                sold_for: sell_to,
+                amount_out,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
+        Ok(amount_claimed)
+    }
+
+    /// Registers a stop-loss / take-profit style order on the account. Anyone may execute
+    /// it through `trigger_order` once its condition holds.
+    pub fn place_order(
+        env: &Env,
+        smart_account: Address,
+        condition: OrderCondition,
+        action: OrderAction,
+    ) -> Result<u32, AccountManagerError> {
+        let trader_address = Self::get_trader_address(env, &smart_account);
+        trader_address.require_auth();
+
+        match &action {
+            OrderAction::SwapAndRepay(_, token_in, token_out, _) => {
+                if token_in == token_out {
+                    return Err(AccountManagerError::InvalidTokenPair);
+                }
+            }
+            OrderAction::Deleverage(share_bps) => {
+                if *share_bps == 0 || *share_bps > BPS_DENOMINATOR {
+                    return Err(AccountManagerError::InvalidParameter);
+                }
+            }
+        }
+
+        let mut orders = Self::get_orders(env, smart_account.clone());
+        if orders.len() >= MAX_OPEN_ORDERS {
+            return Err(AccountManagerError::TooManyOpenOrders);
+        }
+
+        let id_key = AccountManagerKey::NextOrderId(smart_account.clone());
+        let order_id: u32 = env.storage().persistent().get(&id_key).unwrap_or(1);
+        env.storage().persistent().set(&id_key, &(order_id + 1));
+        Self::extend_ttl_account_manager(env, id_key);
+
+        orders.push_back(ConditionalOrder {
+            order_id,
+            condition,
+            action,
+            created_at: env.ledger().timestamp(),
+        });
+        Self::set_orders(env, &smart_account, orders);
+
+        env.events().publish(
+            (Symbol::new(env, "Order_Placed"), smart_account),
+            order_id,
+        );
+        Ok(order_id)
+    }
+
+    pub fn cancel_order(
+        env: &Env,
+        smart_account: Address,
+        order_id: u32,
+    ) -> Result<(), AccountManagerError> {
+        let trader_address = Self::get_trader_address(env, &smart_account);
+        trader_address.require_auth();
+
+        Self::take_order(env, &smart_account, order_id)?;
+
+        env.events().publish(
+            (Symbol::new(env, "Order_Cancelled"), smart_account),
+            order_id,
+        );
+        Ok(())
+    }
+
+    /// Permissionless entry point for keepers. Executes the order if its condition holds
+    /// against the oracle / risk engine and pays the keeper fee out of the account.
+    pub fn trigger_order(
+        env: &Env,
+        keeper: Address,
+        smart_account: Address,
+        order_id: u32,
+    ) -> Result<(), AccountManagerError> {
+        keeper.require_auth();
+
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+
+        let order = Self::take_order(env, &smart_account, order_id)?;
+        if !Self::order_condition_met(env, &registry_client, &smart_account, &order.condition) {
+            return Err(AccountManagerError::OrderConditionNotMet);
+        }
+
+        let smart_account_client = smart_account_contract::Client::new(env, &smart_account);
+        match order.action {
+            OrderAction::SwapAndRepay(venue, token_in, token_out, min_amount_out_wad) => {
+                if !Self::can_call(
+                    env,
+                    venue.clone(),
+                    SmartAccExternalAction::Swap,
+                    soroban_sdk::vec![env, token_in.clone(), token_out.clone()],
+                ) {
+                    panic_with_error!(env, AccountManagerError::ExternalCallNotAllowed);
+                }
+                if !Self::get_iscollateral_allowed(env, token_out.clone()) {
+                    return Err(AccountManagerError::CollateralNotAllowed);
+                }
+
+                let amount_in_wad = Self::convert_u256_to_u128(
+                    env,
+                    &smart_account_client.get_collateral_token_balance(&token_in),
+                );
+                if amount_in_wad == 0 {
+                    return Err(AccountManagerError::NoSwappableCollateral);
+                }
+                let min_out_u128 = Self::convert_u256_to_u128(env, &min_amount_out_wad);
+                let amount_out_wad = smart_account_client.swap_collateral(
+                    &venue,
+                    &token_in,
+                    &token_out,
+                    &amount_in_wad,
+                    &min_out_u128,
+                );
+                if amount_out_wad < min_out_u128 {
+                    return Err(AccountManagerError::SlippageExceeded);
+                }
+                Self::sync_collateral_holder(env, &smart_account_client, &token_in);
+                Self::sync_collateral_holder(env, &smart_account_client, &token_out);
+
+                let fee_wad = Self::pay_keeper_fee(
+                    env,
+                    &smart_account_client,
+                    &keeper,
+                    &token_out,
+                    amount_out_wad,
+                );
+                if smart_account_client
+                    .get_all_borrowed_tokens()
+                    .contains(token_out.clone())
+                {
+                    let debt = smart_account_client.get_borrowed_token_debt(&token_out);
+                    let available = U256::from_u128(env, amount_out_wad - fee_wad);
+                    Self::repay_from_collateral(
+                        env,
+                        &smart_account_client,
+                        &smart_account,
+                        &token_out,
+                        if debt < available { debt } else { available },
+                    );
+                }
+            }
+            OrderAction::Deleverage(share_bps) => {
+                let venues = Self::swap_venues(&registry_client);
+                let max_slippage_bps = Self::get_deleverage_config(env).max_slippage_bps;
+                let mut repaid = false;
+                for token_symbol in smart_account_client.get_all_borrowed_tokens().iter() {
+                    if Self::is_blend_debt_symbol(env, &token_symbol) {
+                        continue;
+                    }
+                    let debt = smart_account_client.get_borrowed_token_debt(&token_symbol);
+                    let target = debt
+                        .mul(&U256::from_u32(env, share_bps))
+                        .div(&U256::from_u32(env, BPS_DENOMINATOR));
+                    if target == U256::from_u128(env, 0) {
+                        continue;
+                    }
+                    let collateral = smart_account_client.get_collateral_token_balance(&token_symbol);
+                    if collateral != U256::from_u128(env, 0) {
+                        let repay_amount = if target < collateral { target } else { collateral };
+                        Self::repay_from_collateral(
+                            env,
+                            &smart_account_client,
+                            &smart_account,
+                            &token_symbol,
+                            repay_amount.clone(),
+                        );
+                        Self::pay_keeper_fee(
+                            env,
+                            &smart_account_client,
+                            &keeper,
+                            &token_symbol,
+                            Self::convert_u256_to_u128(env, &repay_amount),
+                        );
+                        repaid = true;
+                        continue;
+                    }
+
+                    // Nothing held in the debt token: buy it with other collateral instead
+                    let Some((_, _, amount_out)) = Self::swap_collateral_into(
+                        env,
+                        &registry_client,
+                        &smart_account_client,
+                        &venues,
+                        &token_symbol,
+                        &target,
+                        max_slippage_bps,
+                    )?
+                    else {
+                        continue;
+                    };
+                    let amount_out_wad = Self::convert_u256_to_u128(env, &amount_out);
+                    let fee_wad = Self::pay_keeper_fee(
+                        env,
+                        &smart_account_client,
+                        &keeper,
+                        &token_symbol,
+                        amount_out_wad,
+                    );
+                    let available = U256::from_u128(env, amount_out_wad - fee_wad);
+                    Self::repay_from_collateral(
+                        env,
+                        &smart_account_client,
+                        &smart_account,
+                        &token_symbol,
+                        if debt < available { debt } else { available },
+                    );
+                    repaid = true;
+                }
+                if !repaid {
+                    return Err(AccountManagerError::NoSwappableCollateral);
+                }
+            }
+        }
+
+        env.events().publish(
+            (Symbol::new(env, "Order_Triggered"), smart_account.clone()),
+            OrderTriggeredEvent {
+                smart_account,
+                order_id,
+                keeper,
+                timestamp: env.ledger().timestamp(),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn is_order_triggerable(env: &Env, smart_account: Address, order_id: u32) -> bool {
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        Self::get_orders(env, smart_account.clone())
+            .iter()
+            .find(|order| order.order_id == order_id)
+            .map(|order| {
+                Self::order_condition_met(env, &registry_client, &smart_account, &order.condition)
+            })
+            .unwrap_or(false)
+    }
+
+    pub fn get_orders(env: &Env, smart_account: Address) -> Vec<ConditionalOrder> {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::ConditionalOrders(smart_account))
+            .unwrap_or(Vec::new(env))
+    }
+
+    pub fn set_keeper_fee_bps(env: &Env, fee_bps: u32) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&AccountManagerKey::Admin)
+            .unwrap_or_else(|| panic!("Admin key not set!"));
+        admin.require_auth();
+
+        if fee_bps > MAX_KEEPER_FEE_BPS {
+            panic_with_error!(env, AccountManagerError::InvalidParameter);
+        }
+        let key = AccountManagerKey::KeeperFeeBps;
+        env.storage().persistent().set(&key, &fee_bps);
+        Self::extend_ttl_account_manager(env, key);
+    }
+
+    pub fn get_keeper_fee_bps(env: &Env) -> u32 {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::KeeperFeeBps)
+            .unwrap_or(DEFAULT_KEEPER_FEE_BPS)
+    }
+
+    fn set_orders(env: &Env, smart_account: &Address, orders: Vec<ConditionalOrder>) {
+        let key = AccountManagerKey::ConditionalOrders(smart_account.clone());
+        env.storage().persistent().set(&key, &orders);
+        Self::extend_ttl_account_manager(env, key);
+    }
+
+    /// Removes an order from the account's book and returns it.
+    /// Built-in swap venues in the order deleveraging tries them.
+    fn swap_venues(registry_client: &registry_contract::Client) -> Vec<Address> {
+        let mut venues = Vec::new(&registry_client.env);
+        if registry_client.has_aquarius_router_address() {
+            venues.push_back(registry_client.get_aquarius_router_address());
+        }
+        if registry_client.has_soroswap_router_address() {
+            venues.push_back(registry_client.get_soroswap_router_address());
+        }
+        venues
     }
 
-    pub fn generate_salt(
+    /// Sells the first other collateral with a whitelisted swap into `debt_symbol` for up
+    /// to `target_amount` of it, at oracle value less `max_slippage_bps`. Returns the
+    /// collateral sold, the amount sold and the amount received, or `None` if nothing could be.
+    fn swap_collateral_into(
         env: &Env,
-        trader_address: Address,
-        account_manager: Address,
-        smart_account_num: u32,
-    ) -> BytesN<32> {
-        // Convert addresses to XDR for consistent serialization
-        // Make sure empty addresses are not sent
-        assert!(trader_address.to_string().len() != 0);
-        assert!(account_manager.to_string().len() != 0);
+        registry_client: &registry_contract::Client,
+        smart_account_client: &smart_account_contract::Client,
+        venues: &Vec<Address>,
+        debt_symbol: &Symbol,
+        target_amount: &U256,
+        max_slippage_bps: u32,
+    ) -> Result<Option<(Symbol, U256, U256)>, AccountManagerError> {
+        for collateral_symbol in smart_account_client.get_all_collateral_tokens().iter() {
+            if collateral_symbol == *debt_symbol {
+                continue;
+            }
+            let Some(venue) = venues.iter().find(|venue| {
+                Self::can_call(
+                    env,
+                    venue.clone(),
+                    SmartAccExternalAction::Swap,
+                    soroban_sdk::vec![env, collateral_symbol.clone(), debt_symbol.clone()],
+                )
+            }) else {
+                continue;
+            };
+            let collateral_balance =
+                smart_account_client.get_collateral_token_balance(&collateral_symbol);
+            if collateral_balance == U256::from_u128(env, 0) {
+                continue;
+            }
 
-        let trader_xdr = trader_address.to_xdr(env);
-        let manager_xdr = account_manager.to_xdr(env);
-        let num_xdr = smart_account_num.to_le_bytes();
+            let debt_price = Self::get_oracle_price_wad(env, registry_client, debt_symbol);
+            let collateral_price =
+                Self::get_oracle_price_wad(env, registry_client, &collateral_symbol);
+            let needed = target_amount.mul(&debt_price).div(&collateral_price);
+            let amount_in = if needed < collateral_balance {
+                needed
+            } else {
+                collateral_balance
+            };
+            let min_amount_out = amount_in
+                .mul(&collateral_price)
+                .div(&debt_price)
+                .mul(&U256::from_u32(env, BPS_DENOMINATOR - max_slippage_bps))
+                .div(&U256::from_u32(env, BPS_DENOMINATOR));
+
+            let min_out_u128 = Self::convert_u256_to_u128(env, &min_amount_out);
+            let amount_out_wad = smart_account_client.swap_collateral(
+                &venue,
+                &collateral_symbol,
+                debt_symbol,
+                &Self::convert_u256_to_u128(env, &amount_in),
+                &min_out_u128,
+            );
+            if amount_out_wad < min_out_u128 {
+                return Err(AccountManagerError::SlippageExceeded);
+            }
+            Self::sync_collateral_holder(env, smart_account_client, &collateral_symbol);
+            Self::sync_collateral_holder(env, smart_account_client, debt_symbol);
+            return Ok(Some((
+                collateral_symbol,
+                amount_in,
+                U256::from_u128(env, amount_out_wad),
+            )));
+        }
+        Ok(None)
+    }
 
-        // Create a combined buffer to hash both addresses together
-        let mut combined = Bytes::new(env);
+    fn take_order(
+        env: &Env,
+        smart_account: &Address,
+        order_id: u32,
+    ) -> Result<ConditionalOrder, AccountManagerError> {
+        let mut orders = Self::get_orders(env, smart_account.clone());
+        let index = orders
+            .iter()
+            .position(|order| order.order_id == order_id)
+            .ok_or(AccountManagerError::OrderNotFound)?;
+        let order = orders.get_unchecked(index as u32);
+        orders.remove(index as u32);
+        Self::set_orders(env, smart_account, orders);
+        Ok(order)
+    }
 
-        // Append trader address bytes
-        for i in 0..trader_xdr.len() {
-            combined.push_back(trader_xdr.get(i).unwrap());
+    fn order_condition_met(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+        condition: &OrderCondition,
+    ) -> bool {
+        match condition {
+            OrderCondition::PriceBelow(token_symbol, price_wad) => {
+                Self::get_oracle_price_wad(env, registry_client, token_symbol) < *price_wad
+            }
+            OrderCondition::PriceAbove(token_symbol, price_wad) => {
+                Self::get_oracle_price_wad(env, registry_client, token_symbol) > *price_wad
+            }
+            OrderCondition::HealthBelow(ratio_wad) => {
+                let risk_engine_address = registry_client.get_risk_engine_address();
+                let risk_engine_client =
+                    risk_engine_contract::Client::new(env, &risk_engine_address);
+                let debt = risk_engine_client.get_current_total_borrows(smart_account);
+                if debt == U256::from_u128(env, 0) {
+                    return false;
+                }
+                let balance = risk_engine_client.get_current_total_balance(smart_account);
+                balance.mul(&U256::from_u128(env, WAD_U128)).div(&debt) < *ratio_wad
+            }
         }
+    }
 
-        // Append account manager bytes
-        for i in 0..manager_xdr.len() {
-            combined.push_back(manager_xdr.get(i).unwrap());
-        }
+    /// Repays debt in `token_symbol` using collateral the account holds in the same token.
+    fn repay_from_collateral(
+        env: &Env,
+        smart_account_client: &smart_account_contract::Client,
+        smart_account: &Address,
+        token_symbol: &Symbol,
+        repay_amount_wad: U256,
+    ) {
+        let repaid_wad =
+            Self::repay_internal(env, smart_account, repay_amount_wad, token_symbol.clone());
+        let collateral = smart_account_client.get_collateral_token_balance(token_symbol);
+        smart_account_client
+            .set_collateral_token_balance(token_symbol, &collateral.sub(&repaid_wad));
+        Self::sync_collateral_holder(env, smart_account_client, token_symbol);
+    }
 
-        for i in 0..num_xdr.len() {
-            combined.push_back(*num_xdr.get(i).unwrap());
+    /// Sends the keeper its fee on `amount_wad` out of the account's collateral in
+    /// `token_symbol`. Returns the fee in WAD.
+    fn pay_keeper_fee(
+        env: &Env,
+        smart_account_client: &smart_account_contract::Client,
+        keeper: &Address,
+        token_symbol: &Symbol,
+        amount_wad: u128,
+    ) -> u128 {
+        let collateral = Self::convert_u256_to_u128(
+            env,
+            &smart_account_client.get_collateral_token_balance(token_symbol),
+        );
+        let fee_wad = (amount_wad * Self::get_keeper_fee_bps(env) as u128
+            / BPS_DENOMINATOR as u128)
+            .min(collateral);
+        if fee_wad > 0 {
+            smart_account_client.remove_collateral_token_balance(keeper, token_symbol, &fee_wad);
+            Self::sync_collateral_holder(env, smart_account_client, token_symbol);
         }
-
-        // Use Soroban's built-in SHA256 hash function
-        // This ensures a unique 32-byte output for any unique input combination
-        env.crypto().sha256(&combined).into()
+        fee_wad
     }
 
-    pub fn get_inactive_accounts(env: &Env, trader_address: Address) -> Vec<Address> {
+    pub fn get_leveraged_position(
+        env: &Env,
+        smart_account: Address,
+        venue: Address,
+    ) -> Option<LeveragedPosition> {
         env.storage()
             .persistent()
-            .get(&AccountManagerKey::InactiveAccountOf(trader_address))
-            .unwrap_or(Vec::new(env))
+            .get(&AccountManagerKey::LeveragedPosition(smart_account, venue))
     }
 
-    fn set_inactive_accounts(env: &Env, trader_address: Address, inactive_accounts: Vec<Address>) {
-        let keyx = AccountManagerKey::InactiveAccountOf(trader_address);
-        env.storage().persistent().set(&keyx, &inactive_accounts);
-        Self::extend_ttl_account_manager(env, keyx);
+    fn get_leveraged_venues(env: &Env, smart_account: &Address) -> Vec<Address> {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::LeveragedVenues(smart_account.clone()))
+            .unwrap_or(Vec::new(env))
     }
 
-    fn convert_u256_to_u128(env: &Env, x: &U256) -> u128 {
-        x.to_u128()
-            .unwrap_or_else(|| panic_with_error!(&env, AccountManagerError::IntegerConversionError))
+    fn remove_leveraged_position(env: &Env, smart_account: &Address, venue: &Address) {
+        env.storage()
+            .persistent()
+            .remove(&AccountManagerKey::LeveragedPosition(smart_account.clone(), venue.clone()));
+
+        let mut venues = Self::get_leveraged_venues(env, smart_account);
+        if let Some(index) = venues.first_index_of(venue) {
+            venues.remove(index);
+            let venues_key = AccountManagerKey::LeveragedVenues(smart_account.clone());
+            env.storage().persistent().set(&venues_key, &venues);
+            Self::extend_ttl_account_manager(env, venues_key);
+        }
     }
 
-    fn create_smart_account(
+    /// Applies the isolation-mode rules and the risk engine's borrow check to a new borrow.
+    fn check_borrow_allowed(
         env: &Env,
-        trader_address: &Address,
-        smart_account_hash: BytesN<32>,
-    ) -> Address {
-        let mut trader_smart_accounts = env
-            .storage()
-            .persistent()
-            .get(&AccountManagerKey::SmartAccounts(trader_address.clone()))
-            .unwrap_or(Vec::new(env));
-
-        let salt = Self::generate_salt(
-            &env,
-            trader_address.clone(),
-            env.current_contract_address(),
-            trader_smart_accounts.len(),
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+        token_symbol: &Symbol,
+        borrow_amount_wad: &U256,
+    ) -> Result<(), AccountManagerError> {
+        Self::check_isolated_borrow(
+            env,
+            registry_client,
+            smart_account,
+            token_symbol,
+            borrow_amount_wad,
         );
+        let risk_engine_address = registry_client.get_risk_engine_address();
+        let risk_engine_client = risk_engine_contract::Client::new(env, &risk_engine_address);
+        if !risk_engine_client.is_borrow_allowed(token_symbol, borrow_amount_wad, smart_account) {
+            return Err(AccountManagerError::BorrowNotAllowed);
+        }
+        Ok(())
+    }
 
-        let mut constructor_args = Vec::new(&env);
-        constructor_args.push_back(env.current_contract_address().to_val());
-        constructor_args.push_back(Self::get_registry_address(env).to_val());
-        constructor_args.push_back(trader_address.to_val());
-
-        let smart_account = env
-            .deployer()
-            .with_address(env.current_contract_address(), salt)
-            .deploy_v2(smart_account_hash, constructor_args);
-
-        trader_smart_accounts.push_back(smart_account.clone());
+    fn assert_account_healthy(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+    ) {
+        let risk_engine_address = registry_client.get_risk_engine_address();
+        let risk_engine_client = risk_engine_contract::Client::new(env, &risk_engine_address);
+        if !risk_engine_client.is_account_healthy(
+            &risk_engine_client.get_current_total_balance(smart_account),
+            &risk_engine_client.get_current_total_borrows(smart_account),
+        ) {
+            panic_with_error!(env, AccountManagerError::AccountUnhealthy);
+        }
+    }
 
-        env.storage().persistent().set(
-            &AccountManagerKey::SmartAccounts(trader_address.clone()),
-            &trader_smart_accounts,
-        );
+    /// Reverts if the account breaks the risk engine's per-account or `protocol` exposure
+    /// limits after an external call.
+    fn assert_execute_allowed(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account: &Address,
+        protocol: &Address,
+    ) {
+        let risk_engine_address = registry_client.get_risk_engine_address();
+        let risk_engine_client = risk_engine_contract::Client::new(env, &risk_engine_address);
+        if !risk_engine_client.is_execute_allowed(smart_account, protocol) {
+            panic_with_error!(env, AccountManagerError::ExternalCallNotAllowed);
+        }
+    }
 
-        env.storage().persistent().set(
-            &AccountManagerKey::TraderAddress(smart_account.clone()),
-            &trader_address,
-        );
+    fn get_oracle_price_wad(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        token_symbol: &Symbol,
+    ) -> U256 {
+        let price_wad =
+            risk_engine_contract::Client::new(env, &registry_client.get_risk_engine_address())
+                .get_asset_price(token_symbol);
+        if price_wad == U256::from_u128(env, 0) {
+            panic_with_error!(env, AccountManagerError::InvalidOraclePrice);
+        }
+        price_wad
+    }
 
-        env.events().publish(
-            (Symbol::new(&env, "Smart_account_creation"), trader_address),
-            AccountCreationEvent {
-                smart_account: smart_account.clone(),
-                creation_time: env.ledger().timestamp(),
-            },
-        );
-        smart_account
+    /// True for assets held as tokens in the account: the base assets and listed collateral,
+    /// as opposed to tracking symbols for external positions.
+    fn is_plain_asset(registry_client: &registry_contract::Client, token_symbol: &Symbol) -> bool {
+        *token_symbol == XLM_SYMBOL
+            || *token_symbol == USDC_SYMBOL
+            || *token_symbol == EURC_SYMBOL
+            || registry_client.get_collateral_asset(token_symbol).is_some()
     }
 
-    fn scale_for_operation(amount_wad: u128, xlm_decimals: u32) -> i128 {
-        ((amount_wad * 10u128.pow(xlm_decimals)) / WAD_U128) as i128
+    fn token_contract_address(
+        registry_client: &registry_contract::Client,
+        token_symbol: &Symbol,
+    ) -> Address {
+        if *token_symbol == XLM_SYMBOL {
+            registry_client.get_xlm_contract_adddress()
+        } else if *token_symbol == USDC_SYMBOL {
+            registry_client.get_usdc_contract_address()
+        } else if *token_symbol == EURC_SYMBOL {
+            registry_client.get_eurc_contract_address()
+        } else if let Some(asset) = registry_client.get_collateral_asset(token_symbol) {
+            asset.token_address
+        } else {
+            panic_with_error!(&registry_client.env, AccountManagerError::UnsupportedToken);
+        }
     }
-    /// To be implemented
-    pub fn approve() {}
 
-    pub fn execute(env_x: &Env, smart_account: Address, extern_proto_call_bytes: Bytes) {
-        let trader_address = Self::get_trader_address(&env_x, &smart_account);
-        trader_address.require_auth();
+    /// Token balance of `owner` in `token_symbol`, scaled to WAD.
+    fn token_balance_wad(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        token_symbol: &Symbol,
+        owner: &Address,
+    ) -> U256 {
+        let token_address = Self::token_contract_address(registry_client, token_symbol);
+        let token_client = token::Client::new(env, &token_address);
+        let balance = token_client.balance(owner).max(0) as u128;
+        U256::from_u128(env, balance * (WAD_U128 / 10_u128.pow(token_client.decimals())))
+    }
 
-        let call: ExternalProtocolCall =
-            ExternalProtocolCall::from_xdr(env_x, &extern_proto_call_bytes)
-                .expect("deserialize failed");
+    fn execute_internal(
+        env_x: &Env,
+        trader_address: Address,
+        smart_account: Address,
+        call: ExternalProtocolCall,
+    ) -> i128 {
+        if !Self::can_call(
+            env_x,
+            call.protocol_address.clone(),
+            call.type_action.clone(),
+            call.tokens_out.clone(),
+        ) {
+            panic_with_error!(env_x, AccountManagerError::ExternalCallNotAllowed);
+        }
 
         let registry_address: Address = Self::get_registry_address(&env_x);
         let registry_client = registry_contract::Client::new(&env_x, &registry_address);
 
+        // Delisted Blend pools only let accounts exit what they still hold there
+        if registry_client.is_delisted_blend_pool(&call.protocol_address)
+            && !matches!(
+                call.type_action,
+                SmartAccExternalAction::Withdraw
+                    | SmartAccExternalAction::WithdrawCollateral
+                    | SmartAccExternalAction::BlendRepay
+            )
+        {
+            panic_with_error!(env_x, AccountManagerError::BlendPoolDelisted);
+        }
+
         let mut tokens_amount_wad = Vec::new(env_x);
 
         call.amount_out
@@ -697,118 +3291,287 @@ impl AccountManagerContract {
 
         let smart_acc_client = smart_account_contract::Client::new(env_x, &smart_account);
 
-        let (_ok, token_delta) = smart_acc_client.execute(
-            &call.protocol_address,
-            &call.type_action,
-            &trader_address,
-            &call.tokens_out,
-            &tokens_amount_wad,
+        // Adapters report their own tracking deltas; built-in venues return one position
+        // delta that is mapped to its tracking symbol, so both are booked the same way
+        let deltas = if registry_client.is_protocol_adapter(&call.protocol_address) {
+            let deltas = smart_acc_client.execute_via_adapter(
+                &call.protocol_address,
+                &call.type_action,
+                &call.tokens_out,
+                &tokens_amount_wad,
+            );
+            let allowed_symbols =
+                registry_client.get_adapter_tracking_symbols(&call.protocol_address);
+            for (tracking_symbol, _) in deltas.iter() {
+                if !allowed_symbols.contains(tracking_symbol) {
+                    panic_with_error!(env_x, AccountManagerError::AdapterSymbolMismatch);
+                }
+            }
+            deltas
+        } else {
+            // Blend borrows pay out into the account and repays are paid from it, so those
+            // tokens are booked against its collateral like lending pool borrows
+            let books_token_flows = Self::is_blend_venue(&registry_client, &call.protocol_address)
+                && matches!(
+                    call.type_action,
+                    SmartAccExternalAction::BlendBorrow | SmartAccExternalAction::BlendRepay
+                );
+            let balances_before = if books_token_flows {
+                Self::token_balances_wad(env_x, &registry_client, &call.tokens_out, &smart_account)
+            } else {
+                Vec::new(env_x)
+            };
+            let (_ok, token_delta) = smart_acc_client.execute(
+                &call.protocol_address,
+                &call.type_action,
+                &trader_address,
+                &call.tokens_out,
+                &tokens_amount_wad,
+                &Self::min_amounts_wad(env_x, &call),
+            );
+            if books_token_flows {
+                Self::book_token_flows(
+                    env_x,
+                    &registry_client,
+                    &smart_acc_client,
+                    &call.tokens_out,
+                    &balances_before,
+                );
+            }
+            Self::builtin_tracking_deltas(env_x, &registry_client, &call, token_delta)
+        };
+
+        Self::apply_tracking_deltas(
+            env_x,
+            &registry_client,
+            &smart_acc_client,
+            &smart_account,
+            &deltas,
         );
+        deltas.iter().map(|(_, delta)| delta).sum()
+    }
 
-        // Handle tracking token minting/burning
-        let tracking_token_address = registry_client.get_tracking_token_contract_addr();
-        let tracking_client =
-            tracking_token_contract::Client::new(env_x, &tracking_token_address);
-
-        // Check if this is a Blend protocol operation
-        if registry_client.has_blend_pool_address() {
-            let blend_pool_address = registry_client.get_blend_pool_address();
-            if call.protocol_address == blend_pool_address {
-                if call.tokens_out.len() != 1 {
-                    panic!("Blend operations support exactly one token per call");
-                }
+    /// Tracking symbol and delta for a call to Blend, Aquarius or Soroswap, in the same shape
+    /// adapters report. Swaps and unknown venues produce no deltas.
+    fn builtin_tracking_deltas(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        call: &ExternalProtocolCall,
+        token_delta: i128,
+    ) -> Vec<(Symbol, i128)> {
+        let mut deltas = Vec::new(env);
+        let tracking_symbol = if Self::is_blend_venue(registry_client, &call.protocol_address) {
+            if call.tokens_out.len() != 1 {
+                panic_with_error!(env, AccountManagerError::InvalidTokenCount);
+            }
+            registry_client.get_blend_tracking_symbol(
+                &call.protocol_address,
+                &call.tokens_out.get(0).unwrap(),
+                &Self::blend_position_kind(&call.type_action),
+            )
+        } else if let Some(lp_symbol) =
+            Self::lp_tracking_symbol(env, registry_client, &call.protocol_address, &call.tokens_out)
+        {
+            lp_symbol
+        } else {
+            return deltas;
+        };
 
-                if token_delta != 0 {
-                    let token_symbol = call.tokens_out.get(0).unwrap();
-                    let tracking_symbol = Self::tracking_symbol_for_blend(env_x, &token_symbol);
+        if token_delta != 0 {
+            deltas.push_back((tracking_symbol, token_delta));
+        }
+        deltas
+    }
 
-                    if token_delta > 0 {
-                        tracking_client.mint(&tracking_symbol, &smart_account, &token_delta);
-                    } else {
-                        tracking_client.burn(&tracking_symbol, &smart_account, &(-token_delta));
-                    }
+    /// Mints or burns the tracking tokens in `deltas` and keeps the account's collateral and
+    /// borrowed token lists in step. Blend liabilities are tracked alongside lending pool debt.
+    fn apply_tracking_deltas(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_acc_client: &smart_account_contract::Client,
+        smart_account: &Address,
+        deltas: &Vec<(Symbol, i128)>,
+    ) {
+        let tracking_token_address = registry_client.get_tracking_token_contract_addr();
+        let tracking_client = tracking_token_contract::Client::new(env, &tracking_token_address);
 
-                    let tracking_balance = tracking_client.balance(&smart_account, &tracking_symbol);
-                    if tracking_balance > 0 {
-                        smart_acc_client.add_collateral_token(&tracking_symbol);
-                    }
+        for (tracking_symbol, delta) in deltas.iter() {
+            if delta > 0 {
+                tracking_client.mint(&tracking_symbol, smart_account, &delta);
+            } else if delta < 0 {
+                tracking_client.burn(&tracking_symbol, smart_account, &(-delta));
+            } else {
+                continue;
+            }
+
+            let tracking_balance = tracking_client.balance(smart_account, &tracking_symbol);
+            if Self::is_blend_debt_symbol(env, &tracking_symbol) {
+                if tracking_balance > 0 {
+                    smart_acc_client.add_borrowed_token(&tracking_symbol);
+                    smart_acc_client.set_has_debt(&true);
+                } else {
+                    smart_acc_client.remove_borrowed_token(&tracking_symbol);
                 }
-                return;
+            } else if tracking_balance > 0 {
+                smart_acc_client.add_collateral_token(&tracking_symbol);
             }
         }
-        
-        // Handle Aquarius protocol operations
-        if registry_client.has_aquarius_router_address() {
-            let aquarius_router_address = registry_client.get_aquarius_router_address();
-            if call.protocol_address == aquarius_router_address {
-                match call.type_action {
-                    SmartAccExternalAction::AddLiquidity => {
-                        // Mint LP tracking tokens for liquidity provision
-                        if token_delta > 0 {
-                            let tracking_symbol = Self::tracking_symbol_for_aquarius_lp(
-                                env_x,
-                                &call.tokens_out.get(0).unwrap(),
-                                &call.tokens_out.get(1).unwrap(),
-                            );
-
-                            tracking_client.mint(&tracking_symbol, &smart_account, &token_delta);
-
-                            let tracking_balance =
-                                tracking_client.balance(&smart_account, &tracking_symbol);
-                            if tracking_balance > 0 {
-                                smart_acc_client.add_collateral_token(&tracking_symbol);
-                            }
-                        }
-                    }
-                    SmartAccExternalAction::RemoveLiquidity => {
-                        // Burn LP tracking tokens for liquidity removal
-                        if token_delta < 0 {
-                            let tracking_symbol = Self::tracking_symbol_for_aquarius_lp(
-                                env_x,
-                                &call.tokens_out.get(0).unwrap(),
-                                &call.tokens_out.get(1).unwrap(),
-                            );
-
-                            tracking_client.burn(&tracking_symbol, &smart_account, &(-token_delta));
-                        }
-                    }
-                    SmartAccExternalAction::Swap => {
-                        // Swaps don't affect LP token tracking
-                    }
-                    _ => {}
-                }
-                return;
+    }
+
+    /// Minimum amounts the venue must return, in WAD: the pair tokens followed by the LP
+    /// amount for AddLiquidity, the pair tokens for RemoveLiquidity, the token out for Swap.
+    fn min_amounts_wad(env: &Env, call: &ExternalProtocolCall) -> Vec<u128> {
+        let mut min_amounts = Vec::new(env);
+        call.min_amounts_out
+            .iter()
+            .for_each(|x| min_amounts.push_back(x.to_u128().unwrap()));
+        if call.type_action == SmartAccExternalAction::AddLiquidity {
+            while min_amounts.len() < 2 {
+                min_amounts.push_back(0);
             }
+            min_amounts.push_back(call.min_liquidity_out.to_u128().unwrap());
         }
+        min_amounts
     }
 
+    /// Returns true if the (protocol, action, token set) tuple has been whitelisted by the admin.
     pub fn can_call(
         env: &Env,
-        protocol_addr: Address,
-        smart_account: Address,
-        call_data: Bytes,
-    ) -> (bool, Address, Address) {
-        (
-            true,
-            Address::from_str(env, "strkey"),
-            Address::from_str(env, "strkey"),
-        )
+        protocol_address: Address,
+        action: SmartAccExternalAction,
+        tokens: Vec<Symbol>,
+    ) -> bool {
+        Self::get_allowed_external_calls(env)
+            .iter()
+            .any(|allowed| Self::is_same_call(&allowed, &protocol_address, &action, &tokens))
+    }
+
+    pub fn allow_external_call(
+        env: &Env,
+        protocol_address: Address,
+        action: SmartAccExternalAction,
+        tokens: Vec<Symbol>,
+    ) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&AccountManagerKey::Admin)
+            .unwrap_or_else(|| panic!("Admin key not set!"));
+        admin.require_auth();
+        if Self::has_duplicate_tokens(&tokens) {
+            panic_with_error!(env, AccountManagerError::DuplicateToken);
+        }
+
+        let mut allowed_calls = Self::get_allowed_external_calls(env);
+        if allowed_calls
+            .iter()
+            .any(|allowed| Self::is_same_call(&allowed, &protocol_address, &action, &tokens))
+        {
+            return;
+        }
+
+        allowed_calls.push_back(AllowedExternalCall {
+            protocol_address,
+            action,
+            tokens,
+        });
+        Self::set_allowed_external_calls(env, allowed_calls);
+    }
+
+    pub fn disallow_external_call(
+        env: &Env,
+        protocol_address: Address,
+        action: SmartAccExternalAction,
+        tokens: Vec<Symbol>,
+    ) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&AccountManagerKey::Admin)
+            .unwrap_or_else(|| panic!("Admin key not set!"));
+        admin.require_auth();
+
+        let mut remaining = Vec::new(env);
+        for allowed in Self::get_allowed_external_calls(env).iter() {
+            if !Self::is_same_call(&allowed, &protocol_address, &action, &tokens) {
+                remaining.push_back(allowed);
+            }
+        }
+        Self::set_allowed_external_calls(env, remaining);
+    }
+
+    pub fn get_allowed_external_calls(env: &Env) -> Vec<AllowedExternalCall> {
+        env.storage()
+            .persistent()
+            .get(&AccountManagerKey::AllowedExternalCalls)
+            .unwrap_or(Vec::new(env))
+    }
+
+    pub fn get_allowed_calls_for_protocol(
+        env: &Env,
+        protocol_address: Address,
+    ) -> Vec<AllowedExternalCall> {
+        let mut calls = Vec::new(env);
+        for allowed in Self::get_allowed_external_calls(env).iter() {
+            if allowed.protocol_address == protocol_address {
+                calls.push_back(allowed);
+            }
+        }
+        calls
+    }
+
+    fn set_allowed_external_calls(env: &Env, allowed_calls: Vec<AllowedExternalCall>) {
+        let key = AccountManagerKey::AllowedExternalCalls;
+        env.storage().persistent().set(&key, &allowed_calls);
+        Self::extend_ttl_account_manager(env, key);
+    }
+
+    fn is_same_call(
+        allowed: &AllowedExternalCall,
+        protocol_address: &Address,
+        action: &SmartAccExternalAction,
+        tokens: &Vec<Symbol>,
+    ) -> bool {
+        allowed.protocol_address == *protocol_address
+            && allowed.action == *action
+            && allowed.tokens.len() == tokens.len()
+            && !Self::has_duplicate_tokens(tokens)
+            && tokens.iter().all(|token| allowed.tokens.contains(token))
+    }
+
+    fn has_duplicate_tokens(tokens: &Vec<Symbol>) -> bool {
+        (0..tokens.len()).any(|i| {
+            let token = tokens.get_unchecked(i);
+            (i + 1..tokens.len()).any(|j| tokens.get_unchecked(j) == token)
+        })
     }
 
     pub fn sweepto() {}
 
-    fn tracking_symbol_for_blend(env: &Env, token_symbol: &Symbol) -> Symbol {
-        if *token_symbol == XLM_SYMBOL {
-            Symbol::new(env, BLEND_XLM)
-        } else if *token_symbol == USDC_SYMBOL {
-            Symbol::new(env, BLEND_USDC)
-        } else if *token_symbol == EURC_SYMBOL {
-            Symbol::new(env, BLEND_EURC)
-        } else {
-            panic!("Tracking token not configured for given token symbol");
+    fn blend_position_kind(action: &SmartAccExternalAction) -> BlendPositionKind {
+        match action {
+            SmartAccExternalAction::SupplyCollateral
+            | SmartAccExternalAction::WithdrawCollateral => BlendPositionKind::Collateral,
+            SmartAccExternalAction::BlendBorrow | SmartAccExternalAction::BlendRepay => {
+                BlendPositionKind::Liability
+            }
+            _ => BlendPositionKind::Supply,
         }
     }
 
+    /// Listed Blend pools and delisted ones that still hold positions.
+    fn is_blend_venue(registry_client: &registry_contract::Client, protocol: &Address) -> bool {
+        registry_client.is_blend_pool(protocol) || registry_client.is_delisted_blend_pool(protocol)
+    }
+
+    fn is_blend_debt_symbol(env: &Env, symbol: &Symbol) -> bool {
+        let registry_client = registry_contract::Client::new(env, &Self::get_registry_address(env));
+        matches!(
+            registry_client.get_blend_tracking_info(symbol),
+            Some(info) if info.kind == BlendPositionKind::Liability
+        )
+    }
+
     fn tracking_symbol_for_aquarius_lp(
         env: &Env,
         token0: &Symbol,
@@ -820,7 +3583,80 @@ impl AccountManagerContract {
         {
             Symbol::new(env, AQUARIUS_XLM_USDC)
         } else {
-            panic!("Aquarius LP tracking not configured for this token pair");
+            panic_with_error!(env, AccountManagerError::LpTrackingNotConfigured);
+        }
+    }
+
+    /// Tracking symbol of the LP position for `tokens` at a built-in liquidity venue, or
+    /// `None` if `venue` is not Aquarius or Soroswap.
+    fn lp_tracking_symbol(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        venue: &Address,
+        tokens: &Vec<Symbol>,
+    ) -> Option<Symbol> {
+        if registry_client.has_aquarius_router_address()
+            && *venue == registry_client.get_aquarius_router_address()
+        {
+            Some(Self::tracking_symbol_for_aquarius_lp(
+                env,
+                &tokens.get(0).unwrap(),
+                &tokens.get(1).unwrap(),
+            ))
+        } else if registry_client.has_soroswap_router_address()
+            && *venue == registry_client.get_soroswap_router_address()
+        {
+            Some(Self::tracking_symbol_for_soroswap_lp(
+                env,
+                &tokens.get(0).unwrap(),
+                &tokens.get(1).unwrap(),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// LP amount in the unit an `ExternalProtocolCall` to `venue` takes: WAD for Soroswap,
+    /// raw pool shares for Aquarius.
+    fn lp_call_amount(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        venue: &Address,
+        tokens: &Vec<Symbol>,
+        liquidity: i128,
+    ) -> U256 {
+        let liquidity = liquidity.max(0) as u128;
+        if registry_client.has_soroswap_router_address()
+            && *venue == registry_client.get_soroswap_router_address()
+        {
+            let lp_symbol = Self::tracking_symbol_for_soroswap_lp(
+                env,
+                &tokens.get(0).unwrap(),
+                &tokens.get(1).unwrap(),
+            );
+            let tracking_client = tracking_token_contract::Client::new(
+                env,
+                &registry_client.get_tracking_token_contract_addr(),
+            );
+            let lp_decimals = tracking_client.decimals(&lp_symbol);
+            return U256::from_u128(env, liquidity * (WAD_U128 / 10_u128.pow(lp_decimals)));
+        }
+        U256::from_u128(env, liquidity)
+    }
+
+    fn tracking_symbol_for_soroswap_lp(env: &Env, token0: &Symbol, token1: &Symbol) -> Symbol {
+        let is_pair = |a: &Symbol, b: &Symbol| {
+            (token0 == a && token1 == b) || (token0 == b && token1 == a)
+        };
+
+        if is_pair(&XLM_SYMBOL, &USDC_SYMBOL) {
+            Symbol::new(env, SOROSWAP_XLM_USDC)
+        } else if is_pair(&XLM_SYMBOL, &EURC_SYMBOL) {
+            Symbol::new(env, SOROSWAP_XLM_EURC)
+        } else if is_pair(&USDC_SYMBOL, &EURC_SYMBOL) {
+            Symbol::new(env, SOROSWAP_USDC_EURC)
+        } else {
+            panic_with_error!(env, AccountManagerError::LpTrackingNotConfigured);
         }
     }
 }
@@ -18,16 +18,75 @@ pub enum AccountManagerKey {
     AssetCap,
     Admin,
     RegistryContract,
+    AllowedExternalCalls, // Whitelisted (protocol, action, token set) tuples for execute
+    LeveragedPosition(Address, Address), // (smart account, venue) -> open leveraged LP position
+    LeveragedVenues(Address),            // Venues a smart account holds a leveraged position on
+    Operator(Address, Address),          // (smart account, operator) -> granted permissions
+    OperatorsList(Address),              // Operators holding a grant on a smart account
+    PendingOwner(Address),               // Proposed new owner awaiting acceptance
+    ConditionalOrders(Address),          // Open conditional orders of a smart account
+    NextOrderId(Address),                // Id handed to the next order placed on a smart account
+    KeeperFeeBps,                        // Fee paid to whoever triggers an order, in basis points
+    LiquidationAuction(Address),         // Running Dutch auction for an unhealthy smart account
+    AuctionConfig,                       // Discount schedule used by liquidation auctions
+    DeleverageConfig,                    // Share repaid and swap slippage allowed per deleverage
+    IsolatedDebt(Address),               // Borrows a smart account counted against isolation ceilings
+    DebtHistory(Address, Symbol),        // Cumulative borrow, repay and interest flows in a symbol
+    DebtHistorySymbols(Address),         // Symbols a smart account has ever borrowed
+    CollateralHistory(Address),          // Cumulative collateral deposited and withdrawn at oracle value
+    CollateralHolders(Symbol),           // Smart accounts holding a collateral balance of a symbol
 }
 
 #[contracterror]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum AccountManagerError {
     CollateralTokenNotFound = 1,
     BorrowedTokenNotFound = 2,
     MarginAccountNotFound = 3,
     IntegerConversionError = 4,
     UserDoesntHaveCollateralToken = 5,
+    ExternalCallNotAllowed = 6,
+    SlippageExceeded = 7,
+    PositionNotFound = 8,
+    OperatorNotAuthorized = 9,
+    OperatorNotFound = 10,
+    NoPendingOwnershipTransfer = 11,
+    OrderNotFound = 12,
+    OrderConditionNotMet = 13,
+    DeleverageNotAllowed = 14,
+    AuctionNotActive = 15,
+    AuctionAlreadyActive = 16,
+    AccountHasDebt = 17,
+    InvalidNewOwner = 18,
+    AccountInactive = 19,
+    WithdrawNotAllowed = 20,
+    ZeroAmount = 21,
+    BorrowNotAllowed = 22,
+    AccountHealthy = 23,
+    AccountUnhealthy = 24,
+    LendingPoolNotFound = 25,
+    NoSwappableCollateral = 26,
+    InsufficientCollateral = 27,
+    InvalidParameter = 28,
+    CollateralNotAllowed = 29,
+    AssetCapExceeded = 30,
+    InvalidExpiry = 31,
+    EmptyBatch = 32,
+    InvalidTokenPair = 33,
+    InvalidLeverage = 34,
+    PositionPairMismatch = 35,
+    UnsupportedProtocol = 36,
+    TooManyOpenOrders = 37,
+    InvalidOraclePrice = 38,
+    UnsupportedToken = 39,
+    InvalidTokenCount = 40,
+    AdapterSymbolMismatch = 41,
+    LpTrackingNotConfigured = 42,
+    IsolationBorrowNotAllowed = 43,
+    IsolatedDebtCeilingExceeded = 44,
+    DuplicateToken = 45,
+    BlendPoolDelisted = 46,
+    InvalidBatchCall = 47,
 }
 
 #[contracttype]
@@ -63,6 +122,16 @@ pub struct TraderRepayEvent {
     pub token_symbol: Symbol,
 }
 
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RepayForEvent {
+    pub payer: Address,
+    pub smart_account: Address,
+    pub token_symbol: Symbol,
+    pub token_amount: U256,
+    pub timestamp: u64,
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TraderLiquidateEvent {
@@ -91,4 +160,244 @@ pub struct ExternalProtocolCall {
     pub margin_account: Address,                // Smart account address
     pub fee_fraction: u32,                      // Fee for Aquarius pools (e.g., 30 = 0.3%)
     pub min_liquidity_out: U256,                // Minimum LP tokens to receive (slippage protection)
+    pub min_amounts_out: Vec<U256>,             // Minimum token amounts to receive in WAD (slippage protection)
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AllowedExternalCall {
+    pub protocol_address: Address,
+    pub action: SmartAccExternalAction,
+    pub tokens: Vec<Symbol>, // Token set, matched regardless of order
+}
+
+/// A single step of `AccountManagerContract::execute_batch`. Amounts are in WAD.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BatchCall {
+    DepositCollateral(Symbol, U256),
+    WithdrawCollateral(Symbol, U256),
+    Borrow(Symbol, U256),
+    Repay(Symbol, U256),
+    External(ExternalProtocolCall),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpenPositionRequest {
+    pub collateral_symbol: Symbol,
+    pub collateral_amount_wad: U256,
+    pub borrow_symbol: Symbol,
+    pub target_leverage_wad: U256,
+    pub min_liquidity_out: i128, // Minimum LP units to receive from the venue
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LeveragedPosition {
+    pub collateral_symbol: Symbol,
+    pub borrow_symbol: Symbol,
+    pub liquidity: i128, // LP units held at the venue
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LeveragedPositionEvent {
+    pub smart_account: Address,
+    pub venue: Address,
+    pub collateral_amount: U256,
+    pub borrow_amount: U256,
+    pub liquidity: i128,
+    pub timestamp: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExternalRewardsClaimEvent {
+    pub smart_account: Address,
+    pub protocol: Address,
+    pub reward_token: Address,
+    pub amount_claimed: i128, // Reward token units claimed
+    pub sold_for: Option<Symbol>, // Collateral symbol the rewards were sold for, if any
+    pub amount_out: U256, // Sale proceeds in WAD credited as collateral
+    pub timestamp: u64,
+}
+
+/// Actions a trader can delegate to an operator. `Execute` is scoped to one external action.
+/// Grants only apply to `execute_as_operator`; every other entry point still needs the
+/// trader's signature. Deposits made by an operator are paid from the operator's balance,
+/// since the trader's tokens cannot move without the trader's signature.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OperatorPermission {
+    DepositCollateral,
+    WithdrawCollateral,
+    Borrow,
+    Repay,
+    Execute(SmartAccExternalAction),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OperatorGrant {
+    pub permissions: Vec<OperatorPermission>,
+    pub expiry_ledger: u32, // Last ledger sequence at which the grant is valid
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OperatorGrantEvent {
+    pub smart_account: Address,
+    pub operator: Address,
+    pub permissions: Vec<OperatorPermission>,
+    pub expiry_ledger: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OperatorRevokeEvent {
+    pub smart_account: Address,
+    pub operator: Address,
+    pub timestamp: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OwnershipTransferEvent {
+    pub smart_account: Address,
+    pub previous_owner: Address,
+    pub new_owner: Address,
+    pub timestamp: u64,
+}
+
+/// Condition under which a conditional order may be triggered. Prices and health
+/// ratios are in WAD; the health ratio is total balance value over total debt value.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OrderCondition {
+    PriceBelow(Symbol, U256),
+    PriceAbove(Symbol, U256),
+    HealthBelow(U256),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OrderAction {
+    /// Swap the whole collateral balance of the first token into the second on the given
+    /// venue (receiving at least the given WAD amount) and repay debt in the second token.
+    SwapAndRepay(Address, Symbol, Symbol, U256),
+    /// Repay the given share (basis points) of each lending pool debt out of collateral
+    /// held in the same token.
+    Deleverage(u32),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConditionalOrder {
+    pub order_id: u32,
+    pub condition: OrderCondition,
+    pub action: OrderAction,
+    pub created_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OrderTriggeredEvent {
+    pub smart_account: Address,
+    pub order_id: u32,
+    pub keeper: Address,
+    pub timestamp: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeleverageEvent {
+    pub smart_account: Address,
+    pub collateral_symbol: Symbol,
+    pub debt_symbol: Symbol,
+    pub collateral_sold: U256,
+    pub debt_repaid: U256,
+    pub timestamp: u64,
+}
+
+/// Debt in `borrowed_token` taken while the account held isolated collateral
+/// `isolated_asset`, still counted against that asset's debt ceiling.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IsolatedDebtEntry {
+    pub isolated_asset: Symbol,
+    pub borrowed_token: Symbol,
+    pub amount_wad: U256,
+}
+
+/// Cumulative lending pool flows of an account in `symbol`, in WAD of that token.
+/// Repayments settle accrued interest before principal.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DebtHistory {
+    pub symbol: Symbol,
+    pub principal_borrowed_wad: U256,
+    pub principal_repaid_wad: U256,
+    pub interest_paid_wad: U256,
+}
+
+/// Collateral the trader moved in and out, valued at the oracle price of each transfer.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CollateralHistory {
+    pub deposited_value_wad: U256,
+    pub withdrawn_value_wad: U256,
+}
+
+/// Snapshot returned by `get_account_report`. Values are in WAD at current oracle prices
+/// except `collateral`, which keeps the prices of each transfer. Leverage is the
+/// collateral value over equity.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountReport {
+    pub collateral_value_wad: U256,
+    pub debt_value_wad: U256,
+    pub equity_wad: U256,
+    pub leverage_wad: U256,
+    pub interest_paid_value_wad: U256,
+    pub net_deposits_wad: i128,
+    pub collateral: CollateralHistory,
+    pub debts: Vec<DebtHistory>,
+}
+
+/// Discount given to auction bidders grows by `discount_per_ledger_bps` every ledger
+/// from zero up to `max_discount_bps`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuctionConfig {
+    pub discount_per_ledger_bps: u32,
+    pub max_discount_bps: u32,
+}
+
+/// A deleverage call repays `share_bps` of a debt and accepts swap proceeds down to
+/// `max_slippage_bps` below the oracle value of the collateral sold.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeleverageConfig {
+    pub share_bps: u32,
+    pub max_slippage_bps: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LiquidationAuction {
+    pub start_ledger: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuctionBidEvent {
+    pub smart_account: Address,
+    pub bidder: Address,
+    pub debt_symbol: Symbol,
+    pub debt_repaid: U256,
+    pub collateral_symbol: Symbol,
+    pub collateral_out: U256,
+    pub discount_bps: u32,
+    pub timestamp: u64,
 }
@@ -4,7 +4,7 @@ use crate::errors::{InterestRateError, LendingError};
 use crate::events::{
     LendingDepositEvent, LendingTokenBurnEvent, LendingTokenMintEvent, LendingWithdrawEvent,
 };
-use crate::types::{ContractDetails, PoolDataKey, TokenDataKey};
+use crate::types::{ContractDetails, LenderPosition, PoolDataKey, TokenDataKey};
 use soroban_sdk::{
     Address, Env, String, Symbol, U256, Vec, contract, contractimpl, log, panic_with_error,
     symbol_short, token,
@@ -157,7 +157,7 @@ impl LiquidityPoolUSDC {
     pub fn deposit_usdc(env: Env, lender: Address, amount_wad: U256) {
         lender.require_auth();
         if amount_wad <= U256::from_u128(&env, 0) {
-            panic!("Deposit amount must be positive");
+            panic_with_error!(&env, LendingError::InvalidAmount);
         }
         // Check if pool is initialised
         Self::is_usdc_pool_initialised(&env);
@@ -188,6 +188,11 @@ impl LiquidityPoolUSDC {
 
         // Update lender list
         Self::add_lender_to_list_usdc(&env, &lender);
+        Self::add_lender_flow(
+            &env,
+            PoolDataKey::LenderDepositedWAD(lender.clone()),
+            &amount_wad,
+        );
 
         // Now Mint the VUSDC tokens that were created for the lender
         Self::mint_vusdc_tokens(&env, lender.clone(), vtokens_to_be_minted_wad);
@@ -221,7 +226,7 @@ impl LiquidityPoolUSDC {
 
         // Check if lender has enough token balance to redeem
         if tokens_to_redeem_wad > vusdc_balance_wad_u256 {
-            panic!("Insufficient Token Balance to redeem");
+            panic_with_error!(&env, LendingError::InsufficientVTokenBalance);
         }
 
         let usdc_value_to_transfer_wad =
@@ -252,6 +257,11 @@ impl LiquidityPoolUSDC {
         );
 
         Self::burn_vusdc_tokens(&env, lender.clone(), tokens_to_redeem_wad.clone());
+        Self::add_lender_flow(
+            env,
+            PoolDataKey::LenderWithdrawnWAD(lender.clone()),
+            &usdc_value_to_transfer_wad,
+        );
 
         // emit event after withdraw
         env.events().publish(
@@ -339,13 +349,25 @@ impl LiquidityPoolUSDC {
         account_manager.require_auth();
         Self::update_state(env);
 
-        let borrow_shares_wad = Self::convert_asset_borrow_shares(env, amount_wad.clone());
+        let user_borrow_shares_wad: U256 =
+            Self::get_user_borrow_shares(env, trader_smart_account.clone());
+        // Repayments are capped at the accrued debt; a full repayment burns the exact share
+        // balance so no dust shares remain
+        let user_debt_wad = if user_borrow_shares_wad == U256::from_u32(&env, 0) {
+            user_borrow_shares_wad.clone()
+        } else {
+            Self::convert_borrow_shares_asset(env, user_borrow_shares_wad.clone())
+        };
+        let (amount_wad, borrow_shares_wad) = if amount_wad >= user_debt_wad {
+            (user_debt_wad, user_borrow_shares_wad.clone())
+        } else {
+            let borrow_shares_wad = Self::convert_asset_borrow_shares(env, amount_wad.clone());
+            (amount_wad, borrow_shares_wad)
+        };
         if borrow_shares_wad == U256::from_u32(&env, 0) {
-            panic!("Zero borrow shares");
+            return Err(LendingError::ZeroBorrowShares);
         }
 
-        let user_borrow_shares_wad: U256 =
-            Self::get_user_borrow_shares(env, trader_smart_account.clone());
         let total_borrow_shares_wad: U256 = Self::get_total_borrow_shares(env);
         let key_c = PoolDataKey::BorrowsWAD;
         let borrows_wad: U256 = env
@@ -674,6 +696,53 @@ impl LiquidityPoolUSDC {
         list_address
     }
 
+    pub fn get_lender_position(env: &Env, lender: Address) -> LenderPosition {
+        let vusdc_token_client =
+            vusdc_token_contract::Client::new(env, &Self::get_vtoken_contract_address(env));
+        let vtoken_balance_wad = U256::from_u128(
+            env,
+            Self::scale_for_balance(
+                vusdc_token_client.balance(&lender),
+                vusdc_token_client.decimals(),
+            ) as u128,
+        );
+        let underlying_value_wad = if vtoken_balance_wad == U256::from_u128(env, 0) {
+            U256::from_u128(env, 0)
+        } else {
+            // Share of the cash and the borrows, which include interest accrued until now
+            vtoken_balance_wad
+                .mul(&Self::total_assets(env))
+                .div(&Self::get_current_total_vusdc_balance(env))
+        };
+        let deposited_wad =
+            Self::get_lender_flow(env, PoolDataKey::LenderDepositedWAD(lender.clone()));
+        let withdrawn_wad = Self::get_lender_flow(env, PoolDataKey::LenderWithdrawnWAD(lender));
+        let interest_earned_wad = Self::convert_u256_to_i128(env, &underlying_value_wad)
+            + Self::convert_u256_to_i128(env, &withdrawn_wad)
+            - Self::convert_u256_to_i128(env, &deposited_wad);
+
+        LenderPosition {
+            vtoken_balance_wad,
+            underlying_value_wad,
+            deposited_wad,
+            withdrawn_wad,
+            interest_earned_wad,
+        }
+    }
+
+    fn get_lender_flow(env: &Env, key: PoolDataKey) -> U256 {
+        env.storage()
+            .persistent()
+            .get(&key)
+            .unwrap_or_else(|| U256::from_u128(env, 0))
+    }
+
+    fn add_lender_flow(env: &Env, key: PoolDataKey, amount_wad: &U256) {
+        let total_wad = Self::get_lender_flow(env, key.clone()).add(amount_wad);
+        env.storage().persistent().set(&key, &total_wad);
+        Self::extend_ttl_pooldatakey(env, key);
+    }
+
     pub fn is_usdc_pool_initialised(env: &Env) -> bool {
         if env.storage().persistent().has(&PoolDataKey::Initialised) {
             env.storage()
@@ -681,7 +750,7 @@ impl LiquidityPoolUSDC {
                 .get(&PoolDataKey::Initialised)
                 .unwrap()
         } else {
-            panic!("Lending pool not initialised")
+            panic_with_error!(env, LendingError::PoolNotInitialized)
         }
     }
 
@@ -780,6 +849,11 @@ impl LiquidityPoolUSDC {
             .unwrap_or_else(|| panic_with_error!(&env, LendingError::IntegerConversionError))
     }
 
+    fn convert_u256_to_i128(env: &Env, x: &U256) -> i128 {
+        i128::try_from(Self::convert_u256_to_u128(env, x))
+            .unwrap_or_else(|_| panic_with_error!(&env, LendingError::IntegerConversionError))
+    }
+
     fn get_registry_address(env: &Env) -> Address {
         env.storage()
             .persistent()
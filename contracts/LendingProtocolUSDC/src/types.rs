@@ -1,4 +1,4 @@
-use soroban_sdk::{Address, String, Symbol, contracttype};
+use soroban_sdk::{Address, String, Symbol, U256, contracttype};
 
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[contracttype]
@@ -11,6 +11,8 @@ pub enum PoolDataKey {
     UserBorrowSharesWAD(Address), // Borrow shares of a user
     LastUpdatedTime,              // Last time the pool data was updated
     BorrowsWAD,                   // Total borrowed asset value
+    LenderDepositedWAD(Address),  // Cumulative underlying deposited by a lender
+    LenderWithdrawnWAD(Address),  // Cumulative underlying withdrawn by a lender
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
@@ -35,3 +37,16 @@ pub enum ContractDetails {
     Treasury,
     OriginationFee,
 }
+
+/// A lender's vToken holding and cost basis. `interest_earned_wad` is the current
+/// underlying value plus withdrawals minus deposits, so it also moves with vTokens
+/// transferred in or out of the lender's address.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct LenderPosition {
+    pub vtoken_balance_wad: U256,
+    pub underlying_value_wad: U256,
+    pub deposited_wad: U256,
+    pub withdrawn_wad: U256,
+    pub interest_earned_wad: i128,
+}
@@ -25,6 +25,9 @@ pub enum LendingError {
     LenderNotRegistered = 12,
     InsufficientPoolBalance = 13,
     IntegerConversionError = 14,
+    InvalidAmount = 15,
+    InsufficientVTokenBalance = 16,
+    ZeroBorrowShares = 17,
 }
 
 #[contracterror]
@@ -1,14 +1,29 @@
-use soroban_sdk::{Address, BytesN, Env, Symbol, Vec, contract, contractimpl, symbol_short};
+use soroban_sdk::{
+    Address, BytesN, Env, Symbol, U256, Vec, contract, contractimpl, symbol_short,
+};
 
-use crate::types::{RegistryContractError, RegistryKey};
+use crate::types::{
+    BlendPositionKind, BlendTrackingInfo, CollateralAssetInfo, RegistryContractError, RegistryKey,
+};
 
 const TLL_LEDGERS_YEAR: u32 = 6307200;
 const TLL_LEDGERS_10YEAR: u32 = 6307200 * 10;
 const _TLL_LEDGERS_MONTH: u32 = 518400;
 
+// Account manager client (subset of its interface the registry reads)
+pub mod account_manager_contract {
+    use soroban_sdk::{contractclient, Address, Env, Symbol, Vec};
+
+    #[contractclient(name = "Client")]
+    pub trait AccountManagerTrait {
+        fn get_collateral_holders(env: Env, token_symbol: Symbol) -> Vec<Address>;
+    }
+}
+
 #[contract]
 pub struct RegistryContract;
 const ADMIN: Symbol = symbol_short!("admin");
+const BLEND_UNDERLYINGS: [&str; 3] = ["XLM", "USDC", "EURC"];
 
 #[contractimpl]
 impl RegistryContract {
@@ -193,6 +208,33 @@ impl RegistryContract {
         let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
         admin.require_auth();
 
+        // The first primary pool gets id 0 and the original BLEND_* tracking symbols. A
+        // replacement gets a fresh id and the pool it replaces is delisted, so positions
+        // still open there keep their symbols
+        let mut pools = Self::get_blend_pools(env);
+        let previous: Option<Address> =
+            env.storage().persistent().get(&RegistryKey::BlendPoolContract);
+        if let Some(previous) = &previous
+            && *previous != blend_pool_address
+            && let Some(index) = pools.first_index_of(previous)
+        {
+            pools.remove(index);
+            Self::set_blend_pool_delisted(env, previous, true);
+        }
+        if Self::get_blend_pool_id(env, &blend_pool_address).is_none() {
+            let pool_id = if previous.is_some() {
+                Self::next_blend_pool_id(env)
+            } else {
+                0
+            };
+            Self::register_blend_pool(env, &blend_pool_address, pool_id);
+        }
+        Self::set_blend_pool_delisted(env, &blend_pool_address, false);
+        if !pools.contains(&blend_pool_address) {
+            pools.push_front(blend_pool_address.clone());
+        }
+        Self::set_blend_pools(env, pools);
+
         env.storage()
             .persistent()
             .set(&RegistryKey::BlendPoolContract, &blend_pool_address);
@@ -233,6 +275,21 @@ impl RegistryContract {
         Ok(())
     }
 
+    pub fn set_soroswap_router_address(
+        env: &Env,
+        soroswap_router_address: Address,
+    ) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        env.storage()
+            .persistent()
+            .set(&RegistryKey::SoroswapContract, &soroswap_router_address);
+        Self::extend_ttl_registry(env, RegistryKey::SoroswapContract);
+
+        Ok(())
+    }
+
     pub fn set_aquarius_pool_index(
         env: &Env,
         pool_index: BytesN<32>,
@@ -248,12 +305,362 @@ impl RegistryContract {
         Ok(())
     }
 
+    /// Sets the Aquarius pool used to swap between `token_a` and `token_b`, in either
+    /// direction. Pairs without their own pool fall back to the XLM-USDC pool index.
+    pub fn set_aquarius_pair_pool_index(
+        env: &Env,
+        token_a: Symbol,
+        token_b: Symbol,
+        pool_index: BytesN<32>,
+    ) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        for key in [
+            RegistryKey::AquariusPairPoolIndex(token_a.clone(), token_b.clone()),
+            RegistryKey::AquariusPairPoolIndex(token_b, token_a),
+        ] {
+            env.storage().persistent().set(&key, &pool_index);
+            Self::extend_ttl_registry(env, key);
+        }
+
+        Ok(())
+    }
+
+    /// Whitelists an additional Blend pool and returns the id used to namespace its
+    /// tracking symbols (e.g. `BLEND2_XLM`, `BLEND2_COLL_XLM`, `BLEND2_DEBT_XLM`).
+    pub fn add_blend_pool(env: &Env, blend_pool_address: Address) -> Result<u32, RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        let mut pools = Self::get_blend_pools(env);
+        if pools.contains(&blend_pool_address) {
+            return Err(RegistryContractError::BlendPoolAlreadyListed);
+        }
+
+        // A delisted pool is relisted under its original id
+        let pool_id = match Self::get_blend_pool_id(env, &blend_pool_address) {
+            Some(pool_id) => pool_id,
+            None => {
+                let pool_id = Self::next_blend_pool_id(env);
+                Self::register_blend_pool(env, &blend_pool_address, pool_id);
+                pool_id
+            }
+        };
+        Self::set_blend_pool_delisted(env, &blend_pool_address, false);
+        pools.push_back(blend_pool_address);
+        Self::set_blend_pools(env, pools);
+
+        Ok(pool_id)
+    }
+
+    /// Stops routing new positions to a Blend pool. Its tracking symbols stay mapped so
+    /// open positions keep being valued, and accounts can still withdraw and repay there.
+    pub fn remove_blend_pool(env: &Env, blend_pool_address: Address) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        let mut pools = Self::get_blend_pools(env);
+        let index = pools
+            .first_index_of(&blend_pool_address)
+            .ok_or(RegistryContractError::BlendPoolNotFound)?;
+        pools.remove(index);
+        Self::set_blend_pools(env, pools);
+        Self::set_blend_pool_delisted(env, &blend_pool_address, true);
+
+        Ok(())
+    }
+
+    /// Lists a Stellar Asset Contract under `symbol` so it can back margin accounts. Listing
+    /// an already listed symbol updates its token address and ticker.
+    pub fn list_collateral_asset(
+        env: &Env,
+        symbol: Symbol,
+        token_address: Address,
+        oracle_ticker: Symbol,
+    ) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        let key = RegistryKey::CollateralAsset(symbol.clone());
+        env.storage().persistent().set(
+            &key,
+            &CollateralAssetInfo {
+                token_address,
+                oracle_ticker,
+            },
+        );
+        Self::extend_ttl_registry(env, key);
+
+        let mut assets = Self::get_collateral_assets(env);
+        if !assets.contains(&symbol) {
+            assets.push_back(symbol);
+            Self::set_collateral_assets(env, assets);
+        }
+
+        Ok(())
+    }
+
+    /// Removes a listed collateral asset. Refused while any margin account still holds it,
+    /// as its tokens could no longer be withdrawn or priced.
+    pub fn delist_collateral_asset(env: &Env, symbol: Symbol) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        if let Some(account_manager) = env
+            .storage()
+            .persistent()
+            .get::<_, Address>(&RegistryKey::AccountManagerContract)
+            && !account_manager_contract::Client::new(env, &account_manager)
+                .get_collateral_holders(&symbol)
+                .is_empty()
+        {
+            return Err(RegistryContractError::CollateralAssetInUse);
+        }
+
+        let mut assets = Self::get_collateral_assets(env);
+        let index = assets
+            .first_index_of(&symbol)
+            .ok_or(RegistryContractError::CollateralAssetNotFound)?;
+        assets.remove(index);
+        Self::set_collateral_assets(env, assets);
+        env.storage()
+            .persistent()
+            .remove(&RegistryKey::CollateralAsset(symbol));
+
+        Ok(())
+    }
+
+    pub fn get_collateral_asset(env: &Env, symbol: Symbol) -> Option<CollateralAssetInfo> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::CollateralAsset(symbol))
+    }
+
+    pub fn get_collateral_assets(env: &Env) -> Vec<Symbol> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::CollateralAssetsList)
+            .unwrap_or(Vec::new(env))
+    }
+
+    /// Marks `symbol` as isolated collateral. Accounts holding it may only borrow the
+    /// isolation-borrowable stablecoins, counted at par, up to `debt_ceiling_wad` in total.
+    pub fn set_isolated_asset(
+        env: &Env,
+        symbol: Symbol,
+        debt_ceiling_wad: U256,
+    ) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        let key = RegistryKey::IsolationDebtCeiling(symbol);
+        env.storage().persistent().set(&key, &debt_ceiling_wad);
+        Self::extend_ttl_registry(env, key);
+        Ok(())
+    }
+
+    pub fn remove_isolated_asset(env: &Env, symbol: Symbol) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        let key = RegistryKey::IsolationDebtCeiling(symbol);
+        if !env.storage().persistent().has(&key) {
+            return Err(RegistryContractError::IsolatedAssetNotFound);
+        }
+        env.storage().persistent().remove(&key);
+        Ok(())
+    }
+
+    pub fn set_isolation_borrowable(
+        env: &Env,
+        symbol: Symbol,
+        borrowable: bool,
+    ) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        let mut assets = Self::get_isolation_borrowable_assets(env);
+        let index = assets.first_index_of(&symbol);
+        match (borrowable, index) {
+            (true, None) => assets.push_back(symbol),
+            (false, Some(index)) => {
+                assets.remove(index);
+            }
+            _ => return Ok(()),
+        }
+        let key = RegistryKey::IsolationBorrowableAssets;
+        env.storage().persistent().set(&key, &assets);
+        Self::extend_ttl_registry(env, key);
+        Ok(())
+    }
+
+    pub fn get_isolation_debt_ceiling(env: &Env, symbol: Symbol) -> Option<U256> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::IsolationDebtCeiling(symbol))
+    }
+
+    pub fn get_isolated_debt(env: &Env, symbol: Symbol) -> U256 {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::IsolatedDebt(symbol))
+            .unwrap_or(U256::from_u32(env, 0))
+    }
+
+    pub fn get_isolation_borrowable_assets(env: &Env) -> Vec<Symbol> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::IsolationBorrowableAssets)
+            .unwrap_or(Vec::new(env))
+    }
+
+    pub fn increase_isolated_debt(
+        env: &Env,
+        symbol: Symbol,
+        amount_wad: U256,
+    ) -> Result<(), RegistryContractError> {
+        let acc_manager = Self::get_accountmanager_contract(env)?;
+        acc_manager.require_auth();
+
+        let debt = Self::get_isolated_debt(env, symbol.clone()).add(&amount_wad);
+        Self::set_isolated_debt(env, symbol, debt);
+        Ok(())
+    }
+
+    /// Lowers the debt counted against `symbol`'s ceiling, stopping at zero.
+    pub fn decrease_isolated_debt(
+        env: &Env,
+        symbol: Symbol,
+        amount_wad: U256,
+    ) -> Result<(), RegistryContractError> {
+        let acc_manager = Self::get_accountmanager_contract(env)?;
+        acc_manager.require_auth();
+
+        let debt = Self::get_isolated_debt(env, symbol.clone());
+        let debt = if amount_wad < debt {
+            debt.sub(&amount_wad)
+        } else {
+            U256::from_u32(env, 0)
+        };
+        Self::set_isolated_debt(env, symbol, debt);
+        Ok(())
+    }
+
+    pub fn set_blend_reward_token_address(
+        env: &Env,
+        reward_token_address: Address,
+    ) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        env.storage()
+            .persistent()
+            .set(&RegistryKey::BlendRewardToken, &reward_token_address);
+        Self::extend_ttl_registry(env, RegistryKey::BlendRewardToken);
+
+        Ok(())
+    }
+
+    pub fn set_blend_reward_pool_index(
+        env: &Env,
+        pool_index: BytesN<32>,
+    ) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        env.storage()
+            .persistent()
+            .set(&RegistryKey::BlendRewardPoolIndex, &pool_index);
+        Self::extend_ttl_registry(env, RegistryKey::BlendRewardPoolIndex);
+
+        Ok(())
+    }
+
+    /// Whitelists a protocol adapter together with the tracking symbols it is allowed
+    /// to mint. Re-registering an adapter replaces its previous symbol set.
+    pub fn set_protocol_adapter(
+        env: &Env,
+        adapter_address: Address,
+        tracking_symbols: Vec<Symbol>,
+    ) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        for symbol in tracking_symbols.iter() {
+            let owner: Option<Address> = env
+                .storage()
+                .persistent()
+                .get(&RegistryKey::TrackingSymbolAdapter(symbol.clone()));
+            if let Some(owner) = owner
+                && owner != adapter_address
+            {
+                return Err(RegistryContractError::TrackingSymbolAlreadyMapped);
+            }
+        }
+
+        Self::clear_adapter_symbols(env, &adapter_address);
+
+        for symbol in tracking_symbols.iter() {
+            env.storage().persistent().set(
+                &RegistryKey::TrackingSymbolAdapter(symbol.clone()),
+                &adapter_address,
+            );
+            Self::extend_ttl_registry(env, RegistryKey::TrackingSymbolAdapter(symbol));
+        }
+
+        env.storage().persistent().set(
+            &RegistryKey::ProtocolAdapter(adapter_address.clone()),
+            &tracking_symbols,
+        );
+        Self::extend_ttl_registry(env, RegistryKey::ProtocolAdapter(adapter_address.clone()));
+
+        let mut adapters = Self::get_protocol_adapters(env);
+        if !adapters.contains(adapter_address.clone()) {
+            adapters.push_back(adapter_address);
+            env.storage()
+                .persistent()
+                .set(&RegistryKey::ProtocolAdaptersList, &adapters);
+            Self::extend_ttl_registry(env, RegistryKey::ProtocolAdaptersList);
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_protocol_adapter(
+        env: &Env,
+        adapter_address: Address,
+    ) -> Result<(), RegistryContractError> {
+        let admin: Address = env.storage().persistent().get(&ADMIN).unwrap();
+        admin.require_auth();
+
+        if !Self::is_protocol_adapter(env, adapter_address.clone()) {
+            return Err(RegistryContractError::ProtocolAdapterNotFound);
+        }
+
+        Self::clear_adapter_symbols(env, &adapter_address);
+        env.storage()
+            .persistent()
+            .remove(&RegistryKey::ProtocolAdapter(adapter_address.clone()));
+
+        let mut adapters = Self::get_protocol_adapters(env);
+        if let Some(index) = adapters.first_index_of(adapter_address) {
+            adapters.remove(index);
+            env.storage()
+                .persistent()
+                .set(&RegistryKey::ProtocolAdaptersList, &adapters);
+            Self::extend_ttl_registry(env, RegistryKey::ProtocolAdaptersList);
+        }
+
+        Ok(())
+    }
+
     pub fn get_lendingpool_xlm(env: &Env) -> Result<Address, RegistryContractError> {
         let res: Address = env
             .storage()
             .persistent()
             .get(&RegistryKey::LendingPoolXlm)
-            .unwrap_or_else(|| panic!("Failed to get lendingpool_xlm address"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
 
@@ -262,7 +669,7 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::SmartAccountContractHash)
-            .unwrap_or_else(|| panic!("Failed to get smart account hash"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
 
@@ -271,7 +678,7 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::AccountManagerContract)
-            .unwrap_or_else(|| panic!("Failed to get account_manager address"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
 
@@ -280,7 +687,7 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::LendingPoolEurc)
-            .unwrap_or_else(|| panic!("Failed to get lendingpool_eurc address"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
 
@@ -289,7 +696,7 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::LendingPoolUsdc)
-            .unwrap_or_else(|| panic!("Failed to get lendingpool_usdc address"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
 
@@ -298,7 +705,7 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::RiskEngineContract)
-            .unwrap_or_else(|| panic!("Failed to get risk_enginer contract address"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
 
@@ -307,7 +714,7 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::RateModelContract)
-            .unwrap_or_else(|| panic!("Failed to get rate_model contract address"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
 
@@ -316,7 +723,7 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::OracleContract)
-            .unwrap_or_else(|| panic!("Failed to get oracle contract address"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
 
@@ -325,7 +732,7 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::NativeXlmContractAddress)
-            .expect("Failed to fetch native token contract address for XLM");
+            .ok_or(RegistryContractError::AddressNotSet)?;
 
         Ok(token_contract_address)
     }
@@ -335,7 +742,7 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::UsdcContractAddress)
-            .expect("Failed to fetch token contract address for USDC");
+            .ok_or(RegistryContractError::AddressNotSet)?;
 
         Ok(token_contract_address)
     }
@@ -345,7 +752,7 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::BlendPoolContract)
-            .unwrap_or_else(|| panic!("Failed to get blend_pool contract address"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
     
@@ -356,12 +763,53 @@ impl RegistryContract {
             .has(&RegistryKey::BlendPoolContract)
     }
 
+    pub fn get_blend_pools(env: &Env) -> Vec<Address> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::BlendPoolsList)
+            .unwrap_or(Vec::new(env))
+    }
+
+    pub fn is_blend_pool(env: &Env, blend_pool_address: Address) -> bool {
+        Self::get_blend_pools(env).contains(&blend_pool_address)
+    }
+
+    /// True for Blend pools that were removed or replaced and only accept exit-only calls.
+    pub fn is_delisted_blend_pool(env: &Env, blend_pool_address: Address) -> bool {
+        Self::get_delisted_blend_pools(env).contains(&blend_pool_address)
+    }
+
+    pub fn get_delisted_blend_pools(env: &Env) -> Vec<Address> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::DelistedBlendPoolsList)
+            .unwrap_or(Vec::new(env))
+    }
+
+    pub fn get_blend_tracking_symbol(
+        env: &Env,
+        blend_pool_address: Address,
+        underlying: Symbol,
+        kind: BlendPositionKind,
+    ) -> Result<Symbol, RegistryContractError> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::BlendTrackingSymbol(blend_pool_address, underlying, kind))
+            .ok_or(RegistryContractError::BlendPoolNotFound)
+    }
+
+    pub fn get_blend_tracking_info(env: &Env, tracking_symbol: Symbol) -> Option<BlendTrackingInfo> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::BlendTrackingInfo(tracking_symbol))
+    }
+
     pub fn get_aquarius_router_address(env: &Env) -> Result<Address, RegistryContractError> {
         let res: Address = env
             .storage()
             .persistent()
             .get(&RegistryKey::AquariusRouterContract)
-            .unwrap_or_else(|| panic!("Failed to get aquarius_router contract address"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
     
@@ -372,12 +820,62 @@ impl RegistryContract {
             .has(&RegistryKey::AquariusRouterContract)
     }
 
+    pub fn get_soroswap_router_address(env: &Env) -> Result<Address, RegistryContractError> {
+        let res: Address = env
+            .storage()
+            .persistent()
+            .get(&RegistryKey::SoroswapContract)
+            .ok_or(RegistryContractError::AddressNotSet)?;
+        Ok(res)
+    }
+
+    pub fn has_soroswap_router_address(env: &Env) -> bool {
+        env
+            .storage()
+            .persistent()
+            .has(&RegistryKey::SoroswapContract)
+    }
+
     pub fn get_aquarius_pool_index(env: &Env) -> Result<BytesN<32>, RegistryContractError> {
         let res: BytesN<32> = env
             .storage()
             .persistent()
             .get(&RegistryKey::AquariusPoolIndex)
-            .unwrap_or_else(|| panic!("Failed to get aquarius pool index"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
+        Ok(res)
+    }
+
+    /// Aquarius pool index to swap `token_a` against `token_b` on.
+    pub fn get_aquarius_pair_pool_index(
+        env: &Env,
+        token_a: Symbol,
+        token_b: Symbol,
+    ) -> Result<BytesN<32>, RegistryContractError> {
+        match env
+            .storage()
+            .persistent()
+            .get(&RegistryKey::AquariusPairPoolIndex(token_a, token_b))
+        {
+            Some(pool_index) => Ok(pool_index),
+            None => Self::get_aquarius_pool_index(env),
+        }
+    }
+
+    pub fn get_blend_reward_token_address(env: &Env) -> Result<Address, RegistryContractError> {
+        let res: Address = env
+            .storage()
+            .persistent()
+            .get(&RegistryKey::BlendRewardToken)
+            .ok_or(RegistryContractError::AddressNotSet)?;
+        Ok(res)
+    }
+
+    pub fn get_blend_reward_pool_index(env: &Env) -> Result<BytesN<32>, RegistryContractError> {
+        let res: BytesN<32> = env
+            .storage()
+            .persistent()
+            .get(&RegistryKey::BlendRewardPoolIndex)
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
 
@@ -386,20 +884,66 @@ impl RegistryContract {
             .storage()
             .persistent()
             .get(&RegistryKey::TrackingTokenContract)
-            .unwrap_or_else(|| panic!("Failed to get tracking_token contract address"));
+            .ok_or(RegistryContractError::AddressNotSet)?;
         Ok(res)
     }
 
+    pub fn has_tracking_token_contract_addr(env: &Env) -> bool {
+        env
+            .storage()
+            .persistent()
+            .has(&RegistryKey::TrackingTokenContract)
+    }
+
     pub fn get_eurc_contract_address(env: &Env) -> Result<Address, RegistryContractError> {
         let token_contract_address: Address = env
             .storage()
             .persistent()
             .get(&RegistryKey::EurcContractAddress)
-            .expect("Failed to fetch token contract address for EURC");
+            .ok_or(RegistryContractError::AddressNotSet)?;
 
         Ok(token_contract_address)
     }
 
+    pub fn is_protocol_adapter(env: &Env, adapter_address: Address) -> bool {
+        env.storage()
+            .persistent()
+            .has(&RegistryKey::ProtocolAdapter(adapter_address))
+    }
+
+    pub fn get_adapter_tracking_symbols(
+        env: &Env,
+        adapter_address: Address,
+    ) -> Result<Vec<Symbol>, RegistryContractError> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::ProtocolAdapter(adapter_address))
+            .ok_or(RegistryContractError::ProtocolAdapterNotFound)
+    }
+
+    pub fn has_tracking_symbol_adapter(env: &Env, tracking_symbol: Symbol) -> bool {
+        env.storage()
+            .persistent()
+            .has(&RegistryKey::TrackingSymbolAdapter(tracking_symbol))
+    }
+
+    pub fn get_tracking_symbol_adapter(
+        env: &Env,
+        tracking_symbol: Symbol,
+    ) -> Result<Address, RegistryContractError> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::TrackingSymbolAdapter(tracking_symbol))
+            .ok_or(RegistryContractError::ProtocolAdapterNotFound)
+    }
+
+    pub fn get_protocol_adapters(env: &Env) -> Vec<Address> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::ProtocolAdaptersList)
+            .unwrap_or(Vec::new(env))
+    }
+
     pub fn get_admin(env: &Env) -> Result<Address, RegistryContractError> {
         env.storage()
             .persistent()
@@ -467,6 +1011,12 @@ impl RegistryContract {
         Ok(true)
     }
 
+    fn set_isolated_debt(env: &Env, symbol: Symbol, debt_wad: U256) {
+        let key = RegistryKey::IsolatedDebt(symbol);
+        env.storage().persistent().set(&key, &debt_wad);
+        Self::extend_ttl_registry(env, key);
+    }
+
     fn set_smart_accounts_list(env: &Env, list: Vec<Address>) {
         env.storage()
             .persistent()
@@ -474,6 +1024,144 @@ impl RegistryContract {
         Self::extend_ttl_registry(&env, RegistryKey::SmartAccountsList);
     }
 
+    fn clear_adapter_symbols(env: &Env, adapter_address: &Address) {
+        let previous: Vec<Symbol> = env
+            .storage()
+            .persistent()
+            .get(&RegistryKey::ProtocolAdapter(adapter_address.clone()))
+            .unwrap_or(Vec::new(env));
+        for symbol in previous.iter() {
+            env.storage()
+                .persistent()
+                .remove(&RegistryKey::TrackingSymbolAdapter(symbol));
+        }
+    }
+
+    fn set_blend_pools(env: &Env, pools: Vec<Address>) {
+        env.storage()
+            .persistent()
+            .set(&RegistryKey::BlendPoolsList, &pools);
+        Self::extend_ttl_registry(env, RegistryKey::BlendPoolsList);
+    }
+
+    fn set_collateral_assets(env: &Env, assets: Vec<Symbol>) {
+        env.storage()
+            .persistent()
+            .set(&RegistryKey::CollateralAssetsList, &assets);
+        Self::extend_ttl_registry(env, RegistryKey::CollateralAssetsList);
+    }
+
+    /// Maps every (underlying, position kind) of a pool to its tracking symbol and back.
+    fn get_blend_pool_id(env: &Env, blend_pool_address: &Address) -> Option<u32> {
+        env.storage()
+            .persistent()
+            .get(&RegistryKey::BlendPoolId(blend_pool_address.clone()))
+    }
+
+    fn next_blend_pool_id(env: &Env) -> u32 {
+        let pool_id: u32 = env
+            .storage()
+            .persistent()
+            .get(&RegistryKey::BlendPoolCounter)
+            .unwrap_or(0)
+            + 1;
+        env.storage()
+            .persistent()
+            .set(&RegistryKey::BlendPoolCounter, &pool_id);
+        Self::extend_ttl_registry(env, RegistryKey::BlendPoolCounter);
+        pool_id
+    }
+
+    fn set_blend_pool_delisted(env: &Env, blend_pool_address: &Address, delisted: bool) {
+        let mut delisted_pools = Self::get_delisted_blend_pools(env);
+        let index = delisted_pools.first_index_of(blend_pool_address);
+        match (index, delisted) {
+            (None, true) => delisted_pools.push_back(blend_pool_address.clone()),
+            (Some(index), false) => {
+                delisted_pools.remove(index);
+            }
+            _ => return,
+        }
+        env.storage()
+            .persistent()
+            .set(&RegistryKey::DelistedBlendPoolsList, &delisted_pools);
+        Self::extend_ttl_registry(env, RegistryKey::DelistedBlendPoolsList);
+    }
+
+    fn register_blend_pool(env: &Env, blend_pool_address: &Address, pool_id: u32) {
+        env.storage()
+            .persistent()
+            .set(&RegistryKey::BlendPoolId(blend_pool_address.clone()), &pool_id);
+        Self::extend_ttl_registry(env, RegistryKey::BlendPoolId(blend_pool_address.clone()));
+
+        for underlying in BLEND_UNDERLYINGS {
+            for kind in [
+                BlendPositionKind::Supply,
+                BlendPositionKind::Collateral,
+                BlendPositionKind::Liability,
+            ] {
+                let tracking_symbol = Self::blend_tracking_symbol(env, pool_id, &kind, underlying);
+                let underlying_symbol = Symbol::new(env, underlying);
+                let symbol_key = RegistryKey::BlendTrackingSymbol(
+                    blend_pool_address.clone(),
+                    underlying_symbol.clone(),
+                    kind.clone(),
+                );
+                env.storage().persistent().set(&symbol_key, &tracking_symbol);
+                Self::extend_ttl_registry(env, symbol_key);
+
+                let info_key = RegistryKey::BlendTrackingInfo(tracking_symbol);
+                env.storage().persistent().set(
+                    &info_key,
+                    &BlendTrackingInfo {
+                        pool: blend_pool_address.clone(),
+                        underlying: underlying_symbol,
+                        kind,
+                    },
+                );
+                Self::extend_ttl_registry(env, info_key);
+            }
+        }
+    }
+
+    /// Builds `BLEND{id}[_COLL|_DEBT]_{underlying}`; the id is omitted for the primary pool.
+    fn blend_tracking_symbol(
+        env: &Env,
+        pool_id: u32,
+        kind: &BlendPositionKind,
+        underlying: &str,
+    ) -> Symbol {
+        let mut buf = [0u8; 32];
+        let mut len = 0;
+        let mut push = |bytes: &[u8], len: &mut usize| {
+            buf[*len..*len + bytes.len()].copy_from_slice(bytes);
+            *len += bytes.len();
+        };
+
+        push(b"BLEND", &mut len);
+        if pool_id > 0 {
+            let mut digits = [0u8; 10];
+            let mut n = pool_id;
+            let mut count = 0;
+            while n > 0 {
+                digits[count] = b'0' + (n % 10) as u8;
+                n /= 10;
+                count += 1;
+            }
+            digits[..count].reverse();
+            push(&digits[..count], &mut len);
+        }
+        match kind {
+            BlendPositionKind::Supply => {}
+            BlendPositionKind::Collateral => push(b"_COLL", &mut len),
+            BlendPositionKind::Liability => push(b"_DEBT", &mut len),
+        }
+        push(b"_", &mut len);
+        push(underlying.as_bytes(), &mut len);
+
+        Symbol::new(env, core::str::from_utf8(&buf[..len]).unwrap())
+    }
+
     fn extend_ttl_registry(env: &Env, key: RegistryKey) {
         env.storage()
             .persistent()
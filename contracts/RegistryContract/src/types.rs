@@ -1,12 +1,20 @@
-use soroban_sdk::Address;
+use soroban_sdk::{Address, Symbol};
 use soroban_sdk::{contracterror, contracttype};
 
 #[contracterror]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum RegistryContractError {
     CollateralTokenNotFound = 1,
     BorrowedTokenNotFound = 2,
     MarginAccountNotFound = 3,
+    ProtocolAdapterNotFound = 4,
+    TrackingSymbolAlreadyMapped = 5,
+    BlendPoolAlreadyListed = 6,
+    BlendPoolNotFound = 7,
+    AddressNotSet = 8,
+    CollateralAssetNotFound = 9,
+    IsolatedAssetNotFound = 10,
+    CollateralAssetInUse = 11,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
@@ -29,7 +37,49 @@ pub enum RegistryKey {
     OwnerAddress(Address), // Traders address for respective margin account
     BlendPoolContract,     // Blend Pool Contract Address
     TrackingTokenContract, // Tracking token contract address
-    SoroswapContract,
+    SoroswapContract,           // Soroswap Router Contract Address
     AquariusRouterContract,     // Aquarius Router Contract Address
     AquariusPoolIndex,          // Aquarius Pool Index for XLM-USDC
+    AquariusPairPoolIndex(Symbol, Symbol), // Aquarius pool index used to swap a specific pair
+    ProtocolAdapter(Address),   // Tracking symbols owned by a whitelisted protocol adapter
+    TrackingSymbolAdapter(Symbol), // Adapter responsible for valuing a tracking symbol
+    ProtocolAdaptersList,       // List of all whitelisted protocol adapters
+    BlendRewardToken,           // BLND emissions token paid out by the Blend pool
+    BlendRewardPoolIndex,       // Aquarius pool index used to sell BLND emissions
+    BlendPoolsList,             // All whitelisted Blend pools
+    BlendPoolCounter,           // Last id handed out to an added Blend pool
+    BlendPoolId(Address),       // Id used to namespace a Blend pool's tracking symbols
+    DelistedBlendPoolsList,     // Removed Blend pools that still accept exit-only calls
+    BlendTrackingSymbol(Address, Symbol, BlendPositionKind), // (pool, underlying, kind) -> tracking symbol
+    BlendTrackingInfo(Symbol),  // Tracking symbol -> pool, underlying and position kind
+    CollateralAsset(Symbol),    // Listed SAC collateral -> token address, oracle ticker
+    CollateralAssetsList,       // Symbols of all listed SAC collateral assets
+    IsolationDebtCeiling(Symbol), // Isolated collateral -> max debt borrowed against it, in WAD
+    IsolatedDebt(Symbol),       // Isolated collateral -> debt currently counted against its ceiling
+    IsolationBorrowableAssets,  // Stablecoins isolated accounts may borrow
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[contracttype]
+pub enum BlendPositionKind {
+    Supply,     // b-tokens supplied without collateral
+    Collateral, // b-tokens supplied as collateral
+    Liability,  // d-tokens borrowed
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct BlendTrackingInfo {
+    pub pool: Address,
+    pub underlying: Symbol,
+    pub kind: BlendPositionKind,
+}
+
+/// Stellar Asset Contract accepted as collateral in addition to the built-in XLM, USDC and
+/// EURC. `oracle_ticker` is the symbol the oracle prices it under.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct CollateralAssetInfo {
+    pub token_address: Address,
+    pub oracle_ticker: Symbol,
 }
@@ -1,14 +1,25 @@
 use soroban_sdk::{Address, Env, Map, Symbol, U256, Vec, contract, symbol_short, token};
-use soroban_sdk::{contractimpl, log};
+use soroban_sdk::{contractimpl, log, panic_with_error};
 
 use blend_contract_sdk::pool::Client as BlendPoolClient;
 
 use crate::types::RiskEngineError;
 use crate::types::RiskEngineKey;
+use crate::types::{
+    AccountBreakdown, AccountLimits, AssetBreakdown, CollateralFactorRamp, EModeCategory,
+    SimulatedAction,
+};
+use registry_contract::{BlendPositionKind, BlendTrackingInfo};
 
 // 1.1 * e18
 pub const BALANCE_TO_BORROW_THRESHOLD: u128 = 11_0000000_00000_00000;
+// 1.25 * e18, upper bound of the soft-liquidation zone unless configured
+pub const DEFAULT_DELEVERAGE_THRESHOLD: u128 = 1_250_000_000_000_000_000;
 pub const WAD_U128: u128 = 10000_0000_00000_00000; //1e18
+// Health factor reported for accounts without debt
+pub const NO_DEBT_HEALTH_FACTOR: u128 = u128::MAX;
+// Share of a collateral asset's value counted toward health unless ramped down
+pub const FULL_COLLATERAL_FACTOR_BPS: u32 = 10_000;
 const SCALAR_12_U128: u128 = 1_000_000_000_000; // 1e12 for Blend b_rate
 const TLL_LEDGERS_YEAR: u32 = 6307200;
 const TLL_LEDGERS_10YEAR: u32 = 6307200 * 10;
@@ -16,9 +27,11 @@ const _TLL_LEDGERS_MONTH: u32 = 518400;
 const XLM_SYMBOL: Symbol = symbol_short!("XLM");
 const USDC_SYMBOL: Symbol = symbol_short!("USDC");
 const EURC_SYMBOL: Symbol = symbol_short!("EURC");
-const BLEND_XLM: &str = "BLEND_XLM";
-const BLEND_USDC: &str = "BLEND_USDC";
-const BLEND_EURC: &str = "BLEND_EURC";
+// LP tracking symbols minted by the account manager, see AccountManagerContract
+const AQUARIUS_XLM_USDC: &str = "AQ_XLM_USDC";
+const SOROSWAP_XLM_USDC: &str = "SS_XLM_USDC";
+const SOROSWAP_XLM_EURC: &str = "SS_XLM_EURC";
+const SOROSWAP_USDC_EURC: &str = "SS_USDC_EURC";
 
 #[contract]
 pub struct RiskEngineContract;
@@ -51,19 +64,48 @@ impl RiskEngineContract {
         let collateral_tokens = smart_account_client.get_all_collateral_tokens();
         let borrowed_tokens = smart_account_client.get_all_borrowed_tokens();
 
-        // Build price cache — fetch each unique symbol from oracle exactly once
-        let mut price_cache: Map<Symbol, u128> = Map::new(env);
-        Self::cache_price(env, &oracle_client, &symbol, &mut price_cache);
-        for token in collateral_tokens.iter() {
-            if !Self::is_blend_tracking_symbol(env, &token) {
-                Self::cache_price(env, &oracle_client, &token, &mut price_cache);
+        if let Some(isolated_symbol) = Self::isolated_collateral(
+            env,
+            &registry_client,
+            &smart_account_client,
+            &collateral_tokens,
+        ) {
+            if !registry_client
+                .get_isolation_borrowable_assets()
+                .contains(&symbol)
+            {
+                return Err(RiskEngineError::IsolationBorrowNotAllowed);
+            }
+            let ceiling_wad = registry_client
+                .get_isolation_debt_ceiling(&isolated_symbol)
+                .unwrap_or(U256::from_u128(env, 0));
+            if registry_client
+                .get_isolated_debt(&isolated_symbol)
+                .add(&borrow_amount_wad)
+                > ceiling_wad
+            {
+                return Err(RiskEngineError::IsolatedDebtCeilingExceeded);
             }
         }
-        for token in borrowed_tokens.iter() {
-            Self::cache_price(env, &oracle_client, &token, &mut price_cache);
-        }
+        let mut debt_symbols = borrowed_tokens.clone();
+        debt_symbols.push_back(symbol.clone());
+        let emode_factor_bps = Self::account_emode(env, &collateral_tokens, &debt_symbols)
+            .map(|(_, factor_bps)| factor_bps);
+
+        let mut price_cache: Map<Symbol, u128> = Map::new(env);
+        let assets = Self::collect_breakdown(
+            env,
+            &registry_client,
+            &oracle_client,
+            &margin_account,
+            emode_factor_bps,
+            &mut price_cache,
+        );
+        let (total_balance_wad, total_debt_wad) = Self::breakdown_totals(env, &assets);
+        let (balance_value_wad, debt_value_wad) = Self::breakdown_values(env, &assets);
 
         // Borrow value
+        Self::cache_price(env, &oracle_client, &symbol, &mut price_cache);
         let borrow_price_wad = price_cache.get(symbol.clone()).unwrap_or(0);
         let borrow_value_wad = Self::mul_wad_down(
             env,
@@ -71,55 +113,45 @@ impl RiskEngineContract {
             U256::from_u128(env, borrow_price_wad),
         );
 
-        // Total collateral value (using cached prices)
-        let mut total_balance_wad = U256::from_u128(env, 0);
-        for token in collateral_tokens.iter() {
-            let (token_balance_wad, price_symbol) = if Self::is_blend_tracking_symbol(env, &token) {
-                let tracking_addr = registry_client.get_tracking_token_contract_addr();
-                let tracking_client = tracking_token_contract::Client::new(env, &tracking_addr);
-                let blend_pool_addr = registry_client.get_blend_pool_address();
-                let blend_client = BlendPoolClient::new(env, &blend_pool_addr);
-                let (underlying_sym, underlying_addr, underlying_dec) =
-                    Self::blend_underlying_info(env, &registry_client, &token);
-                let b_balance = tracking_client.balance(&margin_account, &token.clone());
-                let reserve = blend_client.get_reserve(&underlying_addr);
-                let underlying_amt =
-                    Self::b_tokens_to_underlying(env, b_balance, reserve.data.b_rate);
-                let underlying_wad = Self::scale_to_wad(env, underlying_amt, underlying_dec);
-                // Ensure underlying price is cached
-                Self::cache_price(env, &oracle_client, &underlying_sym, &mut price_cache);
-                (underlying_wad, underlying_sym)
-            } else {
-                (
-                    smart_account_client.get_collateral_token_balance(&token.clone()),
-                    token.clone(),
-                )
-            };
-            let price_wad = price_cache.get(price_symbol).unwrap_or(0);
-            total_balance_wad = total_balance_wad.add(&Self::mul_wad_down(
-                env,
-                token_balance_wad,
-                U256::from_u128(env, price_wad),
-            ));
-        }
-
-        // Total debt value — call LendingPools directly (avoids SmartAccount→Registry→Pool chain)
-        let mut total_debt_wad = U256::from_u128(env, 0);
-        for token in borrowed_tokens.iter() {
-            let token_debt_wad = Self::get_debt_direct(env, &registry_client, &token, &margin_account);
-            let price_wad = price_cache.get(token.clone()).unwrap_or(0);
-            total_debt_wad = total_debt_wad.add(&Self::mul_wad_down(
-                env,
-                token_debt_wad,
-                U256::from_u128(env, price_wad),
-            ));
-        }
+        Self::check_account_limits(
+            env,
+            &balance_value_wad.add(&borrow_value_wad),
+            &debt_value_wad.add(&borrow_value_wad),
+        )?;
+        let total_balance_wad = total_balance_wad.add(&borrow_value_wad);
+        let total_debt_wad = total_debt_wad.add(&borrow_value_wad);
+        Self::is_account_healthy(env, total_balance_wad, total_debt_wad)
+    }
 
-        Self::is_account_healthy(
+    /// Checks the account against the per-account limits and `protocol`'s exposure limit
+    /// after an external call has run.
+    pub fn is_execute_allowed(
+        env: &Env,
+        margin_account: Address,
+        protocol: Address,
+    ) -> Result<bool, RiskEngineError> {
+        let registry_client =
+            registry_contract::Client::new(env, &Self::get_registry_address(env));
+        let oracle_client =
+            oracle_contract::Client::new(env, &registry_client.get_oracle_contract_address());
+        let mut price_cache: Map<Symbol, u128> = Map::new(env);
+        let assets = Self::collect_breakdown(
             env,
-            total_balance_wad.add(&borrow_value_wad),
-            total_debt_wad.add(&borrow_value_wad),
-        )
+            &registry_client,
+            &oracle_client,
+            &margin_account,
+            None,
+            &mut price_cache,
+        );
+        let (balance_wad, debt_wad) = Self::breakdown_values(env, &assets);
+        Self::check_account_limits(env, &balance_wad, &debt_wad)?;
+
+        if let Some(limit_wad) = Self::get_protocol_exposure_limit(env, protocol.clone())
+            && Self::get_protocol_exposure(env, margin_account, protocol) > limit_wad
+        {
+            return Err(RiskEngineError::ProtocolExposureLimitExceeded);
+        }
+        Ok(true)
     }
 
     pub fn is_withdraw_allowed(
@@ -141,70 +173,36 @@ impl RiskEngineContract {
         let collateral_tokens = smart_account_client.get_all_collateral_tokens();
         let borrowed_tokens = smart_account_client.get_all_borrowed_tokens();
 
-        // Build price cache
-        let mut price_cache: Map<Symbol, u128> = Map::new(env);
-        Self::cache_price(env, &oracle_client, &symbol, &mut price_cache);
-        for token in collateral_tokens.iter() {
-            if !Self::is_blend_tracking_symbol(env, &token) {
-                Self::cache_price(env, &oracle_client, &token, &mut price_cache);
-            }
-        }
-        for token in borrowed_tokens.iter() {
-            Self::cache_price(env, &oracle_client, &token, &mut price_cache);
-        }
+        let emode_factor_bps = Self::account_emode(env, &collateral_tokens, &borrowed_tokens)
+            .map(|(_, factor_bps)| factor_bps);
 
-        let withdraw_price_wad = price_cache.get(symbol.clone()).unwrap_or(0);
-        let withdraw_value_wad = Self::mul_wad_down(
+        let mut price_cache: Map<Symbol, u128> = Map::new(env);
+        let assets = Self::collect_breakdown(
             env,
-            withdraw_amount_wad.clone(),
-            U256::from_u128(env, withdraw_price_wad),
+            &registry_client,
+            &oracle_client,
+            &margin_account,
+            emode_factor_bps,
+            &mut price_cache,
         );
+        let (total_balance_wad, total_debt_wad) = Self::breakdown_totals(env, &assets);
 
-        // Total collateral value (using cached prices)
-        let mut total_balance_wad = U256::from_u128(env, 0);
-        for token in collateral_tokens.iter() {
-            let (token_balance_wad, price_symbol) = if Self::is_blend_tracking_symbol(env, &token) {
-                let tracking_addr = registry_client.get_tracking_token_contract_addr();
-                let tracking_client = tracking_token_contract::Client::new(env, &tracking_addr);
-                let blend_pool_addr = registry_client.get_blend_pool_address();
-                let blend_client = BlendPoolClient::new(env, &blend_pool_addr);
-                let (underlying_sym, underlying_addr, underlying_dec) =
-                    Self::blend_underlying_info(env, &registry_client, &token);
-                let b_balance = tracking_client.balance(&margin_account, &token.clone());
-                let reserve = blend_client.get_reserve(&underlying_addr);
-                let underlying_amt =
-                    Self::b_tokens_to_underlying(env, b_balance, reserve.data.b_rate);
-                let underlying_wad = Self::scale_to_wad(env, underlying_amt, underlying_dec);
-                Self::cache_price(env, &oracle_client, &underlying_sym, &mut price_cache);
-                (underlying_wad, underlying_sym)
-            } else {
-                (
-                    smart_account_client.get_collateral_token_balance(&token.clone()),
-                    token.clone(),
-                )
-            };
-            let price_wad = price_cache.get(price_symbol).unwrap_or(0);
-            total_balance_wad = total_balance_wad.add(&Self::mul_wad_down(
-                env,
-                token_balance_wad,
-                U256::from_u128(env, price_wad),
-            ));
-        }
-
-        // Total debt value — call LendingPools directly
-        let mut total_debt_wad = U256::from_u128(env, 0);
-        for token in borrowed_tokens.iter() {
-            let token_debt_wad = Self::get_debt_direct(env, &registry_client, &token, &margin_account);
-            let price_wad = price_cache.get(token.clone()).unwrap_or(0);
-            total_debt_wad = total_debt_wad.add(&Self::mul_wad_down(
+        let withdraw_price_symbol = Self::collateral_price_symbol(&registry_client, &symbol);
+        Self::cache_price(env, &oracle_client, &withdraw_price_symbol, &mut price_cache);
+        let withdraw_price_wad = price_cache.get(withdraw_price_symbol).unwrap_or(0);
+        let withdraw_value_wad = Self::weight_collateral(
+            env,
+            &symbol,
+            Self::mul_wad_down(
                 env,
-                token_debt_wad,
-                U256::from_u128(env, price_wad),
-            ));
-        }
+                withdraw_amount_wad.clone(),
+                U256::from_u128(env, withdraw_price_wad),
+            ),
+            emode_factor_bps,
+        );
 
         if withdraw_amount_wad > total_balance_wad {
-            panic!("Cannot withdraw more value than the current collateral value")
+            return Err(RiskEngineError::InsufficientCollateral);
         }
 
         Self::is_account_healthy(env, total_balance_wad.sub(&withdraw_value_wad), total_debt_wad)
@@ -224,6 +222,575 @@ impl RiskEngineContract {
         Ok(res)
     }
 
+    /// True when the account sits in the soft-liquidation zone: still above the liquidation
+    /// threshold but at or below the deleverage threshold.
+    pub fn is_deleverage_allowed(
+        env: &Env,
+        total_account_balance_wad: U256,
+        total_account_debt_wad: U256,
+    ) -> Result<bool, RiskEngineError> {
+        if total_account_debt_wad == U256::from_u128(&env, 0) {
+            return Ok(false);
+        }
+        let ratio = total_account_balance_wad
+            .mul(&U256::from_u128(&env, WAD_U128))
+            .div(&total_account_debt_wad);
+        Ok(ratio > U256::from_u128(&env, BALANCE_TO_BORROW_THRESHOLD)
+            && ratio <= Self::get_deleverage_threshold(env))
+    }
+
+    pub fn set_deleverage_threshold(env: &Env, threshold_wad: U256) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&RiskEngineKey::Admin)
+            .expect("Failed to fetch admin address");
+        admin.require_auth();
+
+        if threshold_wad <= U256::from_u128(&env, BALANCE_TO_BORROW_THRESHOLD) {
+            panic_with_error!(env, RiskEngineError::InvalidThreshold);
+        }
+        env.storage()
+            .persistent()
+            .set(&RiskEngineKey::DeleverageThreshold, &threshold_wad);
+        Self::extend_ttl_risk(&env, RiskEngineKey::DeleverageThreshold);
+    }
+
+    pub fn get_deleverage_threshold(env: &Env) -> U256 {
+        env.storage()
+            .persistent()
+            .get(&RiskEngineKey::DeleverageThreshold)
+            .unwrap_or(U256::from_u128(&env, DEFAULT_DELEVERAGE_THRESHOLD))
+    }
+
+    pub fn set_account_limits(env: &Env, limits: AccountLimits) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&RiskEngineKey::Admin)
+            .expect("Failed to fetch admin address");
+        admin.require_auth();
+
+        let wad = U256::from_u128(env, WAD_U128);
+        let zero = U256::from_u128(env, 0);
+        if limits.max_leverage_wad != zero && limits.max_leverage_wad < wad {
+            panic_with_error!(env, RiskEngineError::InvalidThreshold);
+        }
+        env.storage()
+            .persistent()
+            .set(&RiskEngineKey::AccountLimits, &limits);
+        Self::extend_ttl_risk(env, RiskEngineKey::AccountLimits);
+    }
+
+    pub fn get_account_limits(env: &Env) -> AccountLimits {
+        env.storage()
+            .persistent()
+            .get(&RiskEngineKey::AccountLimits)
+            .unwrap_or(AccountLimits {
+                max_leverage_wad: U256::from_u128(env, 0),
+                max_borrow_value_wad: U256::from_u128(env, 0),
+            })
+    }
+
+    /// Caps the value one account may hold in `protocol`. A zero limit removes the cap.
+    pub fn set_protocol_exposure_limit(env: &Env, protocol: Address, max_exposure_wad: U256) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&RiskEngineKey::Admin)
+            .expect("Failed to fetch admin address");
+        admin.require_auth();
+
+        let key = RiskEngineKey::ProtocolExposureLimit(protocol);
+        if max_exposure_wad == U256::from_u128(env, 0) {
+            env.storage().persistent().remove(&key);
+            return;
+        }
+        env.storage().persistent().set(&key, &max_exposure_wad);
+        Self::extend_ttl_risk(env, key);
+    }
+
+    pub fn get_protocol_exposure_limit(env: &Env, protocol: Address) -> Option<U256> {
+        env.storage()
+            .persistent()
+            .get(&RiskEngineKey::ProtocolExposureLimit(protocol))
+    }
+
+    /// Value of the account's positions in `protocol`: everything a whitelisted adapter
+    /// reports, the supplied and collateral balances of a Blend pool, or the pool share
+    /// behind the LP tracking balances of the Aquarius and Soroswap routers. Other
+    /// protocols leave no position the risk engine can value and report zero.
+    pub fn get_protocol_exposure(env: &Env, margin_account: Address, protocol: Address) -> U256 {
+        let registry_client =
+            registry_contract::Client::new(env, &Self::get_registry_address(env));
+        let oracle_client =
+            oracle_contract::Client::new(env, &registry_client.get_oracle_contract_address());
+        let mut price_cache: Map<Symbol, u128> = Map::new(env);
+        let mut exposure_wad = U256::from_u128(env, 0);
+
+        if registry_client.is_protocol_adapter(&protocol) {
+            let adapter_client = protocol_adapter_contract::Client::new(env, &protocol);
+            for (underlying_symbol, amount_wad) in
+                adapter_client.value_position(&margin_account).iter()
+            {
+                Self::cache_price(env, &oracle_client, &underlying_symbol, &mut price_cache);
+                let price_wad = price_cache.get(underlying_symbol).unwrap_or(0);
+                exposure_wad = exposure_wad.add(&Self::mul_wad_down(
+                    env,
+                    amount_wad,
+                    U256::from_u128(env, price_wad),
+                ));
+            }
+        } else if registry_client.is_blend_pool(&protocol)
+            || registry_client.is_delisted_blend_pool(&protocol)
+        {
+            let smart_account_client = smart_account_contract::Client::new(env, &margin_account);
+            for token in smart_account_client.get_all_collateral_tokens().iter() {
+                let Some(info) = Self::blend_collateral_info(&registry_client, &token) else {
+                    continue;
+                };
+                if info.pool != protocol {
+                    continue;
+                }
+                let amount_wad =
+                    Self::blend_position_wad(env, &registry_client, &info, &token, &margin_account);
+                Self::cache_price(env, &oracle_client, &info.underlying, &mut price_cache);
+                let price_wad = price_cache.get(info.underlying).unwrap_or(0);
+                exposure_wad = exposure_wad.add(&Self::mul_wad_down(
+                    env,
+                    amount_wad,
+                    U256::from_u128(env, price_wad),
+                ));
+            }
+        } else if let Some(lp_symbols) = Self::router_lp_symbols(env, &registry_client, &protocol) {
+            let collateral_tokens =
+                smart_account_contract::Client::new(env, &margin_account).get_all_collateral_tokens();
+            for lp_symbol in lp_symbols.iter() {
+                if !collateral_tokens.contains(&lp_symbol) {
+                    continue;
+                }
+                let Some(underlying_amounts) =
+                    Self::lp_position_wad(env, &registry_client, &lp_symbol, &margin_account)
+                else {
+                    continue;
+                };
+                for (underlying_symbol, amount_wad) in underlying_amounts.iter() {
+                    Self::cache_price(env, &oracle_client, &underlying_symbol, &mut price_cache);
+                    let price_wad = price_cache.get(underlying_symbol).unwrap_or(0);
+                    exposure_wad = exposure_wad.add(&Self::mul_wad_down(
+                        env,
+                        amount_wad,
+                        U256::from_u128(env, price_wad),
+                    ));
+                }
+            }
+        }
+        exposure_wad
+    }
+
+    /// Creates or replaces efficiency mode category `id`. Accounts whose collateral and debt
+    /// all sit in `assets` are measured against `collateral_factor_bps` instead of the
+    /// global liquidation threshold once it beats them. The factor ramps linearly from its
+    /// current value, or the global parameters for a new category, over `duration_secs`.
+    pub fn set_emode_category(
+        env: &Env,
+        id: u32,
+        collateral_factor_bps: u32,
+        assets: Vec<Symbol>,
+        duration_secs: u64,
+    ) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&RiskEngineKey::Admin)
+            .expect("Failed to fetch admin address");
+        admin.require_auth();
+
+        if collateral_factor_bps == 0 || collateral_factor_bps >= FULL_COLLATERAL_FACTOR_BPS {
+            panic_with_error!(env, RiskEngineError::InvalidCollateralFactor);
+        }
+        let start_factor_bps =
+            Self::get_emode_factor_bps(env, id).unwrap_or(Self::emode_neutral_factor_bps());
+        let key = RiskEngineKey::EModeCategory(id);
+        env.storage().persistent().set(
+            &key,
+            &EModeCategory {
+                collateral_factor_bps,
+                assets,
+            },
+        );
+        Self::extend_ttl_risk(env, key);
+        Self::set_emode_factor_ramp(env, id, start_factor_bps, collateral_factor_bps, duration_secs);
+
+        let mut ids = Self::get_emode_category_ids(env);
+        if !ids.contains(id) {
+            ids.push_back(id);
+            Self::set_emode_category_ids(env, ids);
+        }
+    }
+
+    /// Winds category `id` down to zero over `duration_secs`; its accounts fall back to the
+    /// global parameters once the factor drops below them. A zero duration deletes it at once.
+    pub fn remove_emode_category(
+        env: &Env,
+        id: u32,
+        duration_secs: u64,
+    ) -> Result<(), RiskEngineError> {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&RiskEngineKey::Admin)
+            .expect("Failed to fetch admin address");
+        admin.require_auth();
+
+        let mut ids = Self::get_emode_category_ids(env);
+        let index = ids
+            .first_index_of(id)
+            .ok_or(RiskEngineError::EModeCategoryNotFound)?;
+        if duration_secs > 0 {
+            let start_factor_bps = Self::get_emode_factor_bps(env, id).unwrap_or(0);
+            Self::set_emode_factor_ramp(env, id, start_factor_bps, 0, duration_secs);
+            return Ok(());
+        }
+        ids.remove(index);
+        Self::set_emode_category_ids(env, ids);
+        env.storage()
+            .persistent()
+            .remove(&RiskEngineKey::EModeCategory(id));
+        env.storage()
+            .persistent()
+            .remove(&RiskEngineKey::EModeFactorRamp(id));
+        Ok(())
+    }
+
+    pub fn get_emode_category(env: &Env, id: u32) -> Option<EModeCategory> {
+        env.storage()
+            .persistent()
+            .get(&RiskEngineKey::EModeCategory(id))
+    }
+
+    pub fn get_emode_category_ids(env: &Env) -> Vec<u32> {
+        env.storage()
+            .persistent()
+            .get(&RiskEngineKey::EModeCategoryIds)
+            .unwrap_or(Vec::new(env))
+    }
+
+    pub fn get_emode_factor_ramp(env: &Env, id: u32) -> Option<CollateralFactorRamp> {
+        env.storage()
+            .persistent()
+            .get(&RiskEngineKey::EModeFactorRamp(id))
+    }
+
+    /// Collateral factor of efficiency mode category `id`, in basis points, at the current
+    /// ledger time.
+    pub fn get_emode_factor_bps(env: &Env, id: u32) -> Option<u32> {
+        if let Some(ramp) = Self::get_emode_factor_ramp(env, id) {
+            return Some(Self::ramp_factor_bps(env, &ramp));
+        }
+        Self::get_emode_category(env, id).map(|category| category.collateral_factor_bps)
+    }
+
+    /// Efficiency mode category the account currently qualifies for, if any.
+    pub fn get_account_emode(env: &Env, margin_account: Address) -> Option<u32> {
+        let smart_account_client = smart_account_contract::Client::new(env, &margin_account);
+        Self::account_emode(
+            env,
+            &smart_account_client.get_all_collateral_tokens(),
+            &smart_account_client.get_all_borrowed_tokens(),
+        )
+        .map(|(id, _)| id)
+    }
+
+    /// Isolated collateral asset the account holds a balance of, if any. Such accounts may
+    /// only borrow the registry's isolation-borrowable assets up to the asset's debt ceiling.
+    pub fn get_isolated_collateral(env: &Env, margin_account: Address) -> Option<Symbol> {
+        let registry_client =
+            registry_contract::Client::new(env, &Self::get_registry_address(env));
+        let smart_account_client = smart_account_contract::Client::new(env, &margin_account);
+        Self::isolated_collateral(
+            env,
+            &registry_client,
+            &smart_account_client,
+            &smart_account_client.get_all_collateral_tokens(),
+        )
+    }
+
+    /// Moves the collateral factor of `symbol` linearly from its current value to
+    /// `target_factor_bps` over `duration_secs`. A zero duration applies the target at once.
+    pub fn set_collateral_factor_ramp(
+        env: &Env,
+        symbol: Symbol,
+        target_factor_bps: u32,
+        duration_secs: u64,
+    ) {
+        let admin: Address = env
+            .storage()
+            .persistent()
+            .get(&RiskEngineKey::Admin)
+            .expect("Failed to fetch admin address");
+        admin.require_auth();
+
+        if target_factor_bps > FULL_COLLATERAL_FACTOR_BPS {
+            panic_with_error!(env, RiskEngineError::InvalidCollateralFactor);
+        }
+        let now = env.ledger().timestamp();
+        let key = RiskEngineKey::CollateralFactorRamp(symbol.clone());
+        env.storage().persistent().set(
+            &key,
+            &CollateralFactorRamp {
+                start_factor_bps: Self::get_collateral_factor_bps(env, symbol),
+                target_factor_bps,
+                start_time: now,
+                end_time: now + duration_secs,
+            },
+        );
+        Self::extend_ttl_risk(env, key);
+    }
+
+    pub fn get_collateral_factor_ramp(env: &Env, symbol: Symbol) -> Option<CollateralFactorRamp> {
+        env.storage()
+            .persistent()
+            .get(&RiskEngineKey::CollateralFactorRamp(symbol))
+    }
+
+    /// Share of `symbol`'s value, in basis points, counted as collateral at the current
+    /// ledger time.
+    pub fn get_collateral_factor_bps(env: &Env, symbol: Symbol) -> u32 {
+        match Self::get_collateral_factor_ramp(env, symbol) {
+            Some(ramp) => Self::ramp_factor_bps(env, &ramp),
+            None => FULL_COLLATERAL_FACTOR_BPS,
+        }
+    }
+
+    fn ramp_factor_bps(env: &Env, ramp: &CollateralFactorRamp) -> u32 {
+        let now = env.ledger().timestamp();
+        if now >= ramp.end_time {
+            return ramp.target_factor_bps;
+        }
+        let elapsed = (now - ramp.start_time) as u128;
+        let duration = (ramp.end_time - ramp.start_time) as u128;
+        let start = ramp.start_factor_bps as u128;
+        let target = ramp.target_factor_bps as u128;
+        let factor = if target < start {
+            start - (start - target) * elapsed / duration
+        } else {
+            start + (target - start) * elapsed / duration
+        };
+        factor as u32
+    }
+
+    /// WAD price the health checks use for a collateral or debt symbol, resolving listed
+    /// collateral to its oracle ticker and Blend tracking symbols to their underlying.
+    pub fn get_asset_price(env: &Env, symbol: Symbol) -> U256 {
+        let registry_client = registry_contract::Client::new(env, &Self::get_registry_address(env));
+        let oracle_client =
+            oracle_contract::Client::new(env, &registry_client.get_oracle_contract_address());
+        let price_symbol = Self::collateral_price_symbol(
+            &registry_client,
+            &Self::debt_price_symbol(&registry_client, &symbol),
+        );
+        U256::from_u128(env, Self::get_oracle_price_wad(env, &oracle_client, &price_symbol))
+    }
+
+    /// Balance value over debt value in WAD, the ratio `is_account_healthy` compares
+    /// with the liquidation threshold. Accounts without debt report `NO_DEBT_HEALTH_FACTOR`.
+    pub fn get_health_factor(env: &Env, margin_account: Address) -> Result<U256, RiskEngineError> {
+        Ok(Self::get_account_breakdown(env, margin_account)?.health_factor)
+    }
+
+    pub fn get_account_breakdown(
+        env: &Env,
+        margin_account: Address,
+    ) -> Result<AccountBreakdown, RiskEngineError> {
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        let oracle_client =
+            oracle_contract::Client::new(env, &registry_client.get_oracle_contract_address());
+
+        let smart_account_client = smart_account_contract::Client::new(env, &margin_account);
+        let emode_factor_bps = Self::account_emode(
+            env,
+            &smart_account_client.get_all_collateral_tokens(),
+            &smart_account_client.get_all_borrowed_tokens(),
+        )
+        .map(|(_, factor_bps)| factor_bps);
+
+        let mut price_cache: Map<Symbol, u128> = Map::new(env);
+        let assets = Self::collect_breakdown(
+            env,
+            &registry_client,
+            &oracle_client,
+            &margin_account,
+            emode_factor_bps,
+            &mut price_cache,
+        );
+        let (total_collateral_value, total_debt_value) = Self::breakdown_totals(env, &assets);
+        Ok(AccountBreakdown {
+            assets,
+            health_factor: Self::health_factor(
+                env,
+                &total_collateral_value,
+                &total_debt_value,
+            ),
+            total_collateral_value,
+            total_debt_value,
+        })
+    }
+
+    /// Health factor after applying `actions` to the account's current positions. Borrowed
+    /// tokens count toward the balance as they do in `is_borrow_allowed`.
+    pub fn simulate(
+        env: &Env,
+        margin_account: Address,
+        actions: Vec<SimulatedAction>,
+    ) -> Result<U256, RiskEngineError> {
+        let registry_address = Self::get_registry_address(env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        let oracle_client =
+            oracle_contract::Client::new(env, &registry_client.get_oracle_contract_address());
+
+        // Efficiency mode must still hold once the simulated deposits and borrows are in
+        let smart_account_client = smart_account_contract::Client::new(env, &margin_account);
+        let mut collateral_tokens = smart_account_client.get_all_collateral_tokens();
+        let mut borrowed_tokens = smart_account_client.get_all_borrowed_tokens();
+        for action in actions.iter() {
+            match action {
+                SimulatedAction::DepositCollateral(symbol, _) => collateral_tokens.push_back(symbol),
+                SimulatedAction::Borrow(symbol, _) => borrowed_tokens.push_back(symbol),
+                _ => {}
+            }
+        }
+        let emode_factor_bps = Self::account_emode(env, &collateral_tokens, &borrowed_tokens)
+            .map(|(_, factor_bps)| factor_bps);
+
+        let mut price_cache: Map<Symbol, u128> = Map::new(env);
+        let assets = Self::collect_breakdown(
+            env,
+            &registry_client,
+            &oracle_client,
+            &margin_account,
+            emode_factor_bps,
+            &mut price_cache,
+        );
+        let (mut balance_wad, mut debt_wad) = Self::breakdown_totals(env, &assets);
+
+        for action in actions.iter() {
+            let (symbol, amount_wad) = match &action {
+                SimulatedAction::DepositCollateral(symbol, amount)
+                | SimulatedAction::WithdrawCollateral(symbol, amount)
+                | SimulatedAction::Borrow(symbol, amount)
+                | SimulatedAction::Repay(symbol, amount) => (symbol.clone(), amount.clone()),
+            };
+            let price_symbol = Self::collateral_price_symbol(&registry_client, &symbol);
+            Self::cache_price(env, &oracle_client, &price_symbol, &mut price_cache);
+            let value_wad = Self::mul_wad_down(
+                env,
+                amount_wad,
+                U256::from_u128(env, price_cache.get(price_symbol).unwrap_or(0)),
+            );
+            match action {
+                SimulatedAction::DepositCollateral(_, _) => {
+                    balance_wad = balance_wad.add(&Self::weight_collateral(
+                        env,
+                        &symbol,
+                        value_wad,
+                        emode_factor_bps,
+                    ));
+                }
+                SimulatedAction::WithdrawCollateral(_, _) => {
+                    let value_wad =
+                        Self::weight_collateral(env, &symbol, value_wad, emode_factor_bps);
+                    if value_wad > balance_wad {
+                        return Err(RiskEngineError::InsufficientCollateral);
+                    }
+                    balance_wad = balance_wad.sub(&value_wad);
+                }
+                SimulatedAction::Borrow(_, _) => {
+                    balance_wad = balance_wad.add(&value_wad);
+                    debt_wad = debt_wad.add(&value_wad);
+                }
+                SimulatedAction::Repay(_, _) => {
+                    let repaid = if value_wad < debt_wad { value_wad } else { debt_wad.clone() };
+                    debt_wad = debt_wad.sub(&repaid);
+                    balance_wad = if repaid < balance_wad {
+                        balance_wad.sub(&repaid)
+                    } else {
+                        U256::from_u128(env, 0)
+                    };
+                }
+            }
+        }
+
+        Ok(Self::health_factor(env, &balance_wad, &debt_wad))
+    }
+
+    /// Oracle price of `symbol` in WAD at which the account would hit the liquidation
+    /// threshold with every other price unchanged. `None` if moving this price alone cannot
+    /// make the account liquidatable, zero if it is liquidatable at any price.
+    pub fn get_liquidation_price(
+        env: &Env,
+        margin_account: Address,
+        symbol: Symbol,
+    ) -> Result<Option<U256>, RiskEngineError> {
+        let breakdown = Self::get_account_breakdown(env, margin_account)?;
+        let zero = U256::from_u128(env, 0);
+
+        let mut collateral_amount = zero.clone();
+        let mut debt_amount = zero.clone();
+        let mut other_collateral = breakdown.total_collateral_value;
+        let mut other_debt = breakdown.total_debt_value;
+        for asset in breakdown.assets.iter() {
+            if asset.price_symbol != symbol {
+                continue;
+            }
+            if asset.is_debt {
+                debt_amount = debt_amount.add(&asset.amount);
+                other_debt = other_debt.sub(&asset.weighted_value);
+            } else {
+                // Only the collateral factor's share of the amount counts toward health
+                let weighted_amount = if asset.value == zero {
+                    asset.amount.clone()
+                } else {
+                    asset.amount.mul(&asset.weighted_value).div(&asset.value)
+                };
+                collateral_amount = collateral_amount.add(&weighted_amount);
+                other_collateral = other_collateral.sub(&asset.weighted_value);
+            }
+        }
+
+        // Liquidatable once other_collateral + p * collateral_amount
+        // <= threshold * (other_debt + p * debt_amount)
+        let threshold = U256::from_u128(env, BALANCE_TO_BORROW_THRESHOLD);
+        let wad = U256::from_u128(env, WAD_U128);
+        let weighted_debt_amount = Self::mul_wad_down(env, threshold.clone(), debt_amount);
+        let required_collateral = Self::mul_wad_down(env, threshold, other_debt);
+
+        if collateral_amount > weighted_debt_amount {
+            if required_collateral <= other_collateral {
+                return Ok(None);
+            }
+            Ok(Some(
+                required_collateral
+                    .sub(&other_collateral)
+                    .mul(&wad)
+                    .div(&collateral_amount.sub(&weighted_debt_amount)),
+            ))
+        } else if collateral_amount < weighted_debt_amount {
+            if other_collateral <= required_collateral {
+                return Ok(Some(zero));
+            }
+            Ok(Some(
+                other_collateral
+                    .sub(&required_collateral)
+                    .mul(&wad)
+                    .div(&weighted_debt_amount.sub(&collateral_amount)),
+            ))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn get_current_total_balance(
         env: &Env,
         margin_account: Address,
@@ -243,43 +810,23 @@ impl RiskEngineContract {
 
         let oracle_address = registry_client.get_oracle_contract_address();
         let oracle_client = oracle_contract::Client::new(env, &oracle_address);
+        let emode_factor_bps = Self::account_emode(
+            env,
+            &collateral_token_symbols,
+            &smart_account_contract_client.get_all_borrowed_tokens(),
+        )
+        .map(|(_, factor_bps)| factor_bps);
 
-        let mut total_balance_usd_wad: U256 = U256::from_u128(&env, 0);
-        for token in collateral_token_symbols.iter() {
-            let (token_balance_wad, price_symbol) = if Self::is_blend_tracking_symbol(&env, &token)
-            {
-                let tracking_token_address = registry_client.get_tracking_token_contract_addr();
-                let tracking_token_client =
-                    tracking_token_contract::Client::new(&env, &tracking_token_address);
-                let blend_pool_address = registry_client.get_blend_pool_address();
-                let blend_pool_client = BlendPoolClient::new(&env, &blend_pool_address);
-                let (underlying_symbol, underlying_address, underlying_decimals) =
-                    Self::blend_underlying_info(&env, &registry_client, &token);
-                let b_token_balance =
-                    tracking_token_client.balance(&margin_account, &token.clone());
-                let reserve = blend_pool_client.get_reserve(&underlying_address);
-                let b_rate = reserve.data.b_rate;
-                let underlying_amount = Self::b_tokens_to_underlying(&env, b_token_balance, b_rate);
-                let underlying_wad =
-                    Self::scale_to_wad(&env, underlying_amount, underlying_decimals);
-                (underlying_wad, underlying_symbol)
-            } else {
-                (
-                    smart_account_contract_client.get_collateral_token_balance(&token.clone()),
-                    token.clone(),
-                )
-            };
-
-            let oracle_price_wad =
-                Self::get_oracle_price_wad(&env, &oracle_client, &price_symbol);
-            // Multiply balance with oracle price
-            let balance_wad = Self::mul_wad_down(
-                &env,
-                token_balance_wad,
-                U256::from_u128(&env, oracle_price_wad),
-            );
-            total_balance_usd_wad = total_balance_usd_wad.add(&balance_wad);
-        }
+        let mut price_cache: Map<Symbol, u128> = Map::new(env);
+        let assets = Self::collect_collateral(
+            env,
+            &registry_client,
+            &oracle_client,
+            &margin_account,
+            emode_factor_bps,
+            &mut price_cache,
+        );
+        let (total_balance_usd_wad, _) = Self::breakdown_totals(env, &assets);
         Ok(total_balance_usd_wad)
     }
 
@@ -301,7 +848,11 @@ impl RiskEngineContract {
 
         for token in borrowed_tokens.iter() {
             let token_debt_wad = Self::get_debt_direct(env, &registry_client, &token, &margin_account);
-            let oracle_price_wad = Self::get_oracle_price_wad(&env, &oracle_client, &token);
+            let oracle_price_wad = Self::get_oracle_price_wad(
+                &env,
+                &oracle_client,
+                &Self::debt_price_symbol(&registry_client, &token),
+            );
             let debt_value_wad = Self::mul_wad_down(
                 &env,
                 token_debt_wad,
@@ -331,6 +882,9 @@ impl RiskEngineContract {
         } else if token == &EURC_SYMBOL {
             lending_protocol_eurc::Client::new(env, &registry_client.get_lendingpool_eurc())
                 .get_user_borrow_shares(margin_account)
+        } else if let Some(info) = registry_client.get_blend_tracking_info(token) {
+            // Blend liabilities are tracked in d-tokens of the pool they were borrowed from
+            Self::blend_position_wad(env, registry_client, &info, token, margin_account)
         } else {
             U256::from_u128(env, 0)
         }
@@ -349,43 +903,537 @@ impl RiskEngineContract {
     /// Fetch oracle price into the cache only if not already present.
     /// This ensures the oracle contract is called at most once per unique symbol.
     fn cache_price(
-        _env: &Env,
+        env: &Env,
         oracle_client: &oracle_contract::Client,
         symbol: &Symbol,
         cache: &mut Map<Symbol, u128>,
     ) {
         if cache.get(symbol.clone()).is_none() {
-            let (price, decimals) = oracle_client.get_price_latest(symbol);
-            let wad_scale = WAD_U128 / 10_u128.pow(decimals);
-            cache.set(symbol.clone(), price * wad_scale);
+            cache.set(symbol.clone(), Self::get_oracle_price_wad(env, oracle_client, symbol));
+        }
+    }
+
+    /// Every collateral and debt position of the account with the amount, price and value
+    /// the health checks use, pricing through `price_cache`.
+    fn collect_breakdown(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        oracle_client: &oracle_contract::Client,
+        margin_account: &Address,
+        emode_factor_bps: Option<u32>,
+        price_cache: &mut Map<Symbol, u128>,
+    ) -> Vec<AssetBreakdown> {
+        let smart_account_client = smart_account_contract::Client::new(env, margin_account);
+        let mut assets = Self::collect_collateral(
+            env,
+            registry_client,
+            oracle_client,
+            margin_account,
+            emode_factor_bps,
+            price_cache,
+        );
+
+        for token in smart_account_client.get_all_borrowed_tokens().iter() {
+            let amount_wad = Self::get_debt_direct(env, registry_client, &token, margin_account);
+            let price_symbol = Self::debt_price_symbol(registry_client, &token);
+            assets.push_back(Self::price_asset(
+                env,
+                oracle_client,
+                price_cache,
+                token,
+                price_symbol,
+                amount_wad,
+                true,
+            ));
+        }
+
+        assets
+    }
+
+    /// Collateral positions of the account, weighted by collateral factor and efficiency
+    /// mode. Adapter positions are valued once per adapter through `value_position`.
+    /// Tracking positions (adapters, Blend, LP sides) take the factor of their underlying.
+    fn collect_collateral(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        oracle_client: &oracle_contract::Client,
+        margin_account: &Address,
+        emode_factor_bps: Option<u32>,
+        price_cache: &mut Map<Symbol, u128>,
+    ) -> Vec<AssetBreakdown> {
+        let smart_account_client = smart_account_contract::Client::new(env, margin_account);
+        let mut assets: Vec<AssetBreakdown> = Vec::new(env);
+        let mut valued_adapters: Vec<Address> = Vec::new(env);
+
+        for token in smart_account_client.get_all_collateral_tokens().iter() {
+            if registry_client.has_tracking_symbol_adapter(&token) {
+                let adapter_address = registry_client.get_tracking_symbol_adapter(&token);
+                if valued_adapters.contains(&adapter_address) {
+                    continue;
+                }
+                valued_adapters.push_back(adapter_address.clone());
+                let adapter_client = protocol_adapter_contract::Client::new(env, &adapter_address);
+                for (underlying_symbol, amount_wad) in
+                    adapter_client.value_position(margin_account).iter()
+                {
+                    let mut asset = Self::price_asset(
+                        env,
+                        oracle_client,
+                        price_cache,
+                        token.clone(),
+                        underlying_symbol,
+                        amount_wad,
+                        false,
+                    );
+                    asset.weighted_value = Self::weight_collateral(
+                        env,
+                        &asset.price_symbol,
+                        asset.value.clone(),
+                        emode_factor_bps,
+                    );
+                    assets.push_back(asset);
+                }
+                continue;
+            }
+
+            if let Some(underlying_amounts) =
+                Self::lp_position_wad(env, registry_client, &token, margin_account)
+            {
+                for (underlying_symbol, amount_wad) in underlying_amounts.iter() {
+                    let mut asset = Self::price_asset(
+                        env,
+                        oracle_client,
+                        price_cache,
+                        token.clone(),
+                        underlying_symbol,
+                        amount_wad,
+                        false,
+                    );
+                    asset.weighted_value = Self::weight_collateral(
+                        env,
+                        &asset.price_symbol,
+                        asset.value.clone(),
+                        emode_factor_bps,
+                    );
+                    assets.push_back(asset);
+                }
+                continue;
+            }
+
+            let (amount_wad, price_symbol, factor_symbol) =
+                if let Some(info) = Self::blend_collateral_info(registry_client, &token) {
+                    (
+                        Self::blend_position_wad(env, registry_client, &info, &token, margin_account),
+                        info.underlying.clone(),
+                        info.underlying,
+                    )
+                } else {
+                    (
+                        smart_account_client.get_collateral_token_balance(&token),
+                        Self::collateral_price_symbol(&registry_client, &token),
+                        token.clone(),
+                    )
+                };
+            let mut asset = Self::price_asset(
+                env,
+                oracle_client,
+                price_cache,
+                token,
+                price_symbol,
+                amount_wad,
+                false,
+            );
+            asset.weighted_value = Self::weight_collateral(
+                env,
+                &factor_symbol,
+                asset.value.clone(),
+                emode_factor_bps,
+            );
+            assets.push_back(asset);
+        }
+
+        assets
+    }
+
+    fn price_asset(
+        env: &Env,
+        oracle_client: &oracle_contract::Client,
+        price_cache: &mut Map<Symbol, u128>,
+        symbol: Symbol,
+        price_symbol: Symbol,
+        amount_wad: U256,
+        is_debt: bool,
+    ) -> AssetBreakdown {
+        Self::cache_price(env, oracle_client, &price_symbol, price_cache);
+        let price_wad = U256::from_u128(env, price_cache.get(price_symbol.clone()).unwrap_or(0));
+        let value_wad = Self::mul_wad_down(env, amount_wad.clone(), price_wad.clone());
+        AssetBreakdown {
+            symbol,
+            price_symbol,
+            amount: amount_wad,
+            price: price_wad,
+            weighted_value: value_wad.clone(),
+            value: value_wad,
+            is_debt,
+        }
+    }
+
+    fn breakdown_totals(env: &Env, assets: &Vec<AssetBreakdown>) -> (U256, U256) {
+        let mut collateral_wad = U256::from_u128(env, 0);
+        let mut debt_wad = U256::from_u128(env, 0);
+        for asset in assets.iter() {
+            if asset.is_debt {
+                debt_wad = debt_wad.add(&asset.weighted_value);
+            } else {
+                collateral_wad = collateral_wad.add(&asset.weighted_value);
+            }
+        }
+        (collateral_wad, debt_wad)
+    }
+
+    /// Unweighted collateral and debt value of the breakdown, what the account limits cap.
+    fn breakdown_values(env: &Env, assets: &Vec<AssetBreakdown>) -> (U256, U256) {
+        let mut collateral_wad = U256::from_u128(env, 0);
+        let mut debt_wad = U256::from_u128(env, 0);
+        for asset in assets.iter() {
+            if asset.is_debt {
+                debt_wad = debt_wad.add(&asset.value);
+            } else {
+                collateral_wad = collateral_wad.add(&asset.value);
+            }
+        }
+        (collateral_wad, debt_wad)
+    }
+
+    fn check_account_limits(
+        env: &Env,
+        balance_wad: &U256,
+        debt_wad: &U256,
+    ) -> Result<(), RiskEngineError> {
+        let limits = Self::get_account_limits(env);
+        let zero = U256::from_u128(env, 0);
+
+        if limits.max_borrow_value_wad != zero && *debt_wad > limits.max_borrow_value_wad {
+            return Err(RiskEngineError::BorrowLimitExceeded);
+        }
+        if limits.max_leverage_wad != zero && *debt_wad > zero {
+            if debt_wad >= balance_wad {
+                return Err(RiskEngineError::LeverageLimitExceeded);
+            }
+            let leverage_wad = balance_wad
+                .mul(&U256::from_u128(env, WAD_U128))
+                .div(&balance_wad.sub(debt_wad));
+            if leverage_wad > limits.max_leverage_wad {
+                return Err(RiskEngineError::LeverageLimitExceeded);
+            }
+        }
+        Ok(())
+    }
+
+    fn isolated_collateral(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        smart_account_client: &smart_account_contract::Client,
+        collateral_tokens: &Vec<Symbol>,
+    ) -> Option<Symbol> {
+        let zero = U256::from_u128(env, 0);
+        collateral_tokens.iter().find(|token| {
+            registry_client.get_isolation_debt_ceiling(token).is_some()
+                && smart_account_client.get_collateral_token_balance(token) > zero
+        })
+    }
+
+    fn weight_collateral(
+        env: &Env,
+        symbol: &Symbol,
+        value_wad: U256,
+        emode_factor_bps: Option<u32>,
+    ) -> U256 {
+        let full_bps = U256::from_u32(env, FULL_COLLATERAL_FACTOR_BPS);
+        let factor_bps = Self::get_collateral_factor_bps(env, symbol.clone());
+        let mut weighted_wad = if factor_bps == FULL_COLLATERAL_FACTOR_BPS {
+            value_wad
+        } else {
+            value_wad.mul(&U256::from_u32(env, factor_bps)).div(&full_bps)
+        };
+        if let Some(emode_factor_bps) = emode_factor_bps {
+            // Scaled so the liquidation threshold is reached once debt is
+            // `emode_factor_bps` of the collateral value
+            weighted_wad = weighted_wad
+                .mul(&U256::from_u32(env, emode_factor_bps))
+                .mul(&U256::from_u128(env, BALANCE_TO_BORROW_THRESHOLD))
+                .div(&full_bps)
+                .div(&U256::from_u128(env, WAD_U128));
+        }
+        weighted_wad
+    }
+
+    /// Efficiency mode category holding every collateral and debt symbol given with the
+    /// highest current factor, as `(id, factor_bps)`. Categories that do not beat the global
+    /// parameters are skipped.
+    fn account_emode(
+        env: &Env,
+        collateral_tokens: &Vec<Symbol>,
+        borrowed_tokens: &Vec<Symbol>,
+    ) -> Option<(u32, u32)> {
+        if collateral_tokens.is_empty() {
+            return None;
         }
+        let mut best: Option<(u32, u32)> = None;
+        for id in Self::get_emode_category_ids(env).iter() {
+            let Some(category) = Self::get_emode_category(env, id) else {
+                continue;
+            };
+            if !collateral_tokens
+                .iter()
+                .chain(borrowed_tokens.iter())
+                .all(|token| category.assets.contains(&token))
+            {
+                continue;
+            }
+            let factor_bps = Self::get_emode_factor_bps(env, id).unwrap_or(0);
+            let best_factor_bps = best.map_or(Self::emode_neutral_factor_bps(), |(_, bps)| bps);
+            if factor_bps > best_factor_bps {
+                best = Some((id, factor_bps));
+            }
+        }
+        best
     }
 
-    fn is_blend_tracking_symbol(env: &Env, symbol: &Symbol) -> bool {
-        symbol == &Symbol::new(env, BLEND_XLM)
-            || symbol == &Symbol::new(env, BLEND_USDC)
-            || symbol == &Symbol::new(env, BLEND_EURC)
+    // Efficiency mode factor that weighs collateral exactly like the global threshold
+    fn emode_neutral_factor_bps() -> u32 {
+        (FULL_COLLATERAL_FACTOR_BPS as u128 * WAD_U128 / BALANCE_TO_BORROW_THRESHOLD) as u32
     }
 
-    fn blend_underlying_info(
+    fn set_emode_factor_ramp(
         env: &Env,
+        id: u32,
+        start_factor_bps: u32,
+        target_factor_bps: u32,
+        duration_secs: u64,
+    ) {
+        let now = env.ledger().timestamp();
+        let key = RiskEngineKey::EModeFactorRamp(id);
+        env.storage().persistent().set(
+            &key,
+            &CollateralFactorRamp {
+                start_factor_bps,
+                target_factor_bps,
+                start_time: now,
+                end_time: now + duration_secs,
+            },
+        );
+        Self::extend_ttl_risk(env, key);
+    }
+
+    fn health_factor(env: &Env, balance_wad: &U256, debt_wad: &U256) -> U256 {
+        if *debt_wad == U256::from_u128(env, 0) {
+            return U256::from_u128(env, NO_DEBT_HEALTH_FACTOR);
+        }
+        balance_wad.mul(&U256::from_u128(env, WAD_U128)).div(debt_wad)
+    }
+
+    /// Registry info for Blend supply / collateral tracking symbols; `None` for anything else.
+    fn blend_collateral_info(
         registry_client: &registry_contract::Client,
+        token: &Symbol,
+    ) -> Option<BlendTrackingInfo> {
+        if Self::is_base_asset(token) {
+            return None;
+        }
+        registry_client
+            .get_blend_tracking_info(token)
+            .filter(|info| info.kind != BlendPositionKind::Liability)
+    }
+
+    /// Oracle symbol used to price a borrowed token. Blend liabilities are
+    /// priced by their underlying asset.
+    fn debt_price_symbol(registry_client: &registry_contract::Client, token: &Symbol) -> Symbol {
+        if Self::is_base_asset(token) {
+            return token.clone();
+        }
+        match registry_client.get_blend_tracking_info(token) {
+            Some(info) => info.underlying,
+            None => token.clone(),
+        }
+    }
+
+    /// Oracle symbol used to price plain collateral. Listed SAC collateral may be priced
+    /// under a ticker that differs from its symbol.
+    fn collateral_price_symbol(registry_client: &registry_contract::Client, token: &Symbol) -> Symbol {
+        if Self::is_base_asset(token) {
+            return token.clone();
+        }
+        match registry_client.get_collateral_asset(token) {
+            Some(asset) => asset.oracle_ticker,
+            None => token.clone(),
+        }
+    }
+
+    // Skips the registry lookup for plain assets, which are never Blend tracking symbols
+    fn is_base_asset(token: &Symbol) -> bool {
+        *token == XLM_SYMBOL || *token == USDC_SYMBOL || *token == EURC_SYMBOL
+    }
+
+    /// Underlying amount in WAD of a Blend tracking balance, converted with the
+    /// b_rate (supply / collateral) or d_rate (liabilities) of the pool it belongs to.
+    fn blend_position_wad(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        info: &BlendTrackingInfo,
         tracking_symbol: &Symbol,
-    ) -> (Symbol, Address, u32) {
-        if tracking_symbol == &Symbol::new(env, BLEND_XLM) {
-            let addr = registry_client.get_xlm_contract_adddress();
-            let decimals = token::Client::new(env, &addr).decimals();
-            (XLM_SYMBOL, addr, decimals)
-        } else if tracking_symbol == &Symbol::new(env, BLEND_USDC) {
-            let addr = registry_client.get_usdc_contract_address();
-            let decimals = token::Client::new(env, &addr).decimals();
-            (USDC_SYMBOL, addr, decimals)
-        } else if tracking_symbol == &Symbol::new(env, BLEND_EURC) {
-            let addr = registry_client.get_eurc_contract_address();
-            let decimals = token::Client::new(env, &addr).decimals();
-            (EURC_SYMBOL, addr, decimals)
+        margin_account: &Address,
+    ) -> U256 {
+        let tracking_client = tracking_token_contract::Client::new(
+            env,
+            &registry_client.get_tracking_token_contract_addr(),
+        );
+        let underlying_address = Self::base_asset_address(env, registry_client, &info.underlying);
+        let underlying_decimals = token::Client::new(env, &underlying_address).decimals();
+
+        let reserve = BlendPoolClient::new(env, &info.pool).get_reserve(&underlying_address);
+        let rate = if info.kind == BlendPositionKind::Liability {
+            reserve.data.d_rate
         } else {
-            panic!("Unsupported blend tracking symbol");
+            reserve.data.b_rate
+        };
+        let balance = tracking_client.balance(margin_account, tracking_symbol);
+        let underlying_amount = Self::b_tokens_to_underlying(env, balance, rate);
+        Self::scale_to_wad(env, underlying_amount, underlying_decimals)
+    }
+
+    /// LP tracking symbols minted for liquidity added through `router`; `None` when it is
+    /// neither the Aquarius nor the Soroswap router.
+    fn router_lp_symbols(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        router: &Address,
+    ) -> Option<Vec<Symbol>> {
+        if let Ok(Ok(aquarius_router)) = registry_client.try_get_aquarius_router_address()
+            && aquarius_router == *router
+        {
+            return Some(Vec::from_array(env, [Symbol::new(env, AQUARIUS_XLM_USDC)]));
+        }
+        if let Ok(Ok(soroswap_router)) = registry_client.try_get_soroswap_router_address()
+            && soroswap_router == *router
+        {
+            return Some(Vec::from_array(
+                env,
+                [
+                    Symbol::new(env, SOROSWAP_XLM_USDC),
+                    Symbol::new(env, SOROSWAP_XLM_EURC),
+                    Symbol::new(env, SOROSWAP_USDC_EURC),
+                ],
+            ));
+        }
+        None
+    }
+
+    /// Underlying amounts in WAD behind an Aquarius or Soroswap LP tracking balance: the
+    /// account's share of the pool reserves. `None` for symbols that are not LP tracking.
+    fn lp_position_wad(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        tracking_symbol: &Symbol,
+        margin_account: &Address,
+    ) -> Option<Vec<(Symbol, U256)>> {
+        if Self::is_base_asset(tracking_symbol) {
+            return None;
+        }
+        let (is_aquarius, symbol_a, symbol_b) =
+            if *tracking_symbol == Symbol::new(env, AQUARIUS_XLM_USDC) {
+                (true, XLM_SYMBOL, USDC_SYMBOL)
+            } else if *tracking_symbol == Symbol::new(env, SOROSWAP_XLM_USDC) {
+                (false, XLM_SYMBOL, USDC_SYMBOL)
+            } else if *tracking_symbol == Symbol::new(env, SOROSWAP_XLM_EURC) {
+                (false, XLM_SYMBOL, EURC_SYMBOL)
+            } else if *tracking_symbol == Symbol::new(env, SOROSWAP_USDC_EURC) {
+                (false, USDC_SYMBOL, EURC_SYMBOL)
+            } else {
+                return None;
+            };
+
+        let mut amounts = Vec::new(env);
+        let tracking_client = tracking_token_contract::Client::new(
+            env,
+            &registry_client.get_tracking_token_contract_addr(),
+        );
+        let liquidity = tracking_client.balance(margin_account, tracking_symbol);
+        if liquidity <= 0 {
+            return Some(amounts);
+        }
+
+        let address_a = Self::base_asset_address(env, registry_client, &symbol_a);
+        let address_b = Self::base_asset_address(env, registry_client, &symbol_b);
+        let (reserve_a, reserve_b, total_shares) = if is_aquarius {
+            // Aquarius pools list their tokens, and reserves, in address order
+            let router_client = aquarius_router_contract::Client::new(
+                env,
+                &registry_client.get_aquarius_router_address(),
+            );
+            let pool_index = registry_client.get_aquarius_pool_index();
+            let a_first = address_a < address_b;
+            let tokens = if a_first {
+                soroban_sdk::vec![env, address_a.clone(), address_b.clone()]
+            } else {
+                soroban_sdk::vec![env, address_b.clone(), address_a.clone()]
+            };
+            let reserves = router_client.get_reserves(&tokens, &pool_index);
+            let total_shares = router_client.get_total_shares(&tokens, &pool_index);
+            let (reserve0, reserve1) = (reserves.get_unchecked(0), reserves.get_unchecked(1));
+            if a_first {
+                (reserve0, reserve1, total_shares)
+            } else {
+                (reserve1, reserve0, total_shares)
+            }
+        } else {
+            let router_client = soroswap_router_contract::Client::new(
+                env,
+                &registry_client.get_soroswap_router_address(),
+            );
+            let pair_client = soroswap_pair_contract::Client::new(
+                env,
+                &router_client.router_pair_for(&address_a, &address_b),
+            );
+            let (reserve0, reserve1) = pair_client.get_reserves();
+            let total_shares = pair_client.total_supply().max(0) as u128;
+            let (reserve0, reserve1) = (reserve0.max(0) as u128, reserve1.max(0) as u128);
+            if pair_client.token_0() == address_a {
+                (reserve0, reserve1, total_shares)
+            } else {
+                (reserve1, reserve0, total_shares)
+            }
+        };
+        if total_shares == 0 {
+            return Some(amounts);
+        }
+
+        for (symbol, address, reserve) in [
+            (symbol_a, address_a, reserve_a),
+            (symbol_b, address_b, reserve_b),
+        ] {
+            let amount = U256::from_u128(env, liquidity as u128)
+                .mul(&U256::from_u128(env, reserve))
+                .div(&U256::from_u128(env, total_shares));
+            let decimals = token::Client::new(env, &address).decimals();
+            amounts.push_back((symbol, Self::scale_to_wad(env, amount, decimals)));
+        }
+        Some(amounts)
+    }
+
+    fn base_asset_address(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        symbol: &Symbol,
+    ) -> Address {
+        if *symbol == XLM_SYMBOL {
+            registry_client.get_xlm_contract_adddress()
+        } else if *symbol == USDC_SYMBOL {
+            registry_client.get_usdc_contract_address()
+        } else if *symbol == EURC_SYMBOL {
+            registry_client.get_eurc_contract_address()
+        } else {
+            panic_with_error!(env, RiskEngineError::UnsupportedToken);
         }
     }
 
@@ -404,6 +1452,13 @@ impl RiskEngineContract {
         amount.mul(&U256::from_u128(env, WAD_U128)).div(&scale)
     }
 
+    fn set_emode_category_ids(env: &Env, ids: Vec<u32>) {
+        env.storage()
+            .persistent()
+            .set(&RiskEngineKey::EModeCategoryIds, &ids);
+        Self::extend_ttl_risk(env, RiskEngineKey::EModeCategoryIds);
+    }
+
     fn extend_ttl_risk(env: &Env, key: RiskEngineKey) {
         env.storage()
             .persistent()
@@ -464,3 +1519,48 @@ pub mod lending_protocol_eurc {
         file = "../../target/wasm32v1-none/release/lending_protocol_eurc.wasm"
     );
 }
+
+// Valuation subset of the protocol adapter interface (see SmartAccountContract)
+pub mod protocol_adapter_contract {
+    use soroban_sdk::{contractclient, Address, Env, Symbol, Vec, U256};
+
+    #[contractclient(name = "Client")]
+    pub trait ProtocolAdapterTrait {
+        fn value_position(env: Env, smart_account: Address) -> Vec<(Symbol, U256)>;
+    }
+}
+
+// Read-only subset of the Aquarius router interface used to value LP positions
+pub mod aquarius_router_contract {
+    use soroban_sdk::{contractclient, Address, BytesN, Env, Vec};
+
+    #[contractclient(name = "Client")]
+    pub trait AquariusRouterTrait {
+        fn get_reserves(env: Env, tokens: Vec<Address>, pool_index: BytesN<32>) -> Vec<u128>;
+
+        fn get_total_shares(env: Env, tokens: Vec<Address>, pool_index: BytesN<32>) -> u128;
+    }
+}
+
+// Read-only subset of the Soroswap router and pair interfaces used to value LP positions
+pub mod soroswap_router_contract {
+    use soroban_sdk::{contractclient, Address, Env};
+
+    #[contractclient(name = "Client")]
+    pub trait SoroswapRouterTrait {
+        fn router_pair_for(env: Env, token_a: Address, token_b: Address) -> Address;
+    }
+}
+
+pub mod soroswap_pair_contract {
+    use soroban_sdk::{contractclient, Address, Env};
+
+    #[contractclient(name = "Client")]
+    pub trait SoroswapPairTrait {
+        fn token_0(env: Env) -> Address;
+
+        fn get_reserves(env: Env) -> (i128, i128);
+
+        fn total_supply(env: Env) -> i128;
+    }
+}
@@ -1,4 +1,4 @@
-use soroban_sdk::{Address, Symbol, contracterror, contracttype};
+use soroban_sdk::{Address, Symbol, U256, Vec, contracterror, contracttype};
 
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[contracttype]
@@ -23,10 +23,90 @@ pub enum AccountDataKey {
 pub enum RiskEngineKey {
     RegistryContract,
     Admin,
+    DeleverageThreshold, // Health ratio below which anyone may deleverage an account
+    CollateralFactorRamp(Symbol), // Schedule moving a collateral asset's factor toward a target
+    EModeCategory(u32),  // Efficiency mode category -> its assets and collateral factor
+    EModeCategoryIds,    // Ids of all configured efficiency mode categories
+    EModeFactorRamp(u32), // Schedule moving an efficiency mode category's factor toward a target
+    AccountLimits,       // Per-account leverage and borrow value caps
+    ProtocolExposureLimit(Address), // Max value a single account may hold in an external protocol
 }
 
 #[contracterror]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum RiskEngineError {
     RiskEngineNotInitialized = 1,
+    InsufficientCollateral = 2,
+    InvalidThreshold = 3,
+    UnsupportedToken = 4,
+    InvalidCollateralFactor = 5,
+    IsolationBorrowNotAllowed = 6,
+    IsolatedDebtCeilingExceeded = 7,
+    EModeCategoryNotFound = 8,
+    LeverageLimitExceeded = 9,
+    BorrowLimitExceeded = 10,
+    ProtocolExposureLimitExceeded = 11,
+}
+
+/// One collateral or debt position of an account as seen by the health checks.
+/// Amounts, prices and values are in WAD; prices come from the oracle by `price_symbol`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssetBreakdown {
+    pub symbol: Symbol,
+    pub price_symbol: Symbol,
+    pub amount: U256,
+    pub price: U256,
+    pub value: U256,
+    pub weighted_value: U256, // Value counted toward the health factor
+    pub is_debt: bool,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountBreakdown {
+    pub assets: Vec<AssetBreakdown>,
+    pub total_collateral_value: U256,
+    pub total_debt_value: U256,
+    pub health_factor: U256,
+}
+
+/// Hypothetical step applied by `RiskEngineContract::simulate`. Amounts are in WAD.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SimulatedAction {
+    DepositCollateral(Symbol, U256),
+    WithdrawCollateral(Symbol, U256),
+    Borrow(Symbol, U256),
+    Repay(Symbol, U256),
+}
+
+/// Collateral factor of an asset moving linearly from `start_factor_bps` at `start_time`
+/// to `target_factor_bps` at `end_time`. Times are ledger timestamps.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CollateralFactorRamp {
+    pub start_factor_bps: u32,
+    pub target_factor_bps: u32,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+/// Group of correlated assets. An account whose collateral and debt all belong to one
+/// category may borrow up to `collateral_factor_bps` of its collateral value, reached
+/// through the category's factor ramp.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EModeCategory {
+    pub collateral_factor_bps: u32,
+    pub assets: Vec<Symbol>,
+}
+
+/// Caps checked for every account on borrow and `execute`, in WAD. Leverage is the balance
+/// over the balance less debt; a zero field disables that cap.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountLimits {
+    pub max_leverage_wad: U256,
+    pub max_borrow_value_wad: U256,
 }
@@ -10,7 +10,7 @@ use crate::types::{
     SmartAccountDeactivationEvent, SmartAccountError,
 };
 
-use blend_contract_sdk::pool::{self, Positions, Reserve};
+use blend_contract_sdk::pool::Reserve;
 use blend_contract_sdk::pool::{PoolConfig, ReserveConfig, ReserveData};
 
 use blend_contract_sdk::pool::{Client as BlendPoolClient, Request};
@@ -21,6 +21,7 @@ use soroban_sdk::BytesN;
 const TLL_LEDGERS_YEAR: u32 = 6307200;
 const TLL_LEDGERS_10YEAR: u32 = 6307200 * 10;
 const WAD_U128: u128 = 10000_0000_00000_00000; // 10^18 for decimals
+const SCALAR_12_I128: i128 = 1_000_000_000_000; // Blend b_rate / d_rate scale
 const XLM_SYMBOL: Symbol = symbol_short!("XLM");
 const USDC_SYMBOL: Symbol = symbol_short!("USDC");
 const EURC_SYMBOL: Symbol = symbol_short!("EURC");
@@ -28,6 +29,9 @@ const EURC_SYMBOL: Symbol = symbol_short!("EURC");
 // Aquarius pool pair symbol (for XLM-USDC LP tracking)
 const AQUARIUS_XLM_USDC_SYMBOL: Symbol = symbol_short!("AQ_XLM_U");
 
+// Soroswap router rejects transactions executed after the deadline (in seconds)
+const SOROSWAP_DEADLINE_SECS: u64 = 300;
+
 #[contract]
 pub struct SmartAccountContract;
 
@@ -172,6 +176,10 @@ impl SmartAccountContract {
             let eurc_token = token::Client::new(&env, &native_eurc_address);
             let amount_scaled = Self::scale_for_operation(amount_wad, eurc_token.decimals());
             eurc_token.transfer(&this_account, &user_address, &amount_scaled);
+        } else if let Some(asset) = registry_client.get_collateral_asset(&token_symbol) {
+            let asset_token = token::Client::new(&env, &asset.token_address);
+            let amount_scaled = Self::scale_for_operation(amount_wad, asset_token.decimals());
+            asset_token.transfer(&this_account, &user_address, &amount_scaled);
         }
 
         let collateral_balance_wad = Self::get_collateral_token_balance(&env, token_symbol.clone());
@@ -214,6 +222,16 @@ impl SmartAccountContract {
         Ok(())
     }
 
+    pub fn set_owner_address(env: &Env, owner_address: Address) {
+        let account_manager: Address = Self::get_account_manager(&env);
+        account_manager.require_auth();
+
+        env.storage()
+            .persistent()
+            .set(&SmartAccountDataKey::OwnerAddress, &owner_address);
+        Self::extend_ttl_smart_account(&env, SmartAccountDataKey::OwnerAddress);
+    }
+
     pub fn has_debt(env: &Env) -> bool {
         env.storage()
             .persistent()
@@ -281,6 +299,7 @@ impl SmartAccountContract {
         trader_address: Address,
         tokens: Vec<Symbol>,
         tokens_amount_wad: Vec<u128>,
+        min_amounts_wad: Vec<u128>,
     ) -> Result<(bool, i128), SmartAccountError> {
         let account_manager: Address = Self::get_account_manager(&env);
         account_manager.require_auth();
@@ -289,11 +308,25 @@ impl SmartAccountContract {
         let registry_client = registry_contract::Client::new(env, &registry_address);
         let smart_account = env.current_contract_address();
 
-        // Determine which protocol this is
-        // First check if it's an Aquarius-only action (AddLiquidity or RemoveLiquidity)
-        let is_aquarius_action = matches!(action, SmartAccExternalAction::AddLiquidity | SmartAccExternalAction::RemoveLiquidity);
-        
-        if is_aquarius_action {
+        // Venues are matched by address, like protocol adapters; Aquarius and Soroswap
+        // support the same actions
+        if registry_client.has_soroswap_router_address()
+            && target_protocol == registry_client.get_soroswap_router_address()
+        {
+            return Self::execute_soroswap(
+                env,
+                &registry_client,
+                action,
+                &smart_account,
+                tokens,
+                tokens_amount_wad,
+                min_amounts_wad,
+            );
+        }
+
+        if registry_client.has_aquarius_router_address()
+            && target_protocol == registry_client.get_aquarius_router_address()
+        {
             return Self::execute_aquarius(
                 env,
                 &registry_client,
@@ -305,164 +338,310 @@ impl SmartAccountContract {
             );
         }
 
-        // For shared actions (Deposit, Withdraw, Swap), check target_protocol address
-        // to determine which protocol to route to
-        // We'll check Aquarius first (supports more actions), then Blend
-        
-        // Check if Aquarius router is configured
-        if registry_client.has_aquarius_router_address() {
-            let aquarius_router_address = registry_client.get_aquarius_router_address();
-            if target_protocol == aquarius_router_address {
-                return Self::execute_aquarius(
-                    env,
-                    &registry_client,
-                    action,
-                    &trader_address,
-                    &smart_account,
-                    tokens,
-                    tokens_amount_wad,
-                );
+        // Check if this is one of the whitelisted Blend pools; the account manager limits
+        // delisted pools to exit-only requests
+        if registry_client.is_blend_pool(&target_protocol)
+            || registry_client.is_delisted_blend_pool(&target_protocol)
+        {
+            let request_type: u32 = match action {
+                SmartAccExternalAction::Deposit => 0,
+                SmartAccExternalAction::Withdraw => 1,
+                SmartAccExternalAction::SupplyCollateral => 2,
+                SmartAccExternalAction::WithdrawCollateral => 3,
+                SmartAccExternalAction::BlendBorrow => 4,
+                SmartAccExternalAction::BlendRepay => 5,
+                _ => return Err(SmartAccountError::InvalidAction),
+            };
+
+            if tokens.len() != 1 {
+                return Err(SmartAccountError::InvalidTokenCount);
             }
+
+            return Self::execute_blend(
+                env,
+                &registry_client,
+                &target_protocol,
+                &smart_account,
+                request_type,
+                &tokens.get(0).unwrap(),
+                tokens_amount_wad.get(0).unwrap(),
+            );
         }
-        
-        // Check if Blend pool is configured
-        if registry_client.has_blend_pool_address() {
-            let blend_pool_address = registry_client.get_blend_pool_address();
-            if target_protocol == blend_pool_address {
-                // Handle Blend protocol operations
-                let mut request_type: u32 = 0;
-
-                match action {
-                    SmartAccExternalAction::Deposit => request_type = 0,
-                    SmartAccExternalAction::Withdraw => request_type = 1,
-                    SmartAccExternalAction::Swap => request_type = 10,
-                    _ => panic!("Invalid action for Blend protocol"),
+
+        // No matching protocol found
+        Err(SmartAccountError::ProtocolNotSupported)
+    }
+
+    /// Submits a single Blend request and returns the change in the position it affects:
+    /// supply b-tokens (0/1), collateral b-tokens (2/3) or liability d-tokens (4/5).
+    fn execute_blend(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        blend_pool_address: &Address,
+        smart_account: &Address,
+        request_type: u32,
+        token: &Symbol,
+        amount_wad: u128,
+    ) -> Result<(bool, i128), SmartAccountError> {
+        log!(&env, "Token symbol passed: {}", token.clone());
+        let blend_pool_client = BlendPoolClient::new(env, blend_pool_address);
+        let token_address = Self::token_address_for_symbol(registry_client, token);
+        let token_client = token::Client::new(&env, &token_address);
+        let amt = Self::scale_from_wad(amount_wad, token_client.decimals());
+
+        let resv: Reserve = blend_pool_client.get_reserve(&token_address);
+        let index = resv.config.index;
+        let positions_before = blend_pool_client.get_positions(smart_account);
+
+        let mut requests = Vec::new(env);
+        requests.push_back(Request {
+            address: token_address,
+            amount: amt,
+            request_type,
+        });
+
+        let positions = blend_pool_client.submit(smart_account, smart_account, smart_account, &requests);
+        log!(
+            &env,
+            "Blend Pool request {} b_rate {}, amount {}",
+            request_type,
+            resv.data.b_rate,
+            amt
+        );
+
+        let (before, after) = match request_type {
+            0 | 1 => (positions_before.supply, positions.supply),
+            2 | 3 => (positions_before.collateral, positions.collateral),
+            _ => (positions_before.liabilities, positions.liabilities),
+        };
+        let delta = after.get(index).unwrap_or(0) - before.get(index).unwrap_or(0);
+
+        Ok((true, delta))
+    }
+
+    /// Closes this account's whole position in `blend_pool` for every listed (token, request
+    /// type) in a single submit. Only repay (5), withdraw collateral (3) and withdraw (1) are
+    /// accepted and they are submitted in that order. Amounts are rounded up by one unit
+    /// since Blend caps these requests at the position size.
+    pub fn unwind_blend_positions(
+        env: &Env,
+        blend_pool: Address,
+        positions: Vec<(Symbol, u32)>,
+    ) -> Result<(), SmartAccountError> {
+        let account_manager: Address = Self::get_account_manager(&env);
+        account_manager.require_auth();
+
+        let registry_address = Self::get_registry_address(&env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        let blend_pool_client = BlendPoolClient::new(env, &blend_pool);
+        let smart_account = env.current_contract_address();
+        let current = blend_pool_client.get_positions(&smart_account);
+
+        let mut requests = Vec::new(env);
+        for pass in [5u32, 3, 1] {
+            for (token, request_type) in positions.iter() {
+                if request_type != pass {
+                    continue;
                 }
-                
-                let blend_pool_client = BlendPoolClient::new(env, &blend_pool_address);
-
-            for (token, amt_wad) in tokens.iter().zip(tokens_amount_wad) {
-                log!(&env, "Token symbol passed: {}", token);
-                if token == XLM_SYMBOL {
-                    let native_xlm_address = registry_client.get_xlm_contract_adddress();
-                    let xlm_token = token::Client::new(&env, &native_xlm_address);
-                    let amt = Self::scale_from_wad(amt_wad, xlm_token.decimals());
-                    let resv: Reserve = blend_pool_client.get_reserve(&native_xlm_address);
-                    let pool_config: pool::PoolConfig = blend_pool_client.get_config();
-                    blend_pool_client.get_config().oracle;
-                    let positions_before = blend_pool_client.get_positions(&smart_account);
-                    let b_tokens_before =
-                        positions_before.supply.get(resv.config.index).unwrap_or(0);
-
-                    let b_rate = resv.data.b_rate;
-                    let request = Request {
-                        address: native_xlm_address,
-                        amount: amt,
-                        request_type,
-                    };
-                    let mut requests = Vec::new(env);
-                    requests.push_back(request);
-
-                    let positions = blend_pool_client.submit(
-                        &smart_account,
-                        &smart_account,
-                        &resv.asset,
-                        &requests,
-                    );
-                    if request_type == 0 {
-                        log!(&env, "Blend Pool Deposit b_rate {}, amount {}", b_rate, amt);
-                        let b_tokens_minted =
-                            positions.supply.get_unchecked(resv.config.index) - b_tokens_before;
-                        return Ok((true, b_tokens_minted));
-                    } else if request_type == 1 {
-                        let b_tokens_burned =
-                            b_tokens_before - positions.supply.get_unchecked(resv.config.index);
-                        return Ok((true, -b_tokens_burned));
-                    } else {
-                        panic!("Unsupported request type for XLM in Blend Pool");
-                    }
-                } else if token == USDC_SYMBOL {
-                    let usdc_contract_address = registry_client.get_usdc_contract_address();
-                    let usdc_token = token::Client::new(&env, &usdc_contract_address);
-                    let amt = Self::scale_from_wad(amt_wad, usdc_token.decimals());
-                    let resv = blend_pool_client.get_reserve(&usdc_contract_address);
-                    let b_rate = resv.data.b_rate;
-                    let positions_before = blend_pool_client.get_positions(&smart_account);
-                    let b_tokens_before =
-                        positions_before.supply.get(resv.config.index).unwrap_or(0);
-
-                    let request = Request {
-                        address: usdc_contract_address,
-                        amount: amt,
-                        request_type,
-                    };
-                    let mut requests = Vec::new(env);
-                    requests.push_back(request);
-
-                    let positions = blend_pool_client.submit(
-                        &smart_account,
-                        &smart_account,
-                        &resv.asset,
-                        &requests,
-                    );
-                    if request_type == 0 {
-                        log!(&env, "Blend Pool Deposit b_rate {}, amount {}", b_rate, amt);
-                        let b_tokens_minted =
-                            positions.supply.get_unchecked(resv.config.index) - b_tokens_before;
-                        return Ok((true, b_tokens_minted));
-                    } else if request_type == 1 {
-                        let b_tokens_burned =
-                            b_tokens_before - positions.supply.get_unchecked(resv.config.index);
-                        return Ok((true, -b_tokens_burned));
-                    } else {
-                        panic!("Unsupported request type for XLM in Blend Pool");
-                    }
-                } else if token == EURC_SYMBOL {
-                    let eurc_contract_address = registry_client.get_eurc_contract_address();
-                    let eurc_token = token::Client::new(&env, &eurc_contract_address);
-                    let amt = Self::scale_from_wad(amt_wad, eurc_token.decimals());
-                    let resv = blend_pool_client.get_reserve(&eurc_contract_address);
-                    let b_rate = resv.data.b_rate;
-                    let positions_before = blend_pool_client.get_positions(&smart_account);
-                    let b_tokens_before =
-                        positions_before.supply.get(resv.config.index).unwrap_or(0);
-
-                    let request = Request {
-                        address: eurc_contract_address,
-                        amount: amt,
-                        request_type,
-                    };
-                    let mut requests = Vec::new(env);
-                    requests.push_back(request);
-
-                    let positions = blend_pool_client.submit(
-                        &smart_account,
-                        &smart_account,
-                        &resv.asset,
-                        &requests,
-                    );
-                    if request_type == 0 {
-                        log!(&env, "Blend Pool Deposit b_rate {}, amount {}", b_rate, amt);
-                        let b_tokens_minted =
-                            positions.supply.get_unchecked(resv.config.index) - b_tokens_before;
-                        return Ok((true, b_tokens_minted));
-                    } else if request_type == 1 {
-                        let b_tokens_burned =
-                            b_tokens_before - positions.supply.get_unchecked(resv.config.index);
-                        return Ok((true, -b_tokens_burned));
-                    } else {
-                        panic!("Unsupported request type for XLM in Blend Pool");
-                    }
-                } else {
-                    panic!("No external protocol mapped for the given token symbol");
+                let token_address = Self::token_address_for_symbol(&registry_client, &token);
+                let reserve = blend_pool_client.get_reserve(&token_address);
+                let index = reserve.config.index;
+                let (units, rate) = match request_type {
+                    5 => (current.liabilities.get(index), reserve.data.d_rate),
+                    3 => (current.collateral.get(index), reserve.data.b_rate),
+                    1 => (current.supply.get(index), reserve.data.b_rate),
+                    _ => return Err(SmartAccountError::InvalidAction),
+                };
+                let units = units.unwrap_or(0);
+                if units == 0 {
+                    continue;
                 }
+                requests.push_back(Request {
+                    address: token_address,
+                    amount: units * rate / SCALAR_12_I128 + 1,
+                    request_type,
+                });
             }
-                return Ok((false, 0));
+        }
+
+        if !requests.is_empty() {
+            blend_pool_client.submit(&smart_account, &smart_account, &smart_account, &requests);
+            log!(&env, "Blend positions unwound: {} requests", requests.len());
+        }
+        Ok(())
+    }
+
+    /// Amount of `token` in WAD that `unwind_blend_positions` repays to close this account's
+    /// liability in it on `blend_pool`.
+    pub fn get_blend_liability(env: &Env, blend_pool: Address, token: Symbol) -> u128 {
+        let registry_address = Self::get_registry_address(&env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        let blend_pool_client = BlendPoolClient::new(env, &blend_pool);
+        let token_address = Self::token_address_for_symbol(&registry_client, &token);
+        let reserve = blend_pool_client.get_reserve(&token_address);
+        let units = blend_pool_client
+            .get_positions(&env.current_contract_address())
+            .liabilities
+            .get(reserve.config.index)
+            .unwrap_or(0);
+        if units == 0 {
+            return 0;
+        }
+        let amount = (units * reserve.data.d_rate / SCALAR_12_I128 + 1) as u128;
+        amount * (WAD_U128 / 10u128.pow(token::Client::new(env, &token_address).decimals()))
+    }
+
+    /// Claims BLND emissions accrued by every position of this account in `blend_pool`
+    /// (d-token id = index * 2, b-token id = index * 2 + 1) to `to` and returns the amount
+    /// claimed.
+    pub fn claim_blend_emissions(
+        env: &Env,
+        blend_pool: Address,
+        to: Address,
+    ) -> Result<i128, SmartAccountError> {
+        let account_manager: Address = Self::get_account_manager(&env);
+        account_manager.require_auth();
+
+        let blend_pool_client = BlendPoolClient::new(env, &blend_pool);
+        let smart_account = env.current_contract_address();
+
+        let positions = blend_pool_client.get_positions(&smart_account);
+        let mut reserve_token_ids: Vec<u32> = Vec::new(env);
+        for index in positions.liabilities.keys().iter() {
+            reserve_token_ids.push_back(index * 2);
+        }
+        for index in positions.supply.keys().iter().chain(positions.collateral.keys().iter()) {
+            let b_token_id = index * 2 + 1;
+            if !reserve_token_ids.contains(b_token_id) {
+                reserve_token_ids.push_back(b_token_id);
             }
         }
-        
-        // No matching protocol found
-        panic!("No external protocol mapped for the given protocol address");
+
+        if reserve_token_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let claimed = blend_pool_client.claim(&smart_account, &reserve_token_ids, &to);
+        log!(&env, "Blend emissions claimed {}", claimed);
+        Ok(claimed)
+    }
+
+    /// Sells reward tokens held by this account on the configured Aquarius reward pool.
+    /// Returns the amount received in WAD.
+    pub fn sell_reward_tokens(
+        env: &Env,
+        reward_token: Address,
+        token_out: Symbol,
+        amount: i128,
+        min_amount_out_wad: u128,
+    ) -> Result<u128, SmartAccountError> {
+        let account_manager: Address = Self::get_account_manager(&env);
+        account_manager.require_auth();
+
+        let registry_address = Self::get_registry_address(&env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        let router_client = aquarius_router_contract::Client::new(
+            env,
+            &registry_client.get_aquarius_router_address(),
+        );
+
+        let token_out_address = Self::token_address_for_symbol(&registry_client, &token_out);
+        let token_out_decimals = token::Client::new(env, &token_out_address).decimals();
+
+        let mut token_vec = soroban_sdk::vec![env, reward_token.clone(), token_out_address.clone()];
+        if reward_token > token_out_address {
+            token_vec = soroban_sdk::vec![env, token_out_address.clone(), reward_token.clone()];
+        }
+
+        let amount_out = router_client.swap(
+            &env.current_contract_address(),
+            &token_vec,
+            &reward_token,
+            &token_out_address,
+            &registry_client.get_blend_reward_pool_index(),
+            &(amount as u128),
+            &(Self::scale_from_wad(min_amount_out_wad, token_out_decimals) as u128),
+        );
+
+        Ok(amount_out * (WAD_U128 / 10u128.pow(token_out_decimals)))
+    }
+
+    /// Swaps collateral held by this account on `venue` (the Aquarius or Soroswap router)
+    /// and moves the collateral balances from `token_in` to `token_out`. Returns the amount
+    /// received in WAD.
+    pub fn swap_collateral(
+        env: &Env,
+        venue: Address,
+        token_in: Symbol,
+        token_out: Symbol,
+        amount_in_wad: u128,
+        min_amount_out_wad: u128,
+    ) -> Result<u128, SmartAccountError> {
+        let account_manager: Address = Self::get_account_manager(&env);
+        account_manager.require_auth();
+
+        let registry_address = Self::get_registry_address(&env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+
+        let token_in_address = Self::token_address_for_symbol(&registry_client, &token_in);
+        let token_out_address = Self::token_address_for_symbol(&registry_client, &token_out);
+        let token_in_decimals = token::Client::new(env, &token_in_address).decimals();
+        let token_out_decimals = token::Client::new(env, &token_out_address).decimals();
+        let amount_in = Self::scale_from_wad(amount_in_wad, token_in_decimals);
+        let min_amount_out = Self::scale_from_wad(min_amount_out_wad, token_out_decimals);
+
+        let amount_out = if registry_client.has_aquarius_router_address()
+            && venue == registry_client.get_aquarius_router_address()
+        {
+            let mut token_vec =
+                soroban_sdk::vec![env, token_in_address.clone(), token_out_address.clone()];
+            if token_in_address > token_out_address {
+                token_vec =
+                    soroban_sdk::vec![env, token_out_address.clone(), token_in_address.clone()];
+            }
+            aquarius_router_contract::Client::new(env, &venue).swap(
+                &env.current_contract_address(),
+                &token_vec,
+                &token_in_address,
+                &token_out_address,
+                &registry_client.get_aquarius_pair_pool_index(&token_in, &token_out),
+                &(amount_in as u128),
+                &(min_amount_out as u128),
+            )
+        } else if registry_client.has_soroswap_router_address()
+            && venue == registry_client.get_soroswap_router_address()
+        {
+            let amounts = soroswap_router_contract::Client::new(env, &venue)
+                .swap_exact_tokens_for_tokens(
+                    &amount_in,
+                    &min_amount_out,
+                    &soroban_sdk::vec![env, token_in_address, token_out_address],
+                    &env.current_contract_address(),
+                    &(env.ledger().timestamp() + SOROSWAP_DEADLINE_SECS),
+                );
+            amounts.last().unwrap_or(0) as u128
+        } else {
+            return Err(SmartAccountError::InvalidAction);
+        };
+        let amount_out_wad = amount_out * (WAD_U128 / 10u128.pow(token_out_decimals));
+
+        let balance_in_wad = Self::get_collateral_token_balance(env, token_in.clone())
+            .sub(&U256::from_u128(env, amount_in_wad));
+        Self::set_collateral_token_bal_internal(env, token_in.clone(), balance_in_wad.clone());
+        if balance_in_wad == U256::from_u128(env, 0) {
+            Self::remove_collateral_token(env, token_in)?;
+        }
+
+        let mut collateral_tokens = Self::get_all_collateral_tokens(env);
+        if !collateral_tokens.contains(&token_out) {
+            collateral_tokens.push_back(token_out.clone());
+            Self::set_collateral_tokens_list(env, collateral_tokens);
+        }
+        let balance_out_wad = Self::get_collateral_token_balance(env, token_out.clone())
+            .add(&U256::from_u128(env, amount_out_wad));
+        Self::set_collateral_token_bal_internal(env, token_out, balance_out_wad);
+
+        Ok(amount_out_wad)
     }
 
     fn execute_aquarius(
@@ -481,7 +660,7 @@ impl SmartAccountContract {
             SmartAccExternalAction::AddLiquidity => {
                 // Add liquidity to XLM/USDC pool
                 if tokens.len() != 2 {
-                    panic!("AddLiquidity requires exactly 2 tokens");
+                    return Err(SmartAccountError::InvalidTokenCount);
                 }
 
                 let token0 = tokens.get(0).unwrap();
@@ -495,7 +674,7 @@ impl SmartAccountContract {
                 } else if token0 == USDC_SYMBOL {
                     registry_client.get_usdc_contract_address()
                 } else {
-                    panic!("Unsupported token for Aquarius");
+                    return Err(SmartAccountError::UnsupportedToken);
                 };
 
                 let token1_address = if token1 == XLM_SYMBOL {
@@ -503,7 +682,7 @@ impl SmartAccountContract {
                 } else if token1 == USDC_SYMBOL {
                     registry_client.get_usdc_contract_address()
                 } else {
-                    panic!("Unsupported token for Aquarius");
+                    return Err(SmartAccountError::UnsupportedToken);
                 };
 
                 let token0_client = token::Client::new(env, &token0_address);
@@ -556,7 +735,7 @@ impl SmartAccountContract {
             SmartAccExternalAction::RemoveLiquidity => {
                 // Remove liquidity from XLM/USDC pool
                 if tokens.len() != 2 {
-                    panic!("RemoveLiquidity requires exactly 2 tokens");
+                    return Err(SmartAccountError::InvalidTokenCount);
                 }
 
                 let token0 = tokens.get(0).unwrap();
@@ -569,7 +748,7 @@ impl SmartAccountContract {
                 } else if token0 == USDC_SYMBOL {
                     registry_client.get_usdc_contract_address()
                 } else {
-                    panic!("Unsupported token for Aquarius");
+                    return Err(SmartAccountError::UnsupportedToken);
                 };
 
                 let token1_address = if token1 == XLM_SYMBOL {
@@ -577,7 +756,7 @@ impl SmartAccountContract {
                 } else if token1 == USDC_SYMBOL {
                     registry_client.get_usdc_contract_address()
                 } else {
-                    panic!("Unsupported token for Aquarius");
+                    return Err(SmartAccountError::UnsupportedToken);
                 };
 
                 // Ensure tokens are sorted
@@ -611,7 +790,7 @@ impl SmartAccountContract {
             SmartAccExternalAction::Swap => {
                 // Swap tokens in Aquarius pool
                 if tokens.len() != 2 {
-                    panic!("Swap requires exactly 2 tokens (in and out)");
+                    return Err(SmartAccountError::InvalidTokenCount);
                 }
 
                 let token_in = tokens.get(0).unwrap();
@@ -624,7 +803,7 @@ impl SmartAccountContract {
                 } else if token_in == USDC_SYMBOL {
                     registry_client.get_usdc_contract_address()
                 } else {
-                    panic!("Unsupported token for Aquarius swap");
+                    return Err(SmartAccountError::UnsupportedToken);
                 };
 
                 let token_out_address = if token_out == XLM_SYMBOL {
@@ -632,7 +811,7 @@ impl SmartAccountContract {
                 } else if token_out == USDC_SYMBOL {
                     registry_client.get_usdc_contract_address()
                 } else {
-                    panic!("Unsupported token for Aquarius swap");
+                    return Err(SmartAccountError::UnsupportedToken);
                 };
 
                 let token_in_client = token::Client::new(env, &token_in_address);
@@ -668,7 +847,201 @@ impl SmartAccountContract {
                 return Ok((true, 0)); // Swap doesn't affect LP tracking
             }
 
-            _ => panic!("Invalid action for Aquarius protocol"),
+            _ => Err(SmartAccountError::InvalidAction),
+        }
+    }
+
+    /// Routes an external call through a whitelisted protocol adapter.
+    /// Input tokens for Deposit / Swap / AddLiquidity are pushed to the adapter first,
+    /// the adapter holds the resulting position on behalf of this account and returns
+    /// the tracking token deltas to be minted (positive) or burned (negative).
+    pub fn execute_via_adapter(
+        env: &Env,
+        adapter_address: Address,
+        action: SmartAccExternalAction,
+        tokens: Vec<Symbol>,
+        tokens_amount_wad: Vec<u128>,
+    ) -> Result<Vec<(Symbol, i128)>, SmartAccountError> {
+        let account_manager: Address = Self::get_account_manager(&env);
+        account_manager.require_auth();
+
+        let registry_address = Self::get_registry_address(&env);
+        let registry_client = registry_contract::Client::new(env, &registry_address);
+        if !registry_client.is_protocol_adapter(&adapter_address) {
+            return Err(SmartAccountError::AdapterNotWhitelisted);
+        }
+
+        let smart_account = env.current_contract_address();
+        let pushes_tokens = matches!(
+            action,
+            SmartAccExternalAction::Deposit
+                | SmartAccExternalAction::Swap
+                | SmartAccExternalAction::AddLiquidity
+        );
+
+        let mut token_addresses = Vec::new(env);
+        let mut amounts = Vec::new(env);
+        for (token, amt_wad) in tokens.iter().zip(tokens_amount_wad) {
+            let token_address = Self::token_address_for_symbol(&registry_client, &token);
+            let token_client = token::Client::new(env, &token_address);
+            let amount = Self::scale_from_wad(amt_wad, token_client.decimals());
+
+            if pushes_tokens && amount > 0 {
+                token_client.transfer(&smart_account, &adapter_address, &amount);
+            }
+
+            token_addresses.push_back(token_address);
+            amounts.push_back(amount);
+        }
+
+        let adapter_client = protocol_adapter_contract::Client::new(env, &adapter_address);
+        let deltas = adapter_client.execute(&smart_account, &action, &token_addresses, &amounts);
+
+        log!(env, "Adapter execute: {} tracking deltas", deltas.len());
+
+        Ok(deltas)
+    }
+
+    /// Soroswap amounts are in WAD, LP amounts included, and are scaled to the pair tokens'
+    /// decimals here. `min_amounts_wad` holds the minimum token A / token B amounts followed by
+    /// the minimum LP received for AddLiquidity, the minimum token A / token B amounts for
+    /// RemoveLiquidity and the minimum amount out for Swap. Missing entries default to zero.
+    fn execute_soroswap(
+        env: &Env,
+        registry_client: &registry_contract::Client,
+        action: SmartAccExternalAction,
+        smart_account: &Address,
+        tokens: Vec<Symbol>,
+        tokens_amount_wad: Vec<u128>,
+        min_amounts_wad: Vec<u128>,
+    ) -> Result<(bool, i128), SmartAccountError> {
+        let router_address = registry_client.get_soroswap_router_address();
+        let router_client = soroswap_router_contract::Client::new(env, &router_address);
+        let deadline = env.ledger().timestamp() + SOROSWAP_DEADLINE_SECS;
+
+        if tokens.len() != 2 {
+            return Err(SmartAccountError::InvalidTokenCount);
+        }
+        let token_a_address = Self::token_address_for_symbol(registry_client, &tokens.get(0).unwrap());
+        let token_b_address = Self::token_address_for_symbol(registry_client, &tokens.get(1).unwrap());
+        let token_a_decimals = token::Client::new(env, &token_a_address).decimals();
+        let token_b_decimals = token::Client::new(env, &token_b_address).decimals();
+        let min_amount_a = Self::scale_from_wad(min_amounts_wad.get(0).unwrap_or(0), token_a_decimals);
+        let min_amount_b = Self::scale_from_wad(min_amounts_wad.get(1).unwrap_or(0), token_b_decimals);
+
+        match action {
+            SmartAccExternalAction::AddLiquidity => {
+                let amount_a = Self::scale_from_wad(tokens_amount_wad.get(0).unwrap(), token_a_decimals);
+                let amount_b = Self::scale_from_wad(tokens_amount_wad.get(1).unwrap(), token_b_decimals);
+
+                // The router sorts the pair itself and creates it if it doesn't exist yet
+                let (_amount_a, _amount_b, liquidity) = router_client.add_liquidity(
+                    &token_a_address,
+                    &token_b_address,
+                    &amount_a,
+                    &amount_b,
+                    &min_amount_a,
+                    &min_amount_b,
+                    smart_account,
+                    &deadline,
+                );
+
+                let lp_decimals = Self::soroswap_lp_decimals(
+                    env,
+                    &router_client,
+                    &token_a_address,
+                    &token_b_address,
+                );
+                let min_liquidity = Self::scale_from_wad(min_amounts_wad.get(2).unwrap_or(0), lp_decimals);
+                if liquidity < min_liquidity {
+                    return Err(SmartAccountError::SlippageExceeded);
+                }
+
+                log!(env, "Soroswap AddLiquidity: LP tokens received {}", liquidity);
+
+                Ok((true, liquidity))
+            }
+
+            SmartAccExternalAction::RemoveLiquidity => {
+                // LP token amount in first position
+                let lp_decimals = Self::soroswap_lp_decimals(
+                    env,
+                    &router_client,
+                    &token_a_address,
+                    &token_b_address,
+                );
+                let liquidity = Self::scale_from_wad(tokens_amount_wad.get(0).unwrap(), lp_decimals);
+
+                let (amount_a, amount_b) = router_client.remove_liquidity(
+                    &token_a_address,
+                    &token_b_address,
+                    &liquidity,
+                    &min_amount_a,
+                    &min_amount_b,
+                    smart_account,
+                    &deadline,
+                );
+
+                log!(
+                    env,
+                    "Soroswap RemoveLiquidity: LP tokens burned {}, received {} and {}",
+                    liquidity,
+                    amount_a,
+                    amount_b
+                );
+
+                Ok((true, -liquidity))
+            }
+
+            SmartAccExternalAction::Swap => {
+                let amount_in = Self::scale_from_wad(tokens_amount_wad.get(0).unwrap(), token_a_decimals);
+                let min_amount_out =
+                    Self::scale_from_wad(min_amounts_wad.get(0).unwrap_or(0), token_b_decimals);
+
+                let path = soroban_sdk::vec![env, token_a_address.clone(), token_b_address.clone()];
+                let amounts = router_client.swap_exact_tokens_for_tokens(
+                    &amount_in,
+                    &min_amount_out,
+                    &path,
+                    smart_account,
+                    &deadline,
+                );
+
+                log!(
+                    env,
+                    "Soroswap Swap: {} -> {} out",
+                    amount_in,
+                    amounts.last().unwrap_or(0)
+                );
+
+                Ok((true, 0)) // Swap doesn't affect LP tracking
+            }
+
+            _ => Err(SmartAccountError::InvalidAction),
+        }
+    }
+
+    fn soroswap_lp_decimals(
+        env: &Env,
+        router_client: &soroswap_router_contract::Client,
+        token_a: &Address,
+        token_b: &Address,
+    ) -> u32 {
+        let pair_address = router_client.router_pair_for(token_a, token_b);
+        token::Client::new(env, &pair_address).decimals()
+    }
+
+    fn token_address_for_symbol(registry_client: &registry_contract::Client, token: &Symbol) -> Address {
+        if *token == XLM_SYMBOL {
+            registry_client.get_xlm_contract_adddress()
+        } else if *token == USDC_SYMBOL {
+            registry_client.get_usdc_contract_address()
+        } else if *token == EURC_SYMBOL {
+            registry_client.get_eurc_contract_address()
+        } else if let Some(asset) = registry_client.get_collateral_asset(token) {
+            asset.token_address
+        } else {
+            panic_with_error!(&registry_client.env, SmartAccountError::UnsupportedToken);
         }
     }
 
@@ -762,7 +1135,7 @@ impl SmartAccountContract {
             lending_protocol_eurc::Client::new(&env, &registry_client.get_lendingpool_eurc())
                 .get_borrow_balance(&this_account)
         } else {
-            panic!("User doesn't have borrows in the given token");
+            return Err(SmartAccountError::BorrowedTokenNotFound);
         };
 
         Ok(debt)
@@ -880,3 +1253,67 @@ pub mod aquarius_router_contract {
     }
 }
 
+// Soroswap Router Client trait (subset of the router interface used by smart accounts)
+#[allow(clippy::too_many_arguments)] // Mirrors the router's own signatures
+pub mod soroswap_router_contract {
+    use soroban_sdk::{contractclient, Address, Env, Vec};
+
+    #[contractclient(name = "Client")]
+    pub trait SoroswapRouterTrait {
+        fn add_liquidity(
+            env: Env,
+            token_a: Address,
+            token_b: Address,
+            amount_a_desired: i128,
+            amount_b_desired: i128,
+            amount_a_min: i128,
+            amount_b_min: i128,
+            to: Address,
+            deadline: u64,
+        ) -> (i128, i128, i128);
+
+        fn remove_liquidity(
+            env: Env,
+            token_a: Address,
+            token_b: Address,
+            liquidity: i128,
+            amount_a_min: i128,
+            amount_b_min: i128,
+            to: Address,
+            deadline: u64,
+        ) -> (i128, i128);
+
+        fn swap_exact_tokens_for_tokens(
+            env: Env,
+            amount_in: i128,
+            amount_out_min: i128,
+            path: Vec<Address>,
+            to: Address,
+            deadline: u64,
+        ) -> Vec<i128>;
+
+        fn router_pair_for(env: Env, token_a: Address, token_b: Address) -> Address;
+    }
+}
+
+// Common interface implemented by external protocol adapters. Adapters custody the
+// position for each smart account and report it back in underlying assets.
+pub mod protocol_adapter_contract {
+    use crate::types::SmartAccExternalAction;
+    use soroban_sdk::{contractclient, Address, Env, Symbol, Vec, U256};
+
+    #[contractclient(name = "Client")]
+    pub trait ProtocolAdapterTrait {
+        /// Returns (tracking_symbol, delta) pairs for the tracking tokens to mint or burn
+        fn execute(
+            env: Env,
+            smart_account: Address,
+            action: SmartAccExternalAction,
+            tokens: Vec<Address>,
+            amounts: Vec<i128>,
+        ) -> Vec<(Symbol, i128)>;
+
+        /// Returns (underlying_symbol, amount_wad) pairs held for the smart account
+        fn value_position(env: Env, smart_account: Address) -> Vec<(Symbol, U256)>;
+    }
+}
@@ -25,6 +25,10 @@ pub enum SmartAccExternalAction {
     Withdraw,       // Blend: Withdraw from lending pool
     AddLiquidity,   // Aquarius: Add liquidity to pool
     RemoveLiquidity, // Aquarius: Remove liquidity from pool
+    SupplyCollateral,   // Blend: Supply as collateral
+    WithdrawCollateral, // Blend: Withdraw collateral
+    BlendBorrow,        // Blend: Borrow against supplied collateral
+    BlendRepay,         // Blend: Repay a Blend liability
 }
 
 #[contracterror]
@@ -34,6 +38,12 @@ pub enum SmartAccountError {
     BorrowedTokenNotFound = 2,
     MarginAccountNotFound = 3,
     IntegerConversionError = 4,
+    InvalidAction = 5,
+    InvalidTokenCount = 6,
+    ProtocolNotSupported = 7,
+    UnsupportedToken = 8,
+    AdapterNotWhitelisted = 9,
+    SlippageExceeded = 10,
 }
 
 #[contracttype]
@@ -7,17 +7,22 @@
 use std::ops::Add;
 
 use account_manager_contract::account_manager::{self, AccountManagerContract};
-use account_manager_contract::account_manager::{AccountManagerContractClient, WAD_U128};
+use account_manager_contract::account_manager::{
+    AccountManagerContractClient, REPAY_ALL_WAD, WAD_U128,
+};
+use account_manager_contract::types::AccountManagerError;
 use lending_protocol_xlm::liquidity_pool_xlm::{self, LiquidityPoolXLM, LiquidityPoolXLMClient};
 use oracle_contract::oracle_service::{OracleContract, OracleContractClient};
 use registry_contract::registry::RegistryContract;
 use registry_contract::registry::RegistryContractClient;
+use rate_model_contract::rate_model::RateModelContract;
 use risk_engine_contract::risk_engine::RiskEngineContract;
 use sep_40_oracle::testutils::{self, Asset, MockPriceOracle, MockPriceOracleClient};
 // use sep_40_oracle::{Asset as MAsset, PriceData, PriceFeedClient, PriceFeedTrait};
 use smart_account_contract::smart_account::SmartAccountContractClient;
 use soroban_sdk::token::StellarAssetClient;
 use soroban_sdk::{Address as Addr, String, Symbol, U256, symbol_short};
+use soroban_sdk::testutils::Ledger;
 use soroban_sdk::{Env, Vec, testutils::Address};
 use soroban_sdk::{log, token};
 use vxlm_token_contract::v_xlm::VXLMToken;
@@ -703,7 +708,7 @@ fn account_creation_and_duplicate_creation() {
 }
 
 #[test]
-#[should_panic(expected = "Cannot deposit a zero amount")]
+#[should_panic(expected = "Error(Contract, #21)")]
 fn deposit_xlm_failure() {
     let env = Env::default();
     env.mock_all_auths();
@@ -734,7 +739,39 @@ fn deposit_xlm_failure() {
 }
 
 #[test]
-#[should_panic(expected = "Cannot withdraw a zero amount")]
+fn deposit_failures_return_error_codes() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contracts = test_initiation(&env);
+    let xlm_token = StellarAssetClient::new(&env, &contracts.xlm_address);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &contracts.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 5));
+    account_manager_client.set_iscollateral_allowed(&XLM_SYMBOL);
+
+    let trader = Addr::generate(&env);
+    xlm_token.mint(&trader, &10_000i128);
+    let smart_acc = account_manager_client.create_account(&trader);
+
+    let zero = account_manager_client.try_deposit_collateral_tokens(
+        &smart_acc,
+        &XLM_SYMBOL,
+        &U256::from_u128(&env, 0),
+    );
+    assert_eq!(zero, Err(Ok(AccountManagerError::ZeroAmount)));
+
+    let not_allowed = account_manager_client.try_deposit_collateral_tokens(
+        &smart_acc,
+        &USDC_SYMBOL,
+        &U256::from_u128(&env, 1),
+    );
+    assert_eq!(not_allowed, Err(Ok(AccountManagerError::CollateralNotAllowed)));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #21)")]
 fn withdraw_xlm_failure() {
     let env = Env::default();
     env.mock_all_auths();
@@ -822,7 +859,7 @@ fn deposit_xlm_and_withdraw_success() {
 }
 
 #[test]
-#[should_panic(expected = "Cannot withdraw more value than the current collateral value")]
+#[should_panic(expected = "Error(Contract, #2)")]
 fn deposit_xlm_and_withdraw_failure() {
     let env = Env::default();
     env.mock_all_auths();
@@ -877,7 +914,7 @@ fn deposit_xlm_and_withdraw_failure() {
 }
 
 #[test]
-#[should_panic(expected = "User doesn't have collateral in this token")]
+#[should_panic(expected = "Error(Contract, #5)")]
 fn withdraw_without_collateral_should_panic() {
     let env = Env::default();
     env.mock_all_auths();
@@ -897,7 +934,7 @@ fn withdraw_without_collateral_should_panic() {
 }
 
 #[test]
-#[should_panic(expected = "Cannot borrow a zero amount")]
+#[should_panic(expected = "Error(Contract, #21)")]
 fn borrow_xlm_failure() {
     let env = Env::default();
     env.mock_all_auths();
@@ -934,7 +971,7 @@ fn borrow_xlm_failure() {
 }
 
 #[test]
-#[should_panic(expected = "Cannot repay a zero amount")]
+#[should_panic(expected = "Error(Contract, #21)")]
 fn repay_xlm_failure() {
     let env = Env::default();
     env.mock_all_auths();
@@ -1051,7 +1088,240 @@ fn borrow_and_repay_xlm_flow() {
 }
 
 #[test]
-#[should_panic(expected = "Cannot delete account with debt, please repay debt first")]
+fn repay_above_debt_is_capped_at_the_debt() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contracts = test_initiation(&env);
+    liquidity_pool_lenders_initialise(&env, &contracts);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &contracts.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+
+    let trader = Addr::generate(&env);
+    let usdc_token = StellarAssetClient::new(&env, &contracts.usdc_address);
+    usdc_token.mint(&trader, &(10_000i128 * WAD7));
+
+    let smart_acc = account_manager_client.create_account(&trader);
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &USDC_SYMBOL,
+        &U256::from_u128(&env, 100 * WAD_U128),
+    );
+    account_manager_client.borrow(
+        &smart_acc,
+        &U256::from_u128(&env, 10 * WAD_U128),
+        &XLM_SYMBOL,
+    );
+
+    // Extra XLM in the account must stay there when repaying more than is owed
+    StellarAssetClient::new(&env, &contracts.xlm_address).mint(&smart_acc, &(5_i128 * WAD7));
+    account_manager_client.repay(
+        &U256::from_u128(&env, 15 * WAD_U128),
+        &XLM_SYMBOL,
+        &smart_acc,
+    );
+
+    let smart_client = SmartAccountContractClient::new(&env, &smart_acc);
+    assert!(!smart_client.get_all_borrowed_tokens().contains(XLM_SYMBOL));
+    let pool_client = LiquidityPoolXLMClient::new(&env, &contracts.liquidity_pool_xlm);
+    assert_eq!(
+        pool_client.get_user_borrow_shares(&smart_acc),
+        U256::from_u32(&env, 0)
+    );
+    let xlm_token = token::Client::new(&env, &contracts.xlm_address);
+    assert_eq!(xlm_token.balance(&smart_acc), 5_i128 * WAD7);
+}
+
+#[test]
+fn repay_all_closes_debt_with_accrued_interest() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contracts = test_initiation(&env);
+    liquidity_pool_lenders_initialise(&env, &contracts);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &contracts.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+
+    let trader = Addr::generate(&env);
+    let usdc_token = StellarAssetClient::new(&env, &contracts.usdc_address);
+    usdc_token.mint(&trader, &(10_000i128 * WAD7));
+
+    let smart_acc = account_manager_client.create_account(&trader);
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &USDC_SYMBOL,
+        &U256::from_u128(&env, 100 * WAD_U128),
+    );
+    account_manager_client.borrow(
+        &smart_acc,
+        &U256::from_u128(&env, 10 * WAD_U128),
+        &XLM_SYMBOL,
+    );
+
+    // Accrue a month of interest on the borrow
+    env.register_at(
+        &contracts.rate_model_contract,
+        RateModelContract,
+        (contracts.admin.clone(), contracts.registry_contract.clone()),
+    );
+    env.ledger().with_mut(|li| li.timestamp += 30 * 24 * 60 * 60);
+    StellarAssetClient::new(&env, &contracts.xlm_address).mint(&smart_acc, &(1_i128 * WAD7));
+    account_manager_client.repay(
+        &U256::from_u128(&env, REPAY_ALL_WAD),
+        &XLM_SYMBOL,
+        &smart_acc,
+    );
+
+    let smart_client = SmartAccountContractClient::new(&env, &smart_acc);
+    assert!(!smart_client.get_all_borrowed_tokens().contains(XLM_SYMBOL));
+    let pool_client = LiquidityPoolXLMClient::new(&env, &contracts.liquidity_pool_xlm);
+    assert_eq!(
+        pool_client.get_user_borrow_shares(&smart_acc),
+        U256::from_u32(&env, 0)
+    );
+    // Only the accrued debt left the account
+    let xlm_token = token::Client::new(&env, &contracts.xlm_address);
+    let left = xlm_token.balance(&smart_acc);
+    assert!(left > 0 && left < 1_i128 * WAD7);
+}
+
+#[test]
+fn account_report_splits_repayments_into_interest_and_principal() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contracts = test_initiation(&env);
+    liquidity_pool_lenders_initialise(&env, &contracts);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &contracts.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+
+    let trader = Addr::generate(&env);
+    let usdc_token = StellarAssetClient::new(&env, &contracts.usdc_address);
+    usdc_token.mint(&trader, &(10_000i128 * WAD7));
+
+    let smart_acc = account_manager_client.create_account(&trader);
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &USDC_SYMBOL,
+        &U256::from_u128(&env, 10 * WAD_U128),
+    );
+    account_manager_client.borrow(
+        &smart_acc,
+        &U256::from_u128(&env, 10 * WAD_U128),
+        &XLM_SYMBOL,
+    );
+
+    let report = account_manager_client.get_account_report(&smart_acc);
+    assert_eq!(
+        report.collateral_value_wad,
+        U256::from_u128(&env, 9_990_000_000_000_000_000)
+    );
+    assert_eq!(report.debt_value_wad, U256::from_u128(&env, 4 * WAD_U128));
+    assert_eq!(report.net_deposits_wad, 9_990_000_000_000_000_000);
+    assert_eq!(report.debts.len(), 1);
+
+    env.register_at(
+        &contracts.rate_model_contract,
+        RateModelContract,
+        (contracts.admin.clone(), contracts.registry_contract.clone()),
+    );
+    env.ledger().with_mut(|li| li.timestamp += 365 * 24 * 60 * 60);
+    account_manager_client.repay(
+        &U256::from_u128(&env, 10 * WAD_U128),
+        &XLM_SYMBOL,
+        &smart_acc,
+    );
+
+    let history = account_manager_client.get_debt_history(&smart_acc).get(0).unwrap();
+    assert_eq!(history.principal_borrowed_wad, U256::from_u128(&env, 10 * WAD_U128));
+    assert!(history.interest_paid_wad > U256::from_u128(&env, 0));
+    assert_eq!(
+        history.interest_paid_wad.add(&history.principal_repaid_wad),
+        U256::from_u128(&env, 10 * WAD_U128)
+    );
+    assert!(
+        account_manager_client.get_account_report(&smart_acc).interest_paid_value_wad
+            > U256::from_u128(&env, 0)
+    );
+
+    // Collateral the oracle has no price for stays out of the deposit history
+    let unpriced_symbol = Symbol::new(&env, "YUNP");
+    let unpriced_address = env
+        .register_stellar_asset_contract_v2(contracts.admin.clone())
+        .address();
+    RegistryContractClient::new(&env, &contracts.registry_contract).list_collateral_asset(
+        &unpriced_symbol,
+        &unpriced_address,
+        &Symbol::new(&env, "UNPRICED"),
+    );
+    account_manager_client.set_iscollateral_allowed(&unpriced_symbol);
+    StellarAssetClient::new(&env, &unpriced_address).mint(&trader, &(10_i128 * WAD7));
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &unpriced_symbol,
+        &U256::from_u128(&env, 10 * WAD_U128),
+    );
+    assert_eq!(
+        account_manager_client.get_collateral_history(&smart_acc).deposited_value_wad,
+        U256::from_u128(&env, 9_990_000_000_000_000_000)
+    );
+}
+
+#[test]
+fn repay_for_uses_payer_tokens() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contracts = test_initiation(&env);
+    liquidity_pool_lenders_initialise(&env, &contracts);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &contracts.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+
+    let trader = Addr::generate(&env);
+    let usdc_token = StellarAssetClient::new(&env, &contracts.usdc_address);
+    usdc_token.mint(&trader, &(10_000i128 * WAD7));
+
+    let smart_acc = account_manager_client.create_account(&trader);
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &USDC_SYMBOL,
+        &U256::from_u128(&env, 100 * WAD_U128),
+    );
+    account_manager_client.borrow(
+        &smart_acc,
+        &U256::from_u128(&env, 10 * WAD_U128),
+        &XLM_SYMBOL,
+    );
+
+    // A third party pays more than the debt; only the debt is taken from them
+    let payer = Addr::generate(&env);
+    StellarAssetClient::new(&env, &contracts.xlm_address).mint(&payer, &(20_i128 * WAD7));
+    let repaid = account_manager_client.repay_for(
+        &payer,
+        &smart_acc,
+        &XLM_SYMBOL,
+        &U256::from_u128(&env, 15 * WAD_U128),
+    );
+    assert_eq!(repaid, U256::from_u128(&env, 10 * WAD_U128));
+
+    let xlm_token = token::Client::new(&env, &contracts.xlm_address);
+    assert_eq!(xlm_token.balance(&payer), 10_i128 * WAD7);
+    // The borrowed XLM stays in the account
+    assert_eq!(xlm_token.balance(&smart_acc), 10_i128 * WAD7);
+    let smart_client = SmartAccountContractClient::new(&env, &smart_acc);
+    assert!(!smart_client.get_all_borrowed_tokens().contains(XLM_SYMBOL));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #17)")]
 fn delete_account_with_debt() {
     let env = Env::default();
     env.mock_all_auths();
@@ -1202,7 +1472,7 @@ fn close_account_and_activate_again() {
 }
 
 #[test]
-#[should_panic(expected = "Cannot liquidate when account is healthy!!")]
+#[should_panic(expected = "Error(Contract, #23)")]
 fn liquidate_account_test_failure() {
     let env = Env::default();
     env.mock_all_auths();
@@ -1299,6 +1569,14 @@ fn liquidate_account_test_success() {
     let xlm_token = token::Client::new(&env, &contracts.xlm_address);
     let bal = xlm_token.balance(&smart_acc);
     assert!(bal == 0_i128);
+
+    // The repayment and the swept collateral both land in the account history
+    let history = account_manager_client.get_debt_history(&smart_acc).get(0).unwrap();
+    assert_eq!(history.principal_repaid_wad, history.principal_borrowed_wad);
+    assert!(
+        account_manager_client.get_collateral_history(&smart_acc).withdrawn_value_wad
+            > U256::from_u128(&env, 0)
+    );
 }
 
 #[test]
@@ -4,15 +4,21 @@ use account_manager_contract::account_manager::{
     AccountManagerContract, AccountManagerContractClient,
     smart_account_contract::SmartAccExternalAction,
 };
-use account_manager_contract::types::ExternalProtocolCall;
+use account_manager_contract::types::{
+    AccountManagerError, BatchCall, DeleverageConfig, ExternalProtocolCall, OpenPositionRequest,
+    OperatorPermission, OrderAction, OrderCondition,
+};
 use blend_contract_sdk::pool::{
     PoolConfig, Positions, Request, Reserve, ReserveConfig, ReserveData,
 };
 use registry_contract::registry::{RegistryContract, RegistryContractClient};
+use risk_engine_contract::risk_engine::{RiskEngineContract, RiskEngineContractClient};
 use soroban_sdk::xdr::ToXdr;
 use soroban_sdk::{
     Address, Bytes, Env, Map, String, Symbol, U256, Vec, contract, contractimpl, contracttype,
-    symbol_short, testutils::Address as _, token::StellarAssetClient,
+    symbol_short,
+    testutils::{Address as _, Ledger},
+    token::StellarAssetClient,
 };
 use tracking_token_contract::tracking_token::{TrackingToken, TrackingTokenClient};
 
@@ -29,6 +35,12 @@ const EURC_SYMBOL: Symbol = symbol_short!("EURC");
 const BLEND_XLM: &str = "BLEND_XLM";
 const BLEND_USDC: &str = "BLEND_USDC";
 const BLEND_EURC: &str = "BLEND_EURC";
+const BLEND_COLL_XLM: &str = "BLEND_COLL_XLM";
+const BLEND_COLL_USDC: &str = "BLEND_COLL_USDC";
+const BLEND_COLL_EURC: &str = "BLEND_COLL_EURC";
+const BLEND_DEBT_XLM: &str = "BLEND_DEBT_XLM";
+const BLEND_DEBT_USDC: &str = "BLEND_DEBT_USDC";
+const BLEND_DEBT_EURC: &str = "BLEND_DEBT_EURC";
 
 #[derive(Clone)]
 #[contracttype]
@@ -37,6 +49,11 @@ enum MockPoolKey {
     AssetIndex(Address),
     AssetDecimals(Address),
     SupplyPositions(Address),
+    CollateralPositions(Address),
+    LiabilityPositions(Address),
+    RewardToken,
+    Emissions(Address),
+    BRate,
 }
 
 #[contract]
@@ -97,7 +114,11 @@ impl MockBlendPool {
         };
         let data = ReserveData {
             d_rate: SCALAR_12,
-            b_rate: SCALAR_12,
+            b_rate: env
+                .storage()
+                .persistent()
+                .get(&MockPoolKey::BRate)
+                .unwrap_or(SCALAR_12),
             ir_mod: 0,
             b_supply: 0,
             d_supply: 0,
@@ -113,16 +134,7 @@ impl MockBlendPool {
     }
 
     pub fn get_positions(env: Env, address: Address) -> Positions {
-        let supply: Map<u32, i128> = env
-            .storage()
-            .persistent()
-            .get(&MockPoolKey::SupplyPositions(address))
-            .unwrap_or_else(|| Map::new(&env));
-        Positions {
-            liabilities: Map::new(&env),
-            collateral: Map::new(&env),
-            supply,
-        }
+        Self::load_positions(&env, &address)
     }
 
     pub fn submit(
@@ -132,11 +144,7 @@ impl MockBlendPool {
         _to: Address,
         requests: Vec<Request>,
     ) -> Positions {
-        let mut supply: Map<u32, i128> = env
-            .storage()
-            .persistent()
-            .get(&MockPoolKey::SupplyPositions(from.clone()))
-            .unwrap_or_else(|| Map::new(&env));
+        let mut positions = Self::load_positions(&env, &from);
 
         for req in requests.iter() {
             let index: u32 = env
@@ -144,34 +152,122 @@ impl MockBlendPool {
                 .persistent()
                 .get(&MockPoolKey::AssetIndex(req.address.clone()))
                 .unwrap_or(0);
-            let current = supply.get(index).unwrap_or(0);
-            if req.request_type == 0 {
-                supply.set(index, current + req.amount);
-            } else if req.request_type == 1 {
-                let burn = if req.amount > current {
-                    current
-                } else {
-                    req.amount
-                };
-                let new_bal = current - burn;
-                if new_bal == 0 {
-                    supply.remove(index);
-                } else {
-                    supply.set(index, new_bal);
+            match req.request_type {
+                0 => Self::increase(&mut positions.supply, index, req.amount),
+                1 | 3 => {
+                    // Withdrawn tokens are paid out of the pool's own balance
+                    soroban_sdk::token::Client::new(&env, &req.address).transfer(
+                        &env.current_contract_address(),
+                        &from,
+                        &req.amount,
+                    );
+                    if req.request_type == 1 {
+                        Self::decrease(&mut positions.supply, index, req.amount)
+                    } else {
+                        Self::decrease(&mut positions.collateral, index, req.amount)
+                    }
+                }
+                2 => Self::increase(&mut positions.collateral, index, req.amount),
+                4 => {
+                    // Borrowed tokens are paid out of the pool's own balance
+                    soroban_sdk::token::Client::new(&env, &req.address).transfer(
+                        &env.current_contract_address(),
+                        &from,
+                        &req.amount,
+                    );
+                    Self::increase(&mut positions.liabilities, index, req.amount)
                 }
-            } else {
-                panic!("Unsupported request type in mock pool");
+                5 => {
+                    // Repayments must be funded by the account; the transfer itself is skipped
+                    // as the account does not authorize it for the pool
+                    let balance = soroban_sdk::token::Client::new(&env, &req.address).balance(&from);
+                    if balance < req.amount {
+                        panic!("Insufficient balance to repay");
+                    }
+                    Self::decrease(&mut positions.liabilities, index, req.amount)
+                }
+                _ => panic!("Unsupported request type in mock pool"),
             }
         }
 
+        let storage = env.storage().persistent();
+        storage.set(&MockPoolKey::SupplyPositions(from.clone()), &positions.supply);
+        storage.set(
+            &MockPoolKey::CollateralPositions(from.clone()),
+            &positions.collateral,
+        );
+        storage.set(
+            &MockPoolKey::LiabilityPositions(from.clone()),
+            &positions.liabilities,
+        );
+
+        positions
+    }
+
+    pub fn set_b_rate(env: Env, b_rate: i128) {
+        env.storage().persistent().set(&MockPoolKey::BRate, &b_rate);
+    }
+
+    pub fn set_emissions(env: Env, reward_token: Address, user: Address, amount: i128) {
+        env.storage()
+            .persistent()
+            .set(&MockPoolKey::RewardToken, &reward_token);
         env.storage()
             .persistent()
-            .set(&MockPoolKey::SupplyPositions(from.clone()), &supply);
+            .set(&MockPoolKey::Emissions(user), &amount);
+    }
+
+    pub fn claim(env: Env, from: Address, reserve_token_ids: Vec<u32>, to: Address) -> i128 {
+        from.require_auth();
+        if reserve_token_ids.is_empty() {
+            return 0;
+        }
+        let amount: i128 = env
+            .storage()
+            .persistent()
+            .get(&MockPoolKey::Emissions(from.clone()))
+            .unwrap_or(0);
+        if amount > 0 {
+            let reward_token: Address =
+                env.storage().persistent().get(&MockPoolKey::RewardToken).unwrap();
+            soroban_sdk::token::Client::new(&env, &reward_token).transfer(
+                &env.current_contract_address(),
+                &to,
+                &amount,
+            );
+            env.storage()
+                .persistent()
+                .set(&MockPoolKey::Emissions(from), &0i128);
+        }
+        amount
+    }
 
+    fn load_positions(env: &Env, address: &Address) -> Positions {
+        let storage = env.storage().persistent();
         Positions {
-            liabilities: Map::new(&env),
-            collateral: Map::new(&env),
-            supply,
+            liabilities: storage
+                .get(&MockPoolKey::LiabilityPositions(address.clone()))
+                .unwrap_or_else(|| Map::new(env)),
+            collateral: storage
+                .get(&MockPoolKey::CollateralPositions(address.clone()))
+                .unwrap_or_else(|| Map::new(env)),
+            supply: storage
+                .get(&MockPoolKey::SupplyPositions(address.clone()))
+                .unwrap_or_else(|| Map::new(env)),
+        }
+    }
+
+    fn increase(map: &mut Map<u32, i128>, index: u32, amount: i128) {
+        map.set(index, map.get(index).unwrap_or(0) + amount);
+    }
+
+    fn decrease(map: &mut Map<u32, i128>, index: u32, amount: i128) {
+        let current = map.get(index).unwrap_or(0);
+        let new_bal = current - amount.min(current);
+        if new_bal == 0 {
+            map.remove(index);
+        } else {
+            map.set(index, new_bal);
         }
     }
 }
@@ -193,6 +289,23 @@ fn scale_wad_to_token(amount_wad: u128, decimals: u32) -> i128 {
     ((amount_wad * 10u128.pow(decimals)) / WAD_U128) as i128
 }
 
+fn scale_token_to_wad(amount: i128, decimals: u32) -> u128 {
+    amount as u128 * (WAD_U128 / 10u128.pow(decimals))
+}
+
+fn allow_external_calls(
+    env: &Env,
+    account_manager: &Address,
+    protocol: &Address,
+    actions: &[SmartAccExternalAction],
+    tokens: Vec<Symbol>,
+) {
+    let am_client = AccountManagerContractClient::new(env, account_manager);
+    for action in actions {
+        am_client.allow_external_call(protocol, action, &tokens);
+    }
+}
+
 fn build_external_call(
     env: &Env,
     protocol: Address,
@@ -219,6 +332,7 @@ fn build_external_call(
         margin_account: smart_account,
         fee_fraction: 0,
         min_liquidity_out: U256::from_u128(env, 0),
+        min_amounts_out: Vec::new(env),
     };
 
     call.to_xdr(env)
@@ -257,6 +371,9 @@ fn setup() -> TestContext {
 
     let blend_pool_client = MockBlendPoolClient::new(&env, &blend_pool);
     blend_pool_client.init(&admin, &assets, &decimals);
+    for asset in assets.iter() {
+        StellarAssetClient::new(&env, &asset).mint(&blend_pool, &1_000_000_000_000i128);
+    }
 
     let tracking_token = env.register(TrackingToken, ());
     let tracking_client = TrackingTokenClient::new(&env, &tracking_token);
@@ -278,6 +395,21 @@ fn setup() -> TestContext {
         &StellarAssetClient::new(&env, &eurc_token.address()).decimals(),
         &String::from_str(&env, "BLEND EURC"),
     );
+    for (symbol, token) in [
+        (BLEND_COLL_XLM, xlm_token.address()),
+        (BLEND_COLL_USDC, usdc_token.address()),
+        (BLEND_COLL_EURC, eurc_token.address()),
+        (BLEND_DEBT_XLM, xlm_token.address()),
+        (BLEND_DEBT_USDC, usdc_token.address()),
+        (BLEND_DEBT_EURC, eurc_token.address()),
+    ] {
+        tracking_client.initialize(
+            &account_manager,
+            &Symbol::new(&env, symbol),
+            &StellarAssetClient::new(&env, &token).decimals(),
+            &String::from_str(&env, symbol),
+        );
+    }
 
     let registry_client = RegistryContractClient::new(&env, &registry);
     let smart_hash = env.deployer().upload_contract_wasm(SMART_ACCOUNT_WASM);
@@ -288,6 +420,24 @@ fn setup() -> TestContext {
     registry_client.set_blend_pool_address(&blend_pool);
     registry_client.set_tracking_token_contract_addr(&tracking_token);
     registry_client.set_accountmanager_contract(&account_manager);
+    registry_client.set_risk_engine_address(&env.register(MockRiskEngine, ()));
+
+    for token in [XLM_SYMBOL, USDC_SYMBOL, EURC_SYMBOL] {
+        allow_external_calls(
+            &env,
+            &account_manager,
+            &blend_pool,
+            &[
+                SmartAccExternalAction::Deposit,
+                SmartAccExternalAction::Withdraw,
+                SmartAccExternalAction::SupplyCollateral,
+                SmartAccExternalAction::WithdrawCollateral,
+                SmartAccExternalAction::BlendBorrow,
+                SmartAccExternalAction::BlendRepay,
+            ],
+            soroban_sdk::vec![&env, token],
+        );
+    }
 
     TestContext {
         env,
@@ -411,235 +561,2697 @@ fn execute_deposit_and_withdraw_xlm_tracks_supply_position() {
     );
 }
 
-// ============================================================================
-// Aquarius Protocol Integration Tests
-// ============================================================================
+#[test]
+fn execute_blend_collateral_borrow_and_repay_track_positions() {
+    let ctx = setup();
 
-const AQUARIUS_XLM_USDC: &str = "AQ_XLM_USDC";
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let coll_symbol = Symbol::new(&ctx.env, BLEND_COLL_USDC);
+    let debt_symbol = Symbol::new(&ctx.env, BLEND_DEBT_XLM);
 
-#[derive(Clone)]
-#[contracttype]
-enum MockAquariusKey {
-    Admin,
-    PoolIndex,
-    LPBalance(Address),
-    TokenBalance(Address, Address), // (user, token)
+    let collateral_wad = 100u128 * WAD_U128;
+    account_manager_client.execute(
+        &smart_account,
+        &build_external_call(
+            &ctx.env,
+            ctx.blend_pool.clone(),
+            SmartAccExternalAction::SupplyCollateral,
+            USDC_SYMBOL,
+            collateral_wad,
+            smart_account.clone(),
+        ),
+    );
+    let usdc_decimals = StellarAssetClient::new(&ctx.env, &ctx.usdc).decimals();
+    assert_eq!(
+        tracking_client.balance(&smart_account, &coll_symbol),
+        scale_wad_to_token(collateral_wad, usdc_decimals)
+    );
+    assert!(sa_client.get_all_collateral_tokens().contains(&coll_symbol));
+
+    let borrow_wad = 20u128 * WAD_U128;
+    account_manager_client.execute(
+        &smart_account,
+        &build_external_call(
+            &ctx.env,
+            ctx.blend_pool.clone(),
+            SmartAccExternalAction::BlendBorrow,
+            XLM_SYMBOL,
+            borrow_wad,
+            smart_account.clone(),
+        ),
+    );
+    let xlm_decimals = StellarAssetClient::new(&ctx.env, &ctx.xlm).decimals();
+    assert_eq!(
+        tracking_client.balance(&smart_account, &debt_symbol),
+        scale_wad_to_token(borrow_wad, xlm_decimals)
+    );
+    assert!(sa_client.get_all_borrowed_tokens().contains(&debt_symbol));
+    assert!(sa_client.has_debt());
+    // The borrowed XLM sits in the account and counts as collateral
+    assert!(sa_client.get_all_collateral_tokens().contains(&XLM_SYMBOL));
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&XLM_SYMBOL),
+        U256::from_u128(&ctx.env, borrow_wad)
+    );
+
+    let pool_client = blend_contract_sdk::pool::Client::new(&ctx.env, &ctx.blend_pool);
+    let xlm_index = pool_client.get_reserve(&ctx.xlm).config.index;
+    assert_eq!(
+        pool_client
+            .get_positions(&smart_account)
+            .liabilities
+            .get(xlm_index)
+            .unwrap_or(0),
+        scale_wad_to_token(borrow_wad, xlm_decimals)
+    );
+
+    account_manager_client.execute(
+        &smart_account,
+        &build_external_call(
+            &ctx.env,
+            ctx.blend_pool.clone(),
+            SmartAccExternalAction::BlendRepay,
+            XLM_SYMBOL,
+            borrow_wad,
+            smart_account.clone(),
+        ),
+    );
+    assert_eq!(tracking_client.balance(&smart_account, &debt_symbol), 0);
+    assert!(!sa_client.get_all_borrowed_tokens().contains(&debt_symbol));
+    assert!(!sa_client.has_debt());
 }
 
-#[contract]
-pub struct MockAquariusRouter;
+#[test]
+fn settle_account_repays_blend_liabilities() {
+    let ctx = setup();
 
-#[contractimpl]
-impl MockAquariusRouter {
-    pub fn init(env: Env, admin: Address) {
-        admin.require_auth();
-        env.storage()
-            .persistent()
-            .set(&MockAquariusKey::Admin, &admin);
-    }
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    let debt_symbol = Symbol::new(&ctx.env, BLEND_DEBT_XLM);
 
-    pub fn init_standard_pool(
-        env: Env,
-        sender: Address,
-        tokens: Vec<Address>,
-        fee_fraction: u32,
-    ) -> (soroban_sdk::BytesN<32>, Address) {
-        let pool_index = env.crypto().sha256(&tokens.to_xdr(&env));
-        env.storage()
-            .persistent()
-            .set(&MockAquariusKey::PoolIndex, &pool_index);
-        (soroban_sdk::BytesN::from_array(&env, &pool_index.to_array()), sender.clone())
+    for (action, symbol, amount_wad) in [
+        (SmartAccExternalAction::SupplyCollateral, USDC_SYMBOL, 100u128 * WAD_U128),
+        (SmartAccExternalAction::BlendBorrow, XLM_SYMBOL, 20u128 * WAD_U128),
+    ] {
+        account_manager_client.execute(
+            &smart_account,
+            &build_external_call(
+                &ctx.env,
+                ctx.blend_pool.clone(),
+                action,
+                symbol,
+                amount_wad,
+                smart_account.clone(),
+            ),
+        );
     }
+    // Repaying all of it takes one unit more than was borrowed
+    StellarAssetClient::new(&ctx.env, &ctx.xlm).mint(&smart_account, &1);
 
-    pub fn deposit(
-        env: Env,
-        sender: Address,
-        tokens: Vec<Address>,
-        pool_id: soroban_sdk::BytesN<32>,
-        desired_amounts: Vec<u128>,
-        min_shares: u128,
-    ) -> (Vec<u128>, u128) {
-        // Simple mock: LP tokens = average of deposited amounts
-        let amount0 = desired_amounts.get(0).unwrap();
-        let amount1 = desired_amounts.get(1).unwrap();
-        let lp_tokens = (amount0 + amount1) / 2;
+    account_manager_client.settle_account(&smart_account);
 
-        let current_lp = env
-            .storage()
-            .persistent()
-            .get(&MockAquariusKey::LPBalance(sender.clone()))
-            .unwrap_or(0u128);
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    assert_eq!(tracking_client.balance(&smart_account, &debt_symbol), 0);
+    assert!(!sa_client.get_all_borrowed_tokens().contains(&debt_symbol));
+    assert!(!sa_client.has_debt());
+    let pool_client = blend_contract_sdk::pool::Client::new(&ctx.env, &ctx.blend_pool);
+    assert!(pool_client.get_positions(&smart_account).liabilities.is_empty());
+}
 
-        env.storage()
-            .persistent()
-            .set(&MockAquariusKey::LPBalance(sender), &(current_lp + lp_tokens));
+#[test]
+fn risk_engine_values_blend_collateral_and_debt_at_underlying_price() {
+    let ctx = setup();
 
-        (desired_amounts, lp_tokens)
+    let registry_client = RegistryContractClient::new(&ctx.env, &ctx.registry);
+    let risk_engine = ctx.env.register(
+        RiskEngineContract,
+        (ctx.admin.clone(), ctx.registry.clone()),
+    );
+    registry_client.set_oracle_contract_address(&ctx.env.register(MockPriceOracle, ()));
+    registry_client.set_risk_engine_address(&risk_engine);
+
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+
+    for (action, symbol, amount_wad) in [
+        (SmartAccExternalAction::SupplyCollateral, USDC_SYMBOL, 100u128 * WAD_U128),
+        (SmartAccExternalAction::BlendBorrow, XLM_SYMBOL, 20u128 * WAD_U128),
+    ] {
+        account_manager_client.execute(
+            &smart_account,
+            &build_external_call(
+                &ctx.env,
+                ctx.blend_pool.clone(),
+                action,
+                symbol,
+                amount_wad,
+                smart_account.clone(),
+            ),
+        );
     }
 
-    pub fn withdraw(
-        env: Env,
-        sender: Address,
-        tokens: Vec<Address>,
-        pool_id: soroban_sdk::BytesN<32>,
-        share_amount: u128,
-        min_amounts: Vec<u128>,
-    ) -> Vec<u128> {
-        let current_lp = env
-            .storage()
-            .persistent()
-            .get(&MockAquariusKey::LPBalance(sender.clone()))
-            .unwrap_or(0u128);
+    // Mock oracle prices every asset at 0.5 and the mock pool keeps b_rate = d_rate = 1;
+    // the borrowed XLM is held as collateral next to the Blend collateral
+    let risk_client = RiskEngineContractClient::new(&ctx.env, &risk_engine);
+    assert_eq!(
+        risk_client.get_current_total_balance(&smart_account),
+        U256::from_u128(&ctx.env, 60u128 * WAD_U128)
+    );
+    assert_eq!(
+        risk_client.get_current_total_borrows(&smart_account),
+        U256::from_u128(&ctx.env, 10u128 * WAD_U128)
+    );
 
-        if share_amount > current_lp {
-            panic!("Insufficient LP tokens");
-        }
+    // Ramping USDC down halves the Blend collateral backed by it, not the plain XLM
+    risk_client.set_collateral_factor_ramp(&USDC_SYMBOL, &5_000, &0);
+    assert_eq!(
+        risk_client.get_current_total_balance(&smart_account),
+        U256::from_u128(&ctx.env, 35u128 * WAD_U128)
+    );
+}
 
-        env.storage()
-            .persistent()
-            .set(
-                &MockAquariusKey::LPBalance(sender),
-                &(current_lp - share_amount),
-            );
+#[test]
+fn execute_tracks_positions_per_blend_pool() {
+    let ctx = setup();
 
-        // Return proportional amounts
-        soroban_sdk::vec![&env, share_amount, share_amount]
-    }
+    // A second pool whose b-tokens are worth 1.2 of the underlying
+    let stable_pool = ctx.env.register(MockBlendPool, ());
+    let stable_pool_client = MockBlendPoolClient::new(&ctx.env, &stable_pool);
+    stable_pool_client.init(
+        &ctx.admin,
+        &soroban_sdk::vec![&ctx.env, ctx.usdc.clone()],
+        &soroban_sdk::vec![&ctx.env, 7u32],
+    );
+    stable_pool_client.set_b_rate(&1_200_000_000_000);
 
-    pub fn swap(
-        env: Env,
-        sender: Address,
-        tokens: Vec<Address>,
-        token_in: Address,
-        token_out: Address,
-        pool_id: soroban_sdk::BytesN<32>,
-        amount_in: u128,
-        min_amount_out: u128,
-    ) -> u128 {
-        // Simple mock: 1:1 swap with 0.3% fee
-        let amount_out = (amount_in * 997) / 1000;
-        amount_out
-    }
+    let registry_client = RegistryContractClient::new(&ctx.env, &ctx.registry);
+    assert_eq!(registry_client.add_blend_pool(&stable_pool), 1);
+    let risk_engine = ctx.env.register(
+        RiskEngineContract,
+        (ctx.admin.clone(), ctx.registry.clone()),
+    );
+    registry_client.set_oracle_contract_address(&ctx.env.register(MockPriceOracle, ()));
+    registry_client.set_risk_engine_address(&risk_engine);
 
-    pub fn get_lp_balance(env: Env, sender: Address) -> u128 {
-        env.storage()
-            .persistent()
-            .get(&MockAquariusKey::LPBalance(sender))
-            .unwrap_or(0u128)
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let stable_symbol = Symbol::new(&ctx.env, "BLEND1_USDC");
+    tracking_client.initialize(
+        &ctx.account_manager,
+        &stable_symbol,
+        &7u32,
+        &String::from_str(&ctx.env, "BLEND1 USDC"),
+    );
+    allow_external_calls(
+        &ctx.env,
+        &ctx.account_manager,
+        &stable_pool,
+        &[SmartAccExternalAction::Deposit],
+        soroban_sdk::vec![&ctx.env, USDC_SYMBOL],
+    );
+
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+    for pool in [ctx.blend_pool.clone(), stable_pool.clone()] {
+        account_manager_client.execute(
+            &smart_account,
+            &build_external_call(
+                &ctx.env,
+                pool,
+                SmartAccExternalAction::Deposit,
+                USDC_SYMBOL,
+                100u128 * WAD_U128,
+                smart_account.clone(),
+            ),
+        );
     }
+
+    let usdc_decimals = StellarAssetClient::new(&ctx.env, &ctx.usdc).decimals();
+    let expected = scale_wad_to_token(100u128 * WAD_U128, usdc_decimals);
+    assert_eq!(
+        tracking_client.balance(&smart_account, &Symbol::new(&ctx.env, BLEND_USDC)),
+        expected
+    );
+    assert_eq!(tracking_client.balance(&smart_account, &stable_symbol), expected);
+
+    // (100 * 1.0 + 100 * 1.2) USDC at the mock price of 0.5
+    let risk_client = RiskEngineContractClient::new(&ctx.env, &risk_engine);
+    assert_eq!(
+        risk_client.get_current_total_balance(&smart_account),
+        U256::from_u128(&ctx.env, 110u128 * WAD_U128)
+    );
 }
 
-fn build_aquarius_add_liquidity_call(
-    env: &Env,
-    router: Address,
-    token0: Symbol,
-    token1: Symbol,
-    amount0_wad: u128,
-    amount1_wad: u128,
-    smart_account: Address,
-) -> Bytes {
-    let mut tokens_out = Vec::new(env);
-    tokens_out.push_back(token0);
-    tokens_out.push_back(token1);
+#[test]
+fn delisted_blend_pool_only_accepts_exit_calls() {
+    let ctx = setup();
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+    let deposit = |amount_wad: u128| {
+        build_external_call(
+            &ctx.env,
+            ctx.blend_pool.clone(),
+            SmartAccExternalAction::Deposit,
+            USDC_SYMBOL,
+            amount_wad,
+            smart_account.clone(),
+        )
+    };
+    account_manager_client.execute(&smart_account, &deposit(100u128 * WAD_U128));
 
-    let mut amount_out = Vec::new(env);
-    amount_out.push_back(U256::from_u128(env, amount0_wad));
-    amount_out.push_back(U256::from_u128(env, amount1_wad));
+    RegistryContractClient::new(&ctx.env, &ctx.registry).remove_blend_pool(&ctx.blend_pool);
 
-    let call = ExternalProtocolCall {
-        protocol_address: router,
-        type_action: SmartAccExternalAction::AddLiquidity,
-        tokens_out,
-        tokens_in: Vec::new(env),
-        amount_out,
+    assert_eq!(
+        account_manager_client.try_execute(&smart_account, &deposit(WAD_U128)),
+        Err(Ok(soroban_sdk::Error::from_contract_error(
+            AccountManagerError::BlendPoolDelisted as u32
+        )))
+    );
+
+    // The open position can still be withdrawn
+    account_manager_client.execute(
+        &smart_account,
+        &build_external_call(
+            &ctx.env,
+            ctx.blend_pool.clone(),
+            SmartAccExternalAction::Withdraw,
+            USDC_SYMBOL,
+            100u128 * WAD_U128,
+            smart_account.clone(),
+        ),
+    );
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    assert_eq!(
+        tracking_client.balance(&smart_account, &Symbol::new(&ctx.env, BLEND_USDC)),
+        0
+    );
+}
+
+#[test]
+fn liquidate_unwinds_blend_positions_and_burns_tracking_tokens() {
+    let ctx = setup();
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+
+    for (action, token, amount_wad) in [
+        (SmartAccExternalAction::Deposit, XLM_SYMBOL, 30u128 * WAD_U128),
+        (SmartAccExternalAction::SupplyCollateral, USDC_SYMBOL, 100u128 * WAD_U128),
+        (SmartAccExternalAction::BlendBorrow, XLM_SYMBOL, 20u128 * WAD_U128),
+    ] {
+        account_manager_client.execute(
+            &smart_account,
+            &build_external_call(
+                &ctx.env,
+                ctx.blend_pool.clone(),
+                action,
+                token,
+                amount_wad,
+                smart_account.clone(),
+            ),
+        );
+    }
+
+    let risk_engine = ctx.env.register(MockRiskEngine, ());
+    MockRiskEngineClient::new(&ctx.env, &risk_engine).set_unhealthy(&true);
+    RegistryContractClient::new(&ctx.env, &ctx.registry).set_risk_engine_address(&risk_engine);
+
+    account_manager_client.liquidate(&smart_account);
+
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    for symbol in [BLEND_XLM, BLEND_COLL_USDC, BLEND_DEBT_XLM] {
+        assert_eq!(
+            tracking_client.balance(&smart_account, &Symbol::new(&ctx.env, symbol)),
+            0
+        );
+    }
+    let positions = blend_contract_sdk::pool::Client::new(&ctx.env, &ctx.blend_pool)
+        .get_positions(&smart_account);
+    assert!(positions.supply.is_empty());
+    assert!(positions.collateral.is_empty());
+    assert!(positions.liabilities.is_empty());
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert!(sa_client.get_all_borrowed_tokens().is_empty());
+    assert!(sa_client.get_all_collateral_tokens().is_empty());
+}
+
+#[test]
+fn liquidate_sells_collateral_to_repay_blend_liability() {
+    let ctx = setup();
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+    for (action, token, amount_wad) in [
+        (SmartAccExternalAction::SupplyCollateral, USDC_SYMBOL, 100u128 * WAD_U128),
+        (SmartAccExternalAction::BlendBorrow, XLM_SYMBOL, 20u128 * WAD_U128),
+    ] {
+        account_manager_client.execute(
+            &smart_account,
+            &build_external_call(
+                &ctx.env,
+                ctx.blend_pool.clone(),
+                action,
+                token,
+                amount_wad,
+                smart_account.clone(),
+            ),
+        );
+    }
+    // The borrowed XLM leaves the account, so only Blend collateral can pay the debt back
+    account_manager_client.withdraw_collateral_balance(
+        &smart_account,
+        &XLM_SYMBOL,
+        &U256::from_u128(&ctx.env, 20u128 * WAD_U128),
+    );
+
+    let router = ctx.env.register(MockAquariusRouter, ());
+    let registry_client = RegistryContractClient::new(&ctx.env, &ctx.registry);
+    registry_client.set_aquarius_router_address(&router);
+    registry_client.set_aquarius_pool_index(&soroban_sdk::BytesN::from_array(&ctx.env, &[7u8; 32]));
+    StellarAssetClient::new(&ctx.env, &ctx.xlm).mint(&router, &1_000_000_000i128);
+    let risk_engine = ctx.env.register(MockRiskEngine, ());
+    MockRiskEngineClient::new(&ctx.env, &risk_engine).set_unhealthy(&true);
+    registry_client.set_risk_engine_address(&risk_engine);
+
+    account_manager_client.liquidate(&smart_account);
+
+    let positions = blend_contract_sdk::pool::Client::new(&ctx.env, &ctx.blend_pool)
+        .get_positions(&smart_account);
+    assert!(positions.collateral.is_empty());
+    assert!(positions.liabilities.is_empty());
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert!(sa_client.get_all_borrowed_tokens().is_empty());
+    // Part of the withdrawn USDC was sold for XLM before the rest was swept to the owner
+    let usdc_client = soroban_sdk::token::Client::new(&ctx.env, &ctx.usdc);
+    assert!(usdc_client.balance(&ctx.user) < 1_000_000_000i128);
+    assert!(usdc_client.balance(&ctx.user) > 790_000_000i128);
+}
+
+fn deposit_usdc_and_accrue_blnd(ctx: &TestContext, amount: i128) -> (Address, Address) {
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+    account_manager_client.execute(
+        &smart_account,
+        &build_external_call(
+            &ctx.env,
+            ctx.blend_pool.clone(),
+            SmartAccExternalAction::Deposit,
+            USDC_SYMBOL,
+            100u128 * WAD_U128,
+            smart_account.clone(),
+        ),
+    );
+
+    let blnd = ctx.env.register_stellar_asset_contract_v2(ctx.admin.clone()).address();
+    StellarAssetClient::new(&ctx.env, &blnd).mint(&ctx.blend_pool, &amount);
+    RegistryContractClient::new(&ctx.env, &ctx.registry).set_blend_reward_token_address(&blnd);
+    MockBlendPoolClient::new(&ctx.env, &ctx.blend_pool).set_emissions(&blnd, &smart_account, &amount);
+    (smart_account, blnd)
+}
+
+#[test]
+fn claim_external_rewards_pays_blnd_to_owner() {
+    let ctx = setup();
+    let (smart_account, blnd) = deposit_usdc_and_accrue_blnd(&ctx, 50_0000000);
+
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let claimed = account_manager_client.claim_external_rewards(
+        &smart_account,
+        &ctx.blend_pool,
+        &None,
+        &U256::from_u128(&ctx.env, 0),
+    );
+
+    // BLND is not collateral, so it goes straight to the owner instead of idling unbooked
+    assert_eq!(claimed, 50_0000000);
+    let blnd_client = soroban_sdk::token::Client::new(&ctx.env, &blnd);
+    assert_eq!(blnd_client.balance(&ctx.user), 50_0000000);
+    assert_eq!(blnd_client.balance(&smart_account), 0);
+
+    // Emissions are paid out only once
+    let claimed_again = account_manager_client.claim_external_rewards(
+        &smart_account,
+        &ctx.blend_pool,
+        &None,
+        &U256::from_u128(&ctx.env, 0),
+    );
+    assert_eq!(claimed_again, 0);
+}
+
+#[test]
+fn claim_external_rewards_sells_blnd_into_collateral() {
+    let ctx = setup();
+    let (smart_account, _blnd) = deposit_usdc_and_accrue_blnd(&ctx, 50_0000000);
+
+    let router = ctx.env.register(MockAquariusRouter, ());
+    let registry_client = RegistryContractClient::new(&ctx.env, &ctx.registry);
+    registry_client.set_aquarius_router_address(&router);
+    registry_client.set_blend_reward_pool_index(&soroban_sdk::BytesN::from_array(&ctx.env, &[7u8; 32]));
+
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+    account_manager_client.claim_external_rewards(
+        &smart_account,
+        &ctx.blend_pool,
+        &Some(USDC_SYMBOL),
+        &U256::from_u128(&ctx.env, 40u128 * WAD_U128),
+    );
+
+    // Mock router swaps 1:1 minus a 0.3% fee
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert!(sa_client.get_all_collateral_tokens().contains(&USDC_SYMBOL));
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&USDC_SYMBOL),
+        U256::from_u128(&ctx.env, 49_850_000_000_000_000_000)
+    );
+}
+
+#[test]
+#[should_panic]
+fn claim_external_rewards_rejects_sale_below_minimum() {
+    let ctx = setup();
+    let (smart_account, _blnd) = deposit_usdc_and_accrue_blnd(&ctx, 50_0000000);
+
+    let router = ctx.env.register(MockAquariusRouter, ());
+    let registry_client = RegistryContractClient::new(&ctx.env, &ctx.registry);
+    registry_client.set_aquarius_router_address(&router);
+    registry_client.set_blend_reward_pool_index(&soroban_sdk::BytesN::from_array(&ctx.env, &[7u8; 32]));
+
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+    account_manager_client.claim_external_rewards(
+        &smart_account,
+        &ctx.blend_pool,
+        &Some(USDC_SYMBOL),
+        &U256::from_u128(&ctx.env, 50u128 * WAD_U128),
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #6)")]
+fn execute_rejects_call_that_is_not_whitelisted() {
+    let ctx = setup();
+
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+
+    account_manager_client.disallow_external_call(
+        &ctx.blend_pool,
+        &SmartAccExternalAction::Deposit,
+        &soroban_sdk::vec![&ctx.env, USDC_SYMBOL],
+    );
+
+    let call_bytes = build_external_call(
+        &ctx.env,
+        ctx.blend_pool.clone(),
+        SmartAccExternalAction::Deposit,
+        USDC_SYMBOL,
+        100u128 * WAD_U128,
+        smart_account.clone(),
+    );
+    account_manager_client.execute(&smart_account, &call_bytes);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #45)")]
+fn allow_external_call_rejects_duplicate_tokens() {
+    let ctx = setup();
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+
+    account_manager_client.allow_external_call(
+        &Address::generate(&ctx.env),
+        &SmartAccExternalAction::Swap,
+        &soroban_sdk::vec![&ctx.env, XLM_SYMBOL, XLM_SYMBOL],
+    );
+}
+
+#[test]
+fn allowed_external_calls_match_token_sets_in_any_order() {
+    let ctx = setup();
+    let account_manager_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let router = Address::generate(&ctx.env);
+
+    account_manager_client.allow_external_call(
+        &router,
+        &SmartAccExternalAction::Swap,
+        &soroban_sdk::vec![&ctx.env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+    // Duplicate entries are ignored
+    account_manager_client.allow_external_call(
+        &router,
+        &SmartAccExternalAction::Swap,
+        &soroban_sdk::vec![&ctx.env, USDC_SYMBOL, XLM_SYMBOL],
+    );
+
+    assert!(account_manager_client.can_call(
+        &router,
+        &SmartAccExternalAction::Swap,
+        &soroban_sdk::vec![&ctx.env, USDC_SYMBOL, XLM_SYMBOL],
+    ));
+    assert!(!account_manager_client.can_call(
+        &router,
+        &SmartAccExternalAction::AddLiquidity,
+        &soroban_sdk::vec![&ctx.env, XLM_SYMBOL, USDC_SYMBOL],
+    ));
+    assert!(!account_manager_client.can_call(
+        &router,
+        &SmartAccExternalAction::Swap,
+        &soroban_sdk::vec![&ctx.env, XLM_SYMBOL, EURC_SYMBOL],
+    ));
+    assert!(!account_manager_client.can_call(
+        &router,
+        &SmartAccExternalAction::Swap,
+        &soroban_sdk::vec![&ctx.env, XLM_SYMBOL],
+    ));
+    // Repeating a whitelisted token does not match the set
+    assert!(!account_manager_client.can_call(
+        &router,
+        &SmartAccExternalAction::Swap,
+        &soroban_sdk::vec![&ctx.env, XLM_SYMBOL, XLM_SYMBOL],
+    ));
+
+    assert_eq!(
+        account_manager_client.get_allowed_calls_for_protocol(&router).len(),
+        1
+    );
+    // 3 tokens x 6 Blend request actions on the Blend pool + the router swap
+    assert_eq!(account_manager_client.get_allowed_external_calls().len(), 19);
+
+    account_manager_client.disallow_external_call(
+        &router,
+        &SmartAccExternalAction::Swap,
+        &soroban_sdk::vec![&ctx.env, USDC_SYMBOL, XLM_SYMBOL],
+    );
+    assert!(account_manager_client.get_allowed_calls_for_protocol(&router).is_empty());
+}
+
+// ============================================================================
+// Aquarius Protocol Integration Tests
+// ============================================================================
+
+const AQUARIUS_XLM_USDC: &str = "AQ_XLM_USDC";
+
+#[derive(Clone)]
+#[contracttype]
+enum MockAquariusKey {
+    Admin,
+    PoolIndex,
+    LPBalance(Address),
+    TokenBalance(Address, Address), // (user, token)
+    Reserves,
+    TotalShares,
+}
+
+#[contract]
+pub struct MockAquariusRouter;
+
+#[contractimpl]
+impl MockAquariusRouter {
+    pub fn init(env: Env, admin: Address) {
+        admin.require_auth();
+        env.storage()
+            .persistent()
+            .set(&MockAquariusKey::Admin, &admin);
+    }
+
+    pub fn init_standard_pool(
+        env: Env,
+        sender: Address,
+        tokens: Vec<Address>,
+        fee_fraction: u32,
+    ) -> (soroban_sdk::BytesN<32>, Address) {
+        let pool_index = env.crypto().sha256(&tokens.to_xdr(&env));
+        env.storage()
+            .persistent()
+            .set(&MockAquariusKey::PoolIndex, &pool_index);
+        (soroban_sdk::BytesN::from_array(&env, &pool_index.to_array()), sender.clone())
+    }
+
+    pub fn deposit(
+        env: Env,
+        sender: Address,
+        tokens: Vec<Address>,
+        pool_id: soroban_sdk::BytesN<32>,
+        desired_amounts: Vec<u128>,
+        min_shares: u128,
+    ) -> (Vec<u128>, u128) {
+        // Simple mock: LP tokens = average of deposited amounts
+        let amount0 = desired_amounts.get(0).unwrap();
+        let amount1 = desired_amounts.get(1).unwrap();
+        let lp_tokens = (amount0 + amount1) / 2;
+
+        let current_lp = env
+            .storage()
+            .persistent()
+            .get(&MockAquariusKey::LPBalance(sender.clone()))
+            .unwrap_or(0u128);
+
+        env.storage()
+            .persistent()
+            .set(&MockAquariusKey::LPBalance(sender), &(current_lp + lp_tokens));
+
+        let reserves = Self::get_reserves(env.clone(), tokens.clone(), pool_id.clone());
+        env.storage().persistent().set(
+            &MockAquariusKey::Reserves,
+            &soroban_sdk::vec![
+                &env,
+                reserves.get(0).unwrap() + amount0,
+                reserves.get(1).unwrap() + amount1
+            ],
+        );
+        let total_shares = Self::get_total_shares(env.clone(), tokens, pool_id);
+        env.storage()
+            .persistent()
+            .set(&MockAquariusKey::TotalShares, &(total_shares + lp_tokens));
+
+        (desired_amounts, lp_tokens)
+    }
+
+    pub fn withdraw(
+        env: Env,
+        sender: Address,
+        tokens: Vec<Address>,
+        pool_id: soroban_sdk::BytesN<32>,
+        share_amount: u128,
+        min_amounts: Vec<u128>,
+    ) -> Vec<u128> {
+        let current_lp = env
+            .storage()
+            .persistent()
+            .get(&MockAquariusKey::LPBalance(sender.clone()))
+            .unwrap_or(0u128);
+
+        if share_amount > current_lp {
+            panic!("Insufficient LP tokens");
+        }
+
+        env.storage()
+            .persistent()
+            .set(
+                &MockAquariusKey::LPBalance(sender),
+                &(current_lp - share_amount),
+            );
+
+        // Return proportional amounts
+        let reserves = Self::get_reserves(env.clone(), tokens.clone(), pool_id.clone());
+        let total_shares = Self::get_total_shares(env.clone(), tokens, pool_id);
+        let mut amounts = Vec::new(&env);
+        let mut remaining = Vec::new(&env);
+        for reserve in reserves.iter() {
+            let amount = reserve * share_amount / total_shares;
+            amounts.push_back(amount);
+            remaining.push_back(reserve - amount);
+        }
+        env.storage()
+            .persistent()
+            .set(&MockAquariusKey::Reserves, &remaining);
+        env.storage()
+            .persistent()
+            .set(&MockAquariusKey::TotalShares, &(total_shares - share_amount));
+        amounts
+    }
+
+    pub fn get_reserves(
+        env: Env,
+        _tokens: Vec<Address>,
+        _pool_index: soroban_sdk::BytesN<32>,
+    ) -> Vec<u128> {
+        env.storage()
+            .persistent()
+            .get(&MockAquariusKey::Reserves)
+            .unwrap_or(soroban_sdk::vec![&env, 0u128, 0u128])
+    }
+
+    pub fn get_total_shares(
+        env: Env,
+        _tokens: Vec<Address>,
+        _pool_index: soroban_sdk::BytesN<32>,
+    ) -> u128 {
+        env.storage()
+            .persistent()
+            .get(&MockAquariusKey::TotalShares)
+            .unwrap_or(0u128)
+    }
+
+    pub fn swap(
+        env: Env,
+        sender: Address,
+        tokens: Vec<Address>,
+        token_in: Address,
+        token_out: Address,
+        pool_id: soroban_sdk::BytesN<32>,
+        amount_in: u128,
+        min_amount_out: u128,
+    ) -> u128 {
+        // Simple mock: 1:1 swap with 0.3% fee
+        let amount_out = (amount_in * 997) / 1000;
+        if amount_out < min_amount_out {
+            panic!("Out amount is less than min");
+        }
+        // Pays out only when funded; most tests leave the router empty
+        let token_out_client = soroban_sdk::token::Client::new(&env, &token_out);
+        let router = env.current_contract_address();
+        if token_out_client.balance(&router) >= amount_out as i128 {
+            token_out_client.transfer(&router, &sender, &(amount_out as i128));
+        }
+        amount_out
+    }
+
+    pub fn get_lp_balance(env: Env, sender: Address) -> u128 {
+        env.storage()
+            .persistent()
+            .get(&MockAquariusKey::LPBalance(sender))
+            .unwrap_or(0u128)
+    }
+}
+
+fn build_aquarius_add_liquidity_call(
+    env: &Env,
+    router: Address,
+    token0: Symbol,
+    token1: Symbol,
+    amount0_wad: u128,
+    amount1_wad: u128,
+    smart_account: Address,
+) -> Bytes {
+    let mut tokens_out = Vec::new(env);
+    tokens_out.push_back(token0);
+    tokens_out.push_back(token1);
+
+    let mut amount_out = Vec::new(env);
+    amount_out.push_back(U256::from_u128(env, amount0_wad));
+    amount_out.push_back(U256::from_u128(env, amount1_wad));
+
+    let call = ExternalProtocolCall {
+        protocol_address: router,
+        type_action: SmartAccExternalAction::AddLiquidity,
+        tokens_out,
+        tokens_in: Vec::new(env),
+        amount_out,
+        amount_in: Vec::new(env),
+        is_token_pair: true,
+        token_pair_ratio: 0,
+        margin_account: smart_account,
+        fee_fraction: 30u32,
+        min_liquidity_out: U256::from_u128(env, 0),
+        min_amounts_out: Vec::new(env),
+    };
+
+    call.to_xdr(env)
+}
+
+fn build_aquarius_remove_liquidity_call(
+    env: &Env,
+    router: Address,
+    token0: Symbol,
+    token1: Symbol,
+    lp_amount: u128,
+    smart_account: Address,
+) -> Bytes {
+    let mut tokens_out = Vec::new(env);
+    tokens_out.push_back(token0);
+    tokens_out.push_back(token1);
+
+    let mut amount_out = Vec::new(env);
+    amount_out.push_back(U256::from_u128(env, lp_amount));
+
+    let call = ExternalProtocolCall {
+        protocol_address: router,
+        type_action: SmartAccExternalAction::RemoveLiquidity,
+        tokens_out,
+        tokens_in: Vec::new(env),
+        amount_out,
+        amount_in: Vec::new(env),
+        is_token_pair: true,
+        token_pair_ratio: 0,
+        margin_account: smart_account,
+        fee_fraction: 30u32,
+        min_liquidity_out: U256::from_u128(env, 0),
+        min_amounts_out: Vec::new(env),
+    };
+
+    call.to_xdr(env)
+}
+
+fn build_aquarius_swap_call(
+    env: &Env,
+    router: Address,
+    token_in: Symbol,
+    token_out: Symbol,
+    amount_in_wad: u128,
+    smart_account: Address,
+) -> Bytes {
+    let mut tokens_out = Vec::new(env);
+    tokens_out.push_back(token_in);
+    tokens_out.push_back(token_out);
+
+    let mut amount_out = Vec::new(env);
+    amount_out.push_back(U256::from_u128(env, amount_in_wad));
+
+    let call = ExternalProtocolCall {
+        protocol_address: router,
+        type_action: SmartAccExternalAction::Swap,
+        tokens_out,
+        tokens_in: Vec::new(env),
+        amount_out,
+        amount_in: Vec::new(env),
+        is_token_pair: false,
+        token_pair_ratio: 0,
+        margin_account: smart_account,
+        fee_fraction: 30u32,
+        min_liquidity_out: U256::from_u128(env, 0),
+        min_amounts_out: Vec::new(env),
+    };
+
+    call.to_xdr(env)
+}
+
+struct AquariusTestContext {
+    env: Env,
+    admin: Address,
+    user: Address,
+    registry: Address,
+    account_manager: Address,
+    aquarius_router: Address,
+    tracking_token: Address,
+    xlm: Address,
+    usdc: Address,
+}
+
+fn setup_aquarius() -> AquariusTestContext {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    let registry = Address::generate(&env);
+    let account_manager = Address::generate(&env);
+
+    env.register_at(&registry, RegistryContract, (admin.clone(),));
+    env.register_at(
+        &account_manager,
+        AccountManagerContract,
+        (admin.clone(), registry.clone()),
+    );
+
+    let xlm_token = env.register_stellar_asset_contract_v2(admin.clone());
+    let usdc_token = env.register_stellar_asset_contract_v2(admin.clone());
+
+    let aquarius_router = env.register(MockAquariusRouter, ());
+    let aquarius_router_client = MockAquariusRouterClient::new(&env, &aquarius_router);
+    aquarius_router_client.init(&admin);
+
+    // Init pool
+    let mut tokens = Vec::new(&env);
+    tokens.push_back(xlm_token.address());
+    tokens.push_back(usdc_token.address());
+    let (pool_index, _) = aquarius_router_client.init_standard_pool(&admin, &tokens, &30u32);
+
+    let tracking_token = env.register(TrackingToken, ());
+    let tracking_client = TrackingTokenClient::new(&env, &tracking_token);
+
+    tracking_client.initialize(
+        &account_manager,
+        &Symbol::new(&env, AQUARIUS_XLM_USDC),
+        &7u32,
+        &String::from_str(&env, "Aquarius XLM-USDC LP"),
+    );
+
+    let registry_client = RegistryContractClient::new(&env, &registry);
+    let smart_hash = env.deployer().upload_contract_wasm(SMART_ACCOUNT_WASM);
+    registry_client.set_smart_account_hash(&smart_hash);
+    registry_client.set_native_xlm_contract_address(&xlm_token.address());
+    registry_client.set_native_usdc_contract_address(&usdc_token.address());
+    registry_client.set_aquarius_router_address(&aquarius_router);
+    registry_client.set_aquarius_pool_index(&pool_index);
+    registry_client.set_tracking_token_contract_addr(&tracking_token);
+    registry_client.set_accountmanager_contract(&account_manager);
+    registry_client.set_risk_engine_address(&env.register(MockRiskEngine, ()));
+    allow_external_calls(
+        &env,
+        &account_manager,
+        &aquarius_router,
+        &[
+            SmartAccExternalAction::AddLiquidity,
+            SmartAccExternalAction::RemoveLiquidity,
+            SmartAccExternalAction::Swap,
+        ],
+        soroban_sdk::vec![&env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+
+    AquariusTestContext {
+        env,
+        admin,
+        user,
+        registry,
+        account_manager,
+        aquarius_router,
+        tracking_token,
+        xlm: xlm_token.address(),
+        usdc: usdc_token.address(),
+    }
+}
+
+#[test]
+fn test_aquarius_add_liquidity_mints_lp_tracking_tokens() {
+    let ctx = setup_aquarius();
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+
+    // Add liquidity: 1000 XLM + 1000 USDC
+    let xlm_amount_wad = 1000u128 * WAD_U128;
+    let usdc_amount_wad = 1000u128 * WAD_U128;
+
+    let call_bytes = build_aquarius_add_liquidity_call(
+        &ctx.env,
+        ctx.aquarius_router.clone(),
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        xlm_amount_wad,
+        usdc_amount_wad,
+        smart_account.clone(),
+    );
+
+    account_manager_client.execute(&smart_account, &call_bytes);
+
+    // Verify LP tracking tokens were minted
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let lp_tracking_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
+    let lp_balance = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+
+    assert!(lp_balance > 0);
+    // Mock returns average of token amounts: (1000*10^7 + 1000*10^7) / 2 = 10^10
+    assert_eq!(lp_balance, 10_000_000_000); 
+}
+
+#[test]
+fn test_aquarius_remove_liquidity_burns_lp_tracking_tokens() {
+    let ctx = setup_aquarius();
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+
+    // Add liquidity first
+    let xlm_amount_wad = 2000u128 * WAD_U128;
+    let usdc_amount_wad = 2000u128 * WAD_U128;
+
+    let add_call = build_aquarius_add_liquidity_call(
+        &ctx.env,
+        ctx.aquarius_router.clone(),
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        xlm_amount_wad,
+        usdc_amount_wad,
+        smart_account.clone(),
+    );
+
+    account_manager_client.execute(&smart_account, &add_call);
+
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let lp_tracking_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
+    let initial_lp = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+
+    // Remove half the liquidity
+    let remove_amount = initial_lp / 2;
+    let remove_call = build_aquarius_remove_liquidity_call(
+        &ctx.env,
+        ctx.aquarius_router.clone(),
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        remove_amount as u128,
+        smart_account.clone(),
+    );
+
+    account_manager_client.execute(&smart_account, &remove_call);
+
+    // Verify LP tracking tokens were burned
+    let final_lp = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+    assert_eq!(final_lp, initial_lp - remove_amount);
+}
+
+#[test]
+fn test_aquarius_full_flow_add_swap_remove() {
+    let ctx = setup_aquarius();
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+
+    // Step 1: Add liquidity
+    let xlm_amount_wad = 5000u128 * WAD_U128;
+    let usdc_amount_wad = 5000u128 * WAD_U128;
+
+    let add_call = build_aquarius_add_liquidity_call(
+        &ctx.env,
+        ctx.aquarius_router.clone(),
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        xlm_amount_wad,
+        usdc_amount_wad,
+        smart_account.clone(),
+    );
+
+    account_manager_client.execute(&smart_account, &add_call);
+
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let lp_tracking_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
+    let lp_after_add = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+    assert!(lp_after_add > 0);
+
+    // Step 2: Execute a swap (shouldn't affect LP tracking)
+    let swap_call = build_aquarius_swap_call(
+        &ctx.env,
+        ctx.aquarius_router.clone(),
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        1000u128 * WAD_U128,
+        smart_account.clone(),
+    );
+
+    account_manager_client.execute(&smart_account, &swap_call);
+
+    let lp_after_swap = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+    assert_eq!(lp_after_swap, lp_after_add); // LP balance unchanged after swap
+
+    // Step 3: Remove all liquidity
+    let remove_call = build_aquarius_remove_liquidity_call(
+        &ctx.env,
+        ctx.aquarius_router.clone(),
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        lp_after_add as u128,
+        smart_account.clone(),
+    );
+
+    account_manager_client.execute(&smart_account, &remove_call);
+
+    let lp_final = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+    assert_eq!(lp_final, 0);
+}
+
+#[test]
+fn test_aquarius_single_token_to_liquidity_flow() {
+    let ctx = setup_aquarius();
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = account_manager_client.create_account(&ctx.user);
+
+    // User starts with only XLM (realistic scenario)
+    let initial_xlm_wad = 10000u128 * WAD_U128;
+
+    // Step 1: User swaps half of their XLM to get USDC
+    let xlm_to_swap_wad = initial_xlm_wad / 2; // Swap 50% of XLM
+    let swap_call = build_aquarius_swap_call(
+        &ctx.env,
+        ctx.aquarius_router.clone(),
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        xlm_to_swap_wad,
+        smart_account.clone(),
+    );
+
+    account_manager_client.execute(&smart_account, &swap_call);
+
+    // Step 2: Now user has both XLM and USDC, add liquidity
+    let remaining_xlm_wad = initial_xlm_wad - xlm_to_swap_wad;
+    // Assuming 1:1 swap ratio for simplicity (in real scenario would query pool)
+    let received_usdc_wad = xlm_to_swap_wad;
+
+    let add_liquidity_call = build_aquarius_add_liquidity_call(
+        &ctx.env,
+        ctx.aquarius_router.clone(),
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        remaining_xlm_wad,
+        received_usdc_wad,
+        smart_account.clone(),
+    );
+
+    account_manager_client.execute(&smart_account, &add_liquidity_call);
+
+    // Verify LP tokens were minted and tracked
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let lp_tracking_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
+    let lp_balance = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+
+    assert!(lp_balance > 0, "LP tracking tokens should be minted");
+
+    // Step 3: Remove liquidity to complete the flow
+    let remove_call = build_aquarius_remove_liquidity_call(
+        &ctx.env,
+        ctx.aquarius_router.clone(),
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        lp_balance as u128,
+        smart_account.clone(),
+    );
+
+    account_manager_client.execute(&smart_account, &remove_call);
+
+    let final_lp_balance = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+    assert_eq!(final_lp_balance, 0, "All LP tokens should be burned");
+}
+
+// ============================================================================
+// Full Cycle: Create Account → Deposit Collateral → Borrow → Open Aquarius Position
+// ============================================================================
+
+#[derive(Clone)]
+#[contracttype]
+enum MockLendingPoolKey {
+    BorrowBalance(Address),
+}
+
+#[contract]
+pub struct MockLendingPool;
+
+#[contractimpl]
+impl MockLendingPool {
+    pub fn lend_to(env: Env, borrower: Address, amount_wad: U256) -> bool {
+        let current: U256 = env
+            .storage()
+            .persistent()
+            .get(&MockLendingPoolKey::BorrowBalance(borrower.clone()))
+            .unwrap_or(U256::from_u128(&env, 0));
+        env.storage()
+            .persistent()
+            .set(&MockLendingPoolKey::BorrowBalance(borrower), &current.add(&amount_wad));
+        true
+    }
+
+    // No interest accrues in the mock
+    pub fn update_state(_env: Env) {}
+
+    pub fn get_borrow_balance(env: Env, borrower: Address) -> U256 {
+        env.storage()
+            .persistent()
+            .get(&MockLendingPoolKey::BorrowBalance(borrower))
+            .unwrap_or(U256::from_u128(&env, 0))
+    }
+
+    pub fn get_user_borrow_shares(env: Env, borrower: Address) -> U256 {
+        env.storage()
+            .persistent()
+            .get(&MockLendingPoolKey::BorrowBalance(borrower))
+            .unwrap_or(U256::from_u128(&env, 0))
+    }
+
+    pub fn collect_from(env: Env, amount_wad: U256, borrower: Address) -> bool {
+        let current: U256 = env
+            .storage()
+            .persistent()
+            .get(&MockLendingPoolKey::BorrowBalance(borrower.clone()))
+            .unwrap_or(U256::from_u128(&env, 0));
+        let remaining = if amount_wad >= current {
+            U256::from_u128(&env, 0)
+        } else {
+            current.sub(&amount_wad)
+        };
+        env.storage()
+            .persistent()
+            .set(&MockLendingPoolKey::BorrowBalance(borrower), &remaining);
+        remaining == U256::from_u128(&env, 0)
+    }
+}
+
+#[derive(Clone)]
+#[contracttype]
+enum MockRiskEngineKey {
+    Unhealthy,
+    InDeleverageZone,
+}
+
+#[contract]
+pub struct MockRiskEngine;
+
+#[contractimpl]
+impl MockRiskEngine {
+    pub fn is_borrow_allowed(_env: Env, _symbol: Symbol, _amount: U256, _account: Address) -> bool {
+        true
+    }
+
+    pub fn is_withdraw_allowed(_env: Env, _symbol: Symbol, _amount: U256, _account: Address) -> bool {
+        true
+    }
+
+    pub fn set_unhealthy(env: Env, unhealthy: bool) {
+        env.storage()
+            .persistent()
+            .set(&MockRiskEngineKey::Unhealthy, &unhealthy);
+    }
+
+    // An unhealthy account reports a 1.05 balance-to-debt ratio
+    pub fn get_current_total_balance(env: Env, _account: Address) -> U256 {
+        if Self::is_unhealthy(&env) {
+            U256::from_u128(&env, 105 * WAD_U128)
+        } else {
+            U256::from_u128(&env, 0)
+        }
+    }
+
+    pub fn get_current_total_borrows(env: Env, _account: Address) -> U256 {
+        if Self::is_unhealthy(&env) {
+            U256::from_u128(&env, 100 * WAD_U128)
+        } else {
+            U256::from_u128(&env, 0)
+        }
+    }
+
+    pub fn is_account_healthy(env: Env, _balance: U256, _debt: U256) -> bool {
+        !Self::is_unhealthy(&env)
+    }
+
+    pub fn set_in_deleverage_zone(env: Env, in_zone: bool) {
+        env.storage()
+            .persistent()
+            .set(&MockRiskEngineKey::InDeleverageZone, &in_zone);
+    }
+
+    pub fn is_deleverage_allowed(env: Env, _balance: U256, _debt: U256) -> bool {
+        env.storage()
+            .persistent()
+            .get(&MockRiskEngineKey::InDeleverageZone)
+            .unwrap_or(false)
+    }
+
+    pub fn get_isolated_collateral(_env: Env, _account: Address) -> Option<Symbol> {
+        None
+    }
+
+    pub fn is_execute_allowed(_env: Env, _account: Address, _protocol: Address) -> bool {
+        true
+    }
+
+    // Matches the mock oracle's 0.5 USD
+    pub fn get_asset_price(env: Env, _symbol: Symbol) -> U256 {
+        U256::from_u128(&env, WAD_U128 / 2)
+    }
+}
+
+impl MockRiskEngine {
+    fn is_unhealthy(env: &Env) -> bool {
+        env.storage()
+            .persistent()
+            .get(&MockRiskEngineKey::Unhealthy)
+            .unwrap_or(false)
+    }
+}
+
+struct FullCycleTestContext {
+    env: Env,
+    user: Address,
+    registry: Address,
+    account_manager: Address,
+    risk_engine: Address,
+    aquarius_router: Address,
+    tracking_token: Address,
+    xlm: Address,
+    usdc: Address,
+}
+
+fn setup_full_cycle() -> FullCycleTestContext {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    let registry = Address::generate(&env);
+    let account_manager = Address::generate(&env);
+
+    env.register_at(&registry, RegistryContract, (admin.clone(),));
+    env.register_at(
+        &account_manager,
+        AccountManagerContract,
+        (admin.clone(), registry.clone()),
+    );
+
+    let xlm_token = env.register_stellar_asset_contract_v2(admin.clone());
+    let usdc_token = env.register_stellar_asset_contract_v2(admin.clone());
+
+    // Mint XLM to user so deposit_collateral_tokens can transfer it (200 XLM, 7 decimals)
+    StellarAssetClient::new(&env, &xlm_token.address()).mint(&user, &2_000_000_000i128);
+
+    // Register Aquarius router and init XLM-USDC pool
+    let aquarius_router = env.register(MockAquariusRouter, ());
+    let aquarius_router_client = MockAquariusRouterClient::new(&env, &aquarius_router);
+    aquarius_router_client.init(&admin);
+    let mut tokens = Vec::new(&env);
+    tokens.push_back(xlm_token.address());
+    tokens.push_back(usdc_token.address());
+    let (pool_index, _) = aquarius_router_client.init_standard_pool(&admin, &tokens, &30u32);
+
+    // Register mock USDC lending pool and mock risk engine
+    let lending_pool_usdc = env.register(MockLendingPool, ());
+    let risk_engine = env.register(MockRiskEngine, ());
+
+    // Register tracking token and initialise AQ_XLM_USDC symbol
+    let tracking_token = env.register(TrackingToken, ());
+    let tracking_client = TrackingTokenClient::new(&env, &tracking_token);
+    tracking_client.initialize(
+        &account_manager,
+        &Symbol::new(&env, AQUARIUS_XLM_USDC),
+        &7u32,
+        &String::from_str(&env, "Aquarius XLM-USDC LP"),
+    );
+
+    // Configure registry
+    let registry_client = RegistryContractClient::new(&env, &registry);
+    let smart_hash = env.deployer().upload_contract_wasm(SMART_ACCOUNT_WASM);
+    registry_client.set_smart_account_hash(&smart_hash);
+    registry_client.set_native_xlm_contract_address(&xlm_token.address());
+    registry_client.set_native_usdc_contract_address(&usdc_token.address());
+    registry_client.set_lendingpool_usdc(&lending_pool_usdc);
+    registry_client.set_risk_engine_address(&risk_engine);
+    registry_client.set_oracle_contract_address(&env.register(MockPriceOracle, ()));
+    registry_client.set_aquarius_router_address(&aquarius_router);
+    registry_client.set_aquarius_pool_index(&pool_index);
+    registry_client.set_tracking_token_contract_addr(&tracking_token);
+    registry_client.set_accountmanager_contract(&account_manager);
+    allow_external_calls(
+        &env,
+        &account_manager,
+        &aquarius_router,
+        &[
+            SmartAccExternalAction::AddLiquidity,
+            SmartAccExternalAction::RemoveLiquidity,
+        ],
+        soroban_sdk::vec![&env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+
+    // Allow XLM as collateral and set asset cap on account manager
+    let am_client = AccountManagerContractClient::new(&env, &account_manager);
+    am_client.set_max_asset_cap(&U256::from_u128(&env, 10));
+    am_client.set_iscollateral_allowed(&XLM_SYMBOL);
+
+    FullCycleTestContext {
+        env,
+        user,
+        registry,
+        account_manager,
+        risk_engine,
+        aquarius_router,
+        tracking_token,
+        xlm: xlm_token.address(),
+        usdc: usdc_token.address(),
+    }
+}
+
+#[test]
+fn test_full_cycle_deposit_collateral_borrow_open_aquarius_position() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+
+    // Step 1: Create margin account
+    let smart_account = am_client.create_account(&ctx.user);
+
+    // Step 2: Deposit 100 XLM as collateral
+    let collateral_wad = 100u128 * WAD_U128;
+    am_client.deposit_collateral_tokens(
+        &smart_account,
+        &XLM_SYMBOL,
+        &U256::from_u128(&ctx.env, collateral_wad),
+    );
+
+    // Verify collateral is recorded in the smart account (in WAD)
+    let sa_client =
+        account_manager_contract::account_manager::smart_account_contract::Client::new(
+            &ctx.env,
+            &smart_account,
+        );
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&XLM_SYMBOL),
+        U256::from_u128(&ctx.env, collateral_wad),
+        "Collateral balance must be recorded in WAD"
+    );
+
+    // Step 3: Borrow 50 USDC against the XLM collateral
+    let borrow_wad = 50u128 * WAD_U128;
+    am_client.borrow(
+        &smart_account,
+        &U256::from_u128(&ctx.env, borrow_wad),
+        &USDC_SYMBOL,
+    );
+
+    // Verify the smart account now carries debt
+    assert!(sa_client.has_debt(), "Smart account must have debt after borrowing");
+    assert!(
+        sa_client.get_all_borrowed_tokens().contains(USDC_SYMBOL),
+        "USDC must appear in borrowed tokens list"
+    );
+
+    // Step 4: Open Aquarius position — add 40 XLM + 40 USDC liquidity
+    let xlm_lp_wad = 40u128 * WAD_U128;
+    let usdc_lp_wad = 40u128 * WAD_U128;
+    let open_position_call = build_aquarius_add_liquidity_call(
+        &ctx.env,
+        ctx.aquarius_router.clone(),
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        xlm_lp_wad,
+        usdc_lp_wad,
+        smart_account.clone(),
+    );
+    am_client.execute(&smart_account, &open_position_call);
+
+    // Verify LP tracking tokens were minted
+    // Mock LP formula: (scaled_xlm + scaled_usdc) / 2
+    let xlm_scaled = scale_wad_to_token(xlm_lp_wad, 7);
+    let usdc_scaled = scale_wad_to_token(usdc_lp_wad, 7);
+    let expected_lp = (xlm_scaled + usdc_scaled) / 2;
+
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let lp_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
+    let lp_balance = tracking_client.balance(&smart_account, &lp_symbol);
+
+    assert!(lp_balance > 0, "LP tracking tokens must be minted after opening position");
+    assert_eq!(lp_balance, expected_lp, "LP balance must match mock pool formula");
+
+    // Verify the LP symbol is now tracked as collateral in the smart account
+    assert!(
+        sa_client.get_all_collateral_tokens().contains(lp_symbol),
+        "Aquarius LP tracking symbol must be added to smart account collateral"
+    );
+}
+
+fn build_leverage_batch(ctx: &FullCycleTestContext, smart_account: &Address) -> Vec<Bytes> {
+    let env = &ctx.env;
+    let add_liquidity = ExternalProtocolCall {
+        protocol_address: ctx.aquarius_router.clone(),
+        type_action: SmartAccExternalAction::AddLiquidity,
+        tokens_out: soroban_sdk::vec![env, XLM_SYMBOL, USDC_SYMBOL],
+        tokens_in: Vec::new(env),
+        amount_out: soroban_sdk::vec![
+            env,
+            U256::from_u128(env, 40u128 * WAD_U128),
+            U256::from_u128(env, 40u128 * WAD_U128)
+        ],
         amount_in: Vec::new(env),
         is_token_pair: true,
         token_pair_ratio: 0,
-        margin_account: smart_account,
+        margin_account: smart_account.clone(),
         fee_fraction: 30u32,
         min_liquidity_out: U256::from_u128(env, 0),
+        min_amounts_out: Vec::new(env),
     };
 
-    call.to_xdr(env)
+    soroban_sdk::vec![
+        env,
+        BatchCall::DepositCollateral(XLM_SYMBOL, U256::from_u128(env, 100u128 * WAD_U128))
+            .to_xdr(env),
+        BatchCall::Borrow(USDC_SYMBOL, U256::from_u128(env, 50u128 * WAD_U128)).to_xdr(env),
+        BatchCall::External(add_liquidity).to_xdr(env),
+    ]
+}
+
+#[test]
+fn test_execute_batch_opens_leveraged_position_in_one_call() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    am_client.execute_batch(&smart_account, &build_leverage_batch(&ctx, &smart_account));
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&XLM_SYMBOL),
+        U256::from_u128(&ctx.env, 100u128 * WAD_U128)
+    );
+    assert!(sa_client.has_debt());
+    assert!(sa_client.get_all_borrowed_tokens().contains(USDC_SYMBOL));
+
+    let expected_lp = (scale_wad_to_token(40u128 * WAD_U128, 7) * 2) / 2;
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let lp_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
+    assert_eq!(tracking_client.balance(&smart_account, &lp_symbol), expected_lp);
+}
+
+#[test]
+fn test_execute_batch_reverts_all_steps_when_unhealthy() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine).set_unhealthy(&true);
+
+    let result =
+        am_client.try_execute_batch(&smart_account, &build_leverage_batch(&ctx, &smart_account));
+    assert!(result.is_err());
+
+    // Nothing from the batch is persisted
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert!(!sa_client.has_debt());
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&XLM_SYMBOL),
+        U256::from_u128(&ctx.env, 0)
+    );
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    assert_eq!(
+        tracking_client.balance(&smart_account, &Symbol::new(&ctx.env, AQUARIUS_XLM_USDC)),
+        0
+    );
+}
+
+#[test]
+fn test_execute_batch_rejects_undecodable_call() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    // Well-formed XDR that does not decode to a BatchCall
+    let result = am_client.try_execute_batch(
+        &smart_account,
+        &soroban_sdk::vec![&ctx.env, XLM_SYMBOL.to_xdr(&ctx.env)],
+    );
+    assert_eq!(result, Err(Ok(AccountManagerError::InvalidBatchCall)));
+}
+
+fn grant_leverage_operator(
+    ctx: &FullCycleTestContext,
+    smart_account: &Address,
+    expiry_ledger: u32,
+) -> Address {
+    let operator = Address::generate(&ctx.env);
+    StellarAssetClient::new(&ctx.env, &ctx.xlm).mint(&operator, &2_000_000_000i128);
+    AccountManagerContractClient::new(&ctx.env, &ctx.account_manager).grant_operator(
+        smart_account,
+        &operator,
+        &soroban_sdk::vec![
+            &ctx.env,
+            OperatorPermission::DepositCollateral,
+            OperatorPermission::Borrow,
+            OperatorPermission::Execute(SmartAccExternalAction::AddLiquidity),
+        ],
+        &expiry_ledger,
+    );
+    operator
+}
+
+#[test]
+fn test_operator_runs_permitted_batch_with_own_funds() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    let operator = grant_leverage_operator(&ctx, &smart_account, 1_000);
+
+    assert_eq!(am_client.get_operators(&smart_account), soroban_sdk::vec![&ctx.env, operator.clone()]);
+
+    am_client.execute_as_operator(
+        &operator,
+        &smart_account,
+        &build_leverage_batch(&ctx, &smart_account),
+    );
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&XLM_SYMBOL),
+        U256::from_u128(&ctx.env, 100u128 * WAD_U128)
+    );
+    assert!(sa_client.has_debt());
+    // The deposit was paid by the operator, not the trader
+    let xlm_client = soroban_sdk::token::Client::new(&ctx.env, &ctx.xlm);
+    assert_eq!(xlm_client.balance(&operator), 1_000_000_000);
+    assert_eq!(xlm_client.balance(&ctx.user), 2_000_000_000);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #9)")]
+fn test_operator_cannot_run_steps_outside_grant() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    let operator = grant_leverage_operator(&ctx, &smart_account, 1_000);
+
+    let withdraw = BatchCall::WithdrawCollateral(XLM_SYMBOL, U256::from_u128(&ctx.env, WAD_U128));
+    am_client.execute_as_operator(
+        &operator,
+        &smart_account,
+        &soroban_sdk::vec![&ctx.env, withdraw.to_xdr(&ctx.env)],
+    );
+}
+
+#[test]
+fn test_operator_grant_expires_and_can_be_revoked() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    let operator = grant_leverage_operator(&ctx, &smart_account, 100);
+
+    ctx.env.ledger().set_sequence_number(101);
+    let result = am_client.try_execute_as_operator(
+        &operator,
+        &smart_account,
+        &build_leverage_batch(&ctx, &smart_account),
+    );
+    assert!(result.is_err());
+
+    am_client.revoke_operator(&smart_account, &operator);
+    assert!(am_client.get_operator_grant(&smart_account, &operator).is_none());
+    assert!(am_client.get_operators(&smart_account).is_empty());
+    assert!(am_client.try_revoke_operator(&smart_account, &operator).is_err());
+}
+
+#[test]
+fn test_ownership_transfer_moves_account_with_open_debt() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    am_client.execute_batch(&smart_account, &build_leverage_batch(&ctx, &smart_account));
+    let operator = grant_leverage_operator(&ctx, &smart_account, 1_000);
+
+    let new_owner = Address::generate(&ctx.env);
+    am_client.transfer_account_ownership(&smart_account, &new_owner);
+    assert_eq!(am_client.get_pending_owner(&smart_account), Some(new_owner.clone()));
+
+    am_client.accept_account_ownership(&smart_account);
+    assert_eq!(
+        ctx.env.auths()[0].0,
+        new_owner,
+        "acceptance must be signed by the proposed owner"
+    );
+    assert!(am_client.get_pending_owner(&smart_account).is_none());
+    assert!(am_client.get_operators(&smart_account).is_empty());
+    assert!(am_client.get_operator_grant(&smart_account, &operator).is_none());
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    // Positions and debt move with the account
+    assert!(sa_client.has_debt());
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&XLM_SYMBOL),
+        U256::from_u128(&ctx.env, 100u128 * WAD_U128)
+    );
+
+    // Withdrawals now need the new owner's signature and pay out to them
+    am_client.withdraw_collateral_balance(
+        &smart_account,
+        &XLM_SYMBOL,
+        &U256::from_u128(&ctx.env, WAD_U128),
+    );
+    assert_eq!(ctx.env.auths()[0].0, new_owner);
+    let xlm_client = soroban_sdk::token::Client::new(&ctx.env, &ctx.xlm);
+    assert_eq!(xlm_client.balance(&new_owner), 10_000_000);
+}
+
+#[test]
+fn test_ownership_transfer_can_be_cancelled() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    am_client.transfer_account_ownership(&smart_account, &Address::generate(&ctx.env));
+    am_client.cancel_ownership_transfer(&smart_account);
+    assert!(am_client.get_pending_owner(&smart_account).is_none());
+    assert!(am_client.try_accept_account_ownership(&smart_account).is_err());
+
+    // The original owner still signs for the account
+    am_client.transfer_account_ownership(&smart_account, &Address::generate(&ctx.env));
+    assert_eq!(ctx.env.auths()[0].0, ctx.user);
+}
+
+#[test]
+fn test_accepting_ownership_clears_orders_positions_and_auction() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    am_client.open_leveraged_position(
+        &smart_account,
+        &ctx.aquarius_router,
+        &leverage_request(&ctx.env, 0i128),
+    );
+    am_client.place_order(
+        &smart_account,
+        &OrderCondition::PriceAbove(XLM_SYMBOL, U256::from_u128(&ctx.env, WAD_U128)),
+        &OrderAction::Deleverage(5_000),
+    );
+    MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine).set_unhealthy(&true);
+    am_client.start_liquidation_auction(&smart_account);
+
+    am_client.transfer_account_ownership(&smart_account, &Address::generate(&ctx.env));
+    am_client.accept_account_ownership(&smart_account);
+
+    assert!(am_client.get_orders(&smart_account).is_empty());
+    assert!(am_client
+        .get_leveraged_position(&smart_account, &ctx.aquarius_router)
+        .is_none());
+    assert!(am_client.get_liquidation_auction(&smart_account).is_none());
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #11)")]
+fn test_accept_ownership_without_pending_transfer_fails() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    am_client.accept_account_ownership(&smart_account);
+}
+
+fn open_usdc_debt(ctx: &FullCycleTestContext, smart_account: &Address) {
+    let env = &ctx.env;
+    AccountManagerContractClient::new(env, &ctx.account_manager).execute_batch(
+        smart_account,
+        &soroban_sdk::vec![
+            env,
+            BatchCall::DepositCollateral(XLM_SYMBOL, U256::from_u128(env, 100u128 * WAD_U128))
+                .to_xdr(env),
+            BatchCall::Borrow(USDC_SYMBOL, U256::from_u128(env, 50u128 * WAD_U128)).to_xdr(env),
+        ],
+    );
+    // Borrowed USDC sits in the account
+    StellarAssetClient::new(env, &ctx.usdc).mint(smart_account, &1_000_000_000i128);
+}
+
+#[test]
+fn test_stop_loss_order_swaps_collateral_and_repays() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+    am_client.set_iscollateral_allowed(&USDC_SYMBOL);
+    allow_external_calls(
+        &ctx.env,
+        &ctx.account_manager,
+        &ctx.aquarius_router,
+        &[SmartAccExternalAction::Swap],
+        soroban_sdk::vec![&ctx.env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+
+    // Oracle prices XLM at 0.5, so a stop at 0.6 is already hit
+    let order_id = am_client.place_order(
+        &smart_account,
+        &OrderCondition::PriceBelow(XLM_SYMBOL, U256::from_u128(&ctx.env, 6 * WAD_U128 / 10)),
+        &OrderAction::SwapAndRepay(
+            ctx.aquarius_router.clone(),
+            XLM_SYMBOL,
+            USDC_SYMBOL,
+            U256::from_u128(&ctx.env, 90u128 * WAD_U128),
+        ),
+    );
+    assert!(am_client.is_order_triggerable(&smart_account, &order_id));
+
+    let keeper = Address::generate(&ctx.env);
+    am_client.trigger_order(&keeper, &smart_account, &order_id);
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    // 100 XLM swapped into 99.7 USDC, 0.1% keeper fee, 50 USDC repaid
+    assert!(!sa_client.get_all_collateral_tokens().contains(XLM_SYMBOL));
+    assert!(sa_client.get_all_borrowed_tokens().is_empty());
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&USDC_SYMBOL),
+        U256::from_u128(&ctx.env, 496_003u128 * WAD_U128 / 10_000)
+    );
+    assert_eq!(soroban_sdk::token::Client::new(&ctx.env, &ctx.usdc).balance(&keeper), 997_000);
+    assert!(am_client.get_orders(&smart_account).is_empty());
+}
+
+#[test]
+fn test_collateral_holders_follow_swaps_and_sweeps() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+    am_client.set_iscollateral_allowed(&USDC_SYMBOL);
+    allow_external_calls(
+        &ctx.env,
+        &ctx.account_manager,
+        &ctx.aquarius_router,
+        &[SmartAccExternalAction::Swap],
+        soroban_sdk::vec![&ctx.env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+    assert!(am_client.get_collateral_holders(&XLM_SYMBOL).contains(&smart_account));
+    assert!(!am_client.get_collateral_holders(&USDC_SYMBOL).contains(&smart_account));
+
+    let order_id = am_client.place_order(
+        &smart_account,
+        &OrderCondition::PriceBelow(XLM_SYMBOL, U256::from_u128(&ctx.env, 6 * WAD_U128 / 10)),
+        &OrderAction::SwapAndRepay(
+            ctx.aquarius_router.clone(),
+            XLM_SYMBOL,
+            USDC_SYMBOL,
+            U256::from_u128(&ctx.env, 90u128 * WAD_U128),
+        ),
+    );
+    am_client.trigger_order(&Address::generate(&ctx.env), &smart_account, &order_id);
+
+    // All XLM was swapped into USDC
+    assert!(!am_client.get_collateral_holders(&XLM_SYMBOL).contains(&smart_account));
+    assert!(am_client.get_collateral_holders(&USDC_SYMBOL).contains(&smart_account));
+
+    am_client.close_account(&smart_account);
+    assert!(!am_client.get_collateral_holders(&USDC_SYMBOL).contains(&smart_account));
+}
+
+#[test]
+fn test_stop_loss_order_swaps_on_soroswap() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+    am_client.set_iscollateral_allowed(&USDC_SYMBOL);
+    let soroswap_router = ctx.env.register(MockSoroswapRouter, ());
+    RegistryContractClient::new(&ctx.env, &ctx.registry).set_soroswap_router_address(&soroswap_router);
+    allow_external_calls(
+        &ctx.env,
+        &ctx.account_manager,
+        &soroswap_router,
+        &[SmartAccExternalAction::Swap],
+        soroban_sdk::vec![&ctx.env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+
+    let order_id = am_client.place_order(
+        &smart_account,
+        &OrderCondition::PriceBelow(XLM_SYMBOL, U256::from_u128(&ctx.env, 6 * WAD_U128 / 10)),
+        &OrderAction::SwapAndRepay(
+            soroswap_router,
+            XLM_SYMBOL,
+            USDC_SYMBOL,
+            U256::from_u128(&ctx.env, 90u128 * WAD_U128),
+        ),
+    );
+    // Only Soroswap is whitelisted for the pair, so the order is routed there
+    am_client.trigger_order(&Address::generate(&ctx.env), &smart_account, &order_id);
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert!(!sa_client.get_all_collateral_tokens().contains(XLM_SYMBOL));
+    assert!(sa_client.get_all_borrowed_tokens().is_empty());
+}
+
+#[test]
+fn test_order_cannot_trigger_before_condition_holds() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+
+    let order_id = am_client.place_order(
+        &smart_account,
+        &OrderCondition::PriceAbove(XLM_SYMBOL, U256::from_u128(&ctx.env, WAD_U128)),
+        &OrderAction::Deleverage(5_000),
+    );
+    assert!(!am_client.is_order_triggerable(&smart_account, &order_id));
+
+    let result = am_client.try_trigger_order(&Address::generate(&ctx.env), &smart_account, &order_id);
+    assert!(result.is_err());
+    assert_eq!(am_client.get_orders(&smart_account).len(), 1);
+
+    am_client.cancel_order(&smart_account, &order_id);
+    assert!(am_client.get_orders(&smart_account).is_empty());
+    assert!(am_client.try_cancel_order(&smart_account, &order_id).is_err());
+}
+
+#[test]
+fn test_health_order_deleverages_from_collateral() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+    am_client.set_iscollateral_allowed(&USDC_SYMBOL);
+    StellarAssetClient::new(&ctx.env, &ctx.usdc).mint(&ctx.user, &400_000_000i128);
+    am_client.deposit_collateral_tokens(
+        &smart_account,
+        &USDC_SYMBOL,
+        &U256::from_u128(&ctx.env, 40u128 * WAD_U128),
+    );
+    am_client.set_keeper_fee_bps(&100);
+
+    let order_id = am_client.place_order(
+        &smart_account,
+        &OrderCondition::HealthBelow(U256::from_u128(&ctx.env, 12 * WAD_U128 / 10)),
+        &OrderAction::Deleverage(5_000),
+    );
+    assert!(!am_client.is_order_triggerable(&smart_account, &order_id));
+
+    // Mock risk engine now reports a 1.05 health ratio
+    MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine).set_unhealthy(&true);
+    let keeper = Address::generate(&ctx.env);
+    am_client.trigger_order(&keeper, &smart_account, &order_id);
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    // Half of the 50 USDC debt repaid out of USDC collateral, plus a 1% fee on it
+    assert_eq!(
+        sa_client.get_borrowed_token_debt(&USDC_SYMBOL),
+        U256::from_u128(&ctx.env, 25u128 * WAD_U128)
+    );
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&USDC_SYMBOL),
+        U256::from_u128(&ctx.env, 1475u128 * WAD_U128 / 100)
+    );
+    assert_eq!(soroban_sdk::token::Client::new(&ctx.env, &ctx.usdc).balance(&keeper), 2_500_000);
+}
+
+#[test]
+fn test_health_order_deleverages_by_swapping_other_collateral() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+    am_client.set_keeper_fee_bps(&100);
+
+    let order_id = am_client.place_order(
+        &smart_account,
+        &OrderCondition::HealthBelow(U256::from_u128(&ctx.env, 12 * WAD_U128 / 10)),
+        &OrderAction::Deleverage(2_000),
+    );
+    MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine).set_unhealthy(&true);
+    let keeper = Address::generate(&ctx.env);
+
+    // No USDC is held and no swap is whitelisted, so the order stays on the book
+    assert_eq!(
+        am_client.try_trigger_order(&keeper, &smart_account, &order_id),
+        Err(Ok(AccountManagerError::NoSwappableCollateral))
+    );
+    assert_eq!(am_client.get_orders(&smart_account).len(), 1);
+
+    allow_external_calls(
+        &ctx.env,
+        &ctx.account_manager,
+        &ctx.aquarius_router,
+        &[SmartAccExternalAction::Swap],
+        soroban_sdk::vec![&ctx.env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+    am_client.trigger_order(&keeper, &smart_account, &order_id);
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    // 10 XLM buys 9.97 USDC; 1% goes to the keeper and the rest repays the debt
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&XLM_SYMBOL),
+        U256::from_u128(&ctx.env, 90u128 * WAD_U128)
+    );
+    assert_eq!(
+        sa_client.get_borrowed_token_debt(&USDC_SYMBOL),
+        U256::from_u128(&ctx.env, 401_297u128 * WAD_U128 / 10_000)
+    );
+    assert_eq!(soroban_sdk::token::Client::new(&ctx.env, &ctx.usdc).balance(&keeper), 997_000);
+    assert!(am_client.get_orders(&smart_account).is_empty());
+}
+
+#[test]
+fn test_deleverage_swaps_bounded_share_of_collateral_into_debt() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+    allow_external_calls(
+        &ctx.env,
+        &ctx.account_manager,
+        &ctx.aquarius_router,
+        &[SmartAccExternalAction::Swap],
+        soroban_sdk::vec![&ctx.env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+    MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine).set_in_deleverage_zone(&true);
+
+    // Anyone may call it; 20% of the 50 USDC debt is bought with 10 XLM at equal oracle prices
+    am_client.deleverage(&smart_account);
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&XLM_SYMBOL),
+        U256::from_u128(&ctx.env, 90u128 * WAD_U128)
+    );
+    assert_eq!(
+        sa_client.get_borrowed_token_debt(&USDC_SYMBOL),
+        U256::from_u128(&ctx.env, 4003u128 * WAD_U128 / 100)
+    );
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&USDC_SYMBOL),
+        U256::from_u128(&ctx.env, 0)
+    );
+}
+
+#[test]
+fn test_deleverage_uses_configured_share_on_soroswap() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+    let soroswap_router = ctx.env.register(MockSoroswapRouter, ());
+    RegistryContractClient::new(&ctx.env, &ctx.registry).set_soroswap_router_address(&soroswap_router);
+    allow_external_calls(
+        &ctx.env,
+        &ctx.account_manager,
+        &soroswap_router,
+        &[SmartAccExternalAction::Swap],
+        soroban_sdk::vec![&ctx.env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+    am_client.set_deleverage_config(&DeleverageConfig {
+        share_bps: 5_000,
+        max_slippage_bps: 50,
+    });
+    MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine).set_in_deleverage_zone(&true);
+
+    // Half of the 50 USDC debt is bought with 25 XLM on Soroswap at a 0.3% fee
+    am_client.deleverage(&smart_account);
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert_eq!(
+        sa_client.get_collateral_token_balance(&XLM_SYMBOL),
+        U256::from_u128(&ctx.env, 75u128 * WAD_U128)
+    );
+    assert_eq!(
+        sa_client.get_borrowed_token_debt(&USDC_SYMBOL),
+        U256::from_u128(&ctx.env, 25_075u128 * WAD_U128 / 1_000)
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #28)")]
+fn test_deleverage_config_rejects_zero_share() {
+    let ctx = setup_full_cycle();
+    AccountManagerContractClient::new(&ctx.env, &ctx.account_manager).set_deleverage_config(
+        &DeleverageConfig {
+            share_bps: 0,
+            max_slippage_bps: 100,
+        },
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #14)")]
+fn test_deleverage_rejected_outside_soft_zone() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+
+    am_client.deleverage(&smart_account);
 }
 
-fn build_aquarius_remove_liquidity_call(
+#[test]
+fn test_liquidate_removes_aquarius_liquidity_and_returns_collateral() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    am_client.execute_batch(&smart_account, &build_leverage_batch(&ctx, &smart_account));
+    // Borrowed USDC sits in the account and is handed back to the pool on liquidation
+    StellarAssetClient::new(&ctx.env, &ctx.usdc).mint(&smart_account, &500_000_000i128);
+
+    let lp_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    assert!(tracking_client.balance(&smart_account, &lp_symbol) > 0);
+
+    MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine).set_unhealthy(&true);
+    am_client.liquidate(&smart_account);
+
+    assert_eq!(tracking_client.balance(&smart_account, &lp_symbol), 0);
+    assert_eq!(
+        MockAquariusRouterClient::new(&ctx.env, &ctx.aquarius_router).get_lp_balance(&smart_account),
+        0
+    );
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert!(sa_client.get_all_collateral_tokens().is_empty());
+    assert!(sa_client.get_all_borrowed_tokens().is_empty());
+    // The XLM collateral was swept out of the account
+    let xlm_client = soroban_sdk::token::Client::new(&ctx.env, &ctx.xlm);
+    assert_eq!(xlm_client.balance(&smart_account), 0);
+}
+
+#[test]
+fn test_liquidation_auction_discount_grows_until_cap() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+    MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine).set_unhealthy(&true);
+
+    ctx.env.ledger().set_sequence_number(100);
+    am_client.start_liquidation_auction(&smart_account);
+    assert_eq!(am_client.get_auction_discount_bps(&smart_account), 0);
+
+    ctx.env.ledger().set_sequence_number(200);
+    assert_eq!(am_client.get_auction_discount_bps(&smart_account), 500);
+
+    ctx.env.ledger().set_sequence_number(10_000);
+    assert_eq!(am_client.get_auction_discount_bps(&smart_account), 1_500);
+}
+
+#[test]
+fn test_liquidation_auction_restarts_and_ends_on_liquidate() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+    MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine).set_unhealthy(&true);
+
+    ctx.env.ledger().set_sequence_number(100);
+    am_client.start_liquidation_auction(&smart_account);
+    ctx.env.ledger().set_sequence_number(10_000);
+    assert_eq!(am_client.get_auction_discount_bps(&smart_account), 1_500);
+
+    // A leftover auction starts over from zero discount
+    am_client.start_liquidation_auction(&smart_account);
+    assert_eq!(am_client.get_auction_discount_bps(&smart_account), 0);
+
+    am_client.liquidate(&smart_account);
+    assert!(am_client.get_liquidation_auction(&smart_account).is_none());
+}
+
+#[test]
+fn test_liquidation_auction_bid_repays_debt_for_discounted_collateral() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+    let risk_engine_client = MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine);
+    risk_engine_client.set_unhealthy(&true);
+
+    ctx.env.ledger().set_sequence_number(100);
+    am_client.start_liquidation_auction(&smart_account);
+    ctx.env.ledger().set_sequence_number(300);
+
+    let bidder = Address::generate(&ctx.env);
+    StellarAssetClient::new(&ctx.env, &ctx.usdc).mint(&bidder, &200_000_000i128);
+
+    // 20 USDC repaid at a 10% discount buys 20 / 0.9 XLM at equal oracle prices
+    let collateral_out = am_client.bid_on_liquidation_auction(
+        &bidder,
+        &smart_account,
+        &USDC_SYMBOL,
+        &U256::from_u128(&ctx.env, 20u128 * WAD_U128),
+        &XLM_SYMBOL,
+        &U256::from_u128(&ctx.env, 22u128 * WAD_U128),
+    );
+    assert_eq!(
+        collateral_out,
+        U256::from_u128(&ctx.env, 22_222_222_222_222_222_222)
+    );
+    assert_eq!(
+        soroban_sdk::token::Client::new(&ctx.env, &ctx.xlm).balance(&bidder),
+        222_222_222
+    );
+    assert_eq!(soroban_sdk::token::Client::new(&ctx.env, &ctx.usdc).balance(&bidder), 0);
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert_eq!(
+        sa_client.get_borrowed_token_debt(&USDC_SYMBOL),
+        U256::from_u128(&ctx.env, 30u128 * WAD_U128)
+    );
+    assert!(am_client.get_liquidation_auction(&smart_account).is_some());
+
+    risk_engine_client.set_unhealthy(&false);
+    am_client.end_liquidation_auction(&smart_account);
+    assert!(am_client.get_liquidation_auction(&smart_account).is_none());
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #15)")]
+fn test_liquidation_auction_bid_requires_running_auction() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+
+    am_client.bid_on_liquidation_auction(
+        &Address::generate(&ctx.env),
+        &smart_account,
+        &USDC_SYMBOL,
+        &U256::from_u128(&ctx.env, WAD_U128),
+        &XLM_SYMBOL,
+        &U256::from_u128(&ctx.env, 0),
+    );
+}
+
+#[test]
+fn test_liquidation_auction_rejects_healthy_account_and_tracking_collateral() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    open_usdc_debt(&ctx, &smart_account);
+
+    assert_eq!(
+        am_client.try_start_liquidation_auction(&smart_account),
+        Err(Ok(AccountManagerError::AccountHealthy))
+    );
+
+    MockRiskEngineClient::new(&ctx.env, &ctx.risk_engine).set_unhealthy(&true);
+    am_client.start_liquidation_auction(&smart_account);
+    assert_eq!(
+        am_client.try_bid_on_liquidation_auction(
+            &Address::generate(&ctx.env),
+            &smart_account,
+            &USDC_SYMBOL,
+            &U256::from_u128(&ctx.env, WAD_U128),
+            &Symbol::new(&ctx.env, AQUARIUS_XLM_USDC),
+            &U256::from_u128(&ctx.env, 0),
+        ),
+        Err(Ok(AccountManagerError::UnsupportedToken))
+    );
+}
+
+/// 100 XLM of collateral at 2x, borrowing USDC.
+fn leverage_request(env: &Env, min_liquidity_out: i128) -> OpenPositionRequest {
+    OpenPositionRequest {
+        collateral_symbol: XLM_SYMBOL,
+        collateral_amount_wad: U256::from_u128(env, 100u128 * WAD_U128),
+        borrow_symbol: USDC_SYMBOL,
+        target_leverage_wad: U256::from_u128(env, 2u128 * WAD_U128),
+        min_liquidity_out,
+    }
+}
+
+#[test]
+fn test_open_and_close_leveraged_position() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    // 100 XLM at 2x with XLM and USDC both priced at 0.5 USD -> borrow 100 USDC
+    let liquidity = am_client.open_leveraged_position(
+        &smart_account,
+        &ctx.aquarius_router,
+        &leverage_request(&ctx.env, 0i128),
+    );
+
+    let expected_lp = scale_wad_to_token(100u128 * WAD_U128, 7);
+    assert_eq!(liquidity, expected_lp);
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert_eq!(
+        sa_client.get_borrowed_token_debt(&USDC_SYMBOL),
+        U256::from_u128(&ctx.env, 100u128 * WAD_U128)
+    );
+    let position = am_client
+        .get_leveraged_position(&smart_account, &ctx.aquarius_router)
+        .unwrap();
+    assert_eq!(position.liquidity, expected_lp);
+
+    // The mock router does not move tokens, so fund the USDC the unwind would return
+    StellarAssetClient::new(&ctx.env, &ctx.usdc).mint(&smart_account, &1_000_000_000i128);
+
+    am_client.close_position(&smart_account, &ctx.aquarius_router, &Vec::new(&ctx.env));
+
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    assert_eq!(
+        tracking_client.balance(&smart_account, &Symbol::new(&ctx.env, AQUARIUS_XLM_USDC)),
+        0
+    );
+    assert!(!sa_client.get_all_borrowed_tokens().contains(USDC_SYMBOL));
+    assert!(am_client
+        .get_leveraged_position(&smart_account, &ctx.aquarius_router)
+        .is_none());
+}
+
+/// Replaces the mock risk engine with the real one, pricing everything at the mock oracle's 0.5.
+fn install_risk_engine(ctx: &FullCycleTestContext) -> RiskEngineContractClient<'_> {
+    let risk_engine = ctx.env.register(
+        RiskEngineContract,
+        (Address::generate(&ctx.env), ctx.registry.clone()),
+    );
+    RegistryContractClient::new(&ctx.env, &ctx.registry).set_risk_engine_address(&risk_engine);
+    RiskEngineContractClient::new(&ctx.env, &risk_engine)
+}
+
+#[test]
+fn test_open_leveraged_position_values_lp_with_risk_engine() {
+    let ctx = setup_full_cycle();
+    let risk_engine_client = install_risk_engine(&ctx);
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    am_client.open_leveraged_position(
+        &smart_account,
+        &ctx.aquarius_router,
+        &leverage_request(&ctx.env, 0i128),
+    );
+
+    // The account owns the whole pool: 100 XLM and 100 USDC at 0.5 USD, next to the
+    // 100 XLM collateral and 100 USDC of debt
+    let breakdown = risk_engine_client.get_account_breakdown(&smart_account);
+    let lp_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
+    let mut lp_value = U256::from_u128(&ctx.env, 0);
+    for asset in breakdown.assets.iter() {
+        if asset.symbol == lp_symbol {
+            lp_value = lp_value.add(&asset.value);
+        }
+    }
+    assert_eq!(lp_value, U256::from_u128(&ctx.env, 100u128 * WAD_U128));
+    assert_eq!(
+        breakdown.total_collateral_value,
+        U256::from_u128(&ctx.env, 150u128 * WAD_U128)
+    );
+    assert_eq!(
+        breakdown.total_debt_value,
+        U256::from_u128(&ctx.env, 50u128 * WAD_U128)
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #7)")]
+fn test_open_leveraged_position_respects_min_liquidity() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    am_client.open_leveraged_position(
+        &smart_account,
+        &ctx.aquarius_router,
+        &leverage_request(&ctx.env, (scale_wad_to_token(100u128 * WAD_U128, 7) + 1)),
+    );
+}
+
+#[test]
+fn test_open_leveraged_position_rejects_negative_min_liquidity() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    assert_eq!(
+        am_client.try_open_leveraged_position(
+            &smart_account,
+            &ctx.aquarius_router,
+            &leverage_request(&ctx.env, -1),
+        ),
+        Err(Ok(AccountManagerError::InvalidParameter))
+    );
+}
+
+#[test]
+fn test_close_position_removes_only_remaining_liquidity() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    let liquidity = am_client.open_leveraged_position(
+        &smart_account,
+        &ctx.aquarius_router,
+        &leverage_request(&ctx.env, 0),
+    );
+
+    // Part of the LP leaves the account outside of the position helpers
+    am_client.execute(
+        &smart_account,
+        &build_aquarius_remove_liquidity_call(
+            &ctx.env,
+            ctx.aquarius_router.clone(),
+            XLM_SYMBOL,
+            USDC_SYMBOL,
+            (liquidity / 2) as u128,
+            smart_account.clone(),
+        ),
+    );
+
+    StellarAssetClient::new(&ctx.env, &ctx.usdc).mint(&smart_account, &1_000_000_000i128);
+    am_client.close_position(&smart_account, &ctx.aquarius_router, &Vec::new(&ctx.env));
+
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    assert_eq!(
+        tracking_client.balance(&smart_account, &Symbol::new(&ctx.env, AQUARIUS_XLM_USDC)),
+        0
+    );
+    assert!(am_client
+        .get_leveraged_position(&smart_account, &ctx.aquarius_router)
+        .is_none());
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #7)")]
+fn test_close_position_respects_min_amounts_out() {
+    let ctx = setup_full_cycle();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    am_client.open_leveraged_position(
+        &smart_account,
+        &ctx.aquarius_router,
+        &leverage_request(&ctx.env, 0i128),
+    );
+
+    // Nothing comes back from the mock router, so any positive bound must fail
+    am_client.close_position(
+        &smart_account,
+        &ctx.aquarius_router,
+        &soroban_sdk::vec![&ctx.env, U256::from_u128(&ctx.env, WAD_U128)],
+    );
+}
+
+// ============================================================================
+// Soroswap Protocol Integration Tests
+// ============================================================================
+
+const SOROSWAP_XLM_USDC: &str = "SS_XLM_USDC";
+
+#[derive(Clone)]
+#[contracttype]
+enum MockSoroswapKey {
+    LPBalance(Address),
+    Token0,
+    Reserves,
+    TotalSupply,
+}
+
+#[contract]
+pub struct MockSoroswapRouter;
+
+#[contractimpl]
+impl MockSoroswapRouter {
+    pub fn add_liquidity(
+        env: Env,
+        token_a: Address,
+        token_b: Address,
+        amount_a_desired: i128,
+        amount_b_desired: i128,
+        _amount_a_min: i128,
+        _amount_b_min: i128,
+        to: Address,
+        deadline: u64,
+    ) -> (i128, i128, i128) {
+        if deadline < env.ledger().timestamp() {
+            panic!("Deadline expired");
+        }
+        // Simple mock: LP tokens = average of deposited amounts
+        let liquidity = (amount_a_desired + amount_b_desired) / 2;
+        let current_lp = Self::get_lp_balance(env.clone(), to.clone());
+        env.storage()
+            .persistent()
+            .set(&MockSoroswapKey::LPBalance(to), &(current_lp + liquidity));
+
+        // The router doubles as the single pair it manages, sorted like Soroswap pairs
+        let (reserve0, reserve1) = Self::get_reserves(env.clone());
+        let (token0, amount0, amount1) = if token_a < token_b {
+            (token_a, amount_a_desired, amount_b_desired)
+        } else {
+            (token_b, amount_b_desired, amount_a_desired)
+        };
+        env.storage().persistent().set(&MockSoroswapKey::Token0, &token0);
+        env.storage()
+            .persistent()
+            .set(&MockSoroswapKey::Reserves, &(reserve0 + amount0, reserve1 + amount1));
+        env.storage().persistent().set(
+            &MockSoroswapKey::TotalSupply,
+            &(Self::total_supply(env.clone()) + liquidity),
+        );
+
+        (amount_a_desired, amount_b_desired, liquidity)
+    }
+
+    pub fn remove_liquidity(
+        env: Env,
+        token_a: Address,
+        _token_b: Address,
+        liquidity: i128,
+        amount_a_min: i128,
+        amount_b_min: i128,
+        to: Address,
+        _deadline: u64,
+    ) -> (i128, i128) {
+        let current_lp = Self::get_lp_balance(env.clone(), to.clone());
+        if liquidity > current_lp {
+            panic!("Insufficient LP tokens");
+        }
+        env.storage()
+            .persistent()
+            .set(&MockSoroswapKey::LPBalance(to), &(current_lp - liquidity));
+
+        let (reserve0, reserve1) = Self::get_reserves(env.clone());
+        let total_supply = Self::total_supply(env.clone());
+        let amount0 = reserve0 * liquidity / total_supply;
+        let amount1 = reserve1 * liquidity / total_supply;
+        env.storage()
+            .persistent()
+            .set(&MockSoroswapKey::Reserves, &(reserve0 - amount0, reserve1 - amount1));
+        env.storage()
+            .persistent()
+            .set(&MockSoroswapKey::TotalSupply, &(total_supply - liquidity));
+
+        let (amount_a, amount_b) = if token_a == Self::token_0(env) {
+            (amount0, amount1)
+        } else {
+            (amount1, amount0)
+        };
+        if amount_a < amount_a_min || amount_b < amount_b_min {
+            panic!("Insufficient output amount");
+        }
+        (amount_a, amount_b)
+    }
+
+    pub fn router_pair_for(env: Env, _token_a: Address, _token_b: Address) -> Address {
+        env.current_contract_address()
+    }
+
+    pub fn token_0(env: Env) -> Address {
+        env.storage()
+            .persistent()
+            .get(&MockSoroswapKey::Token0)
+            .unwrap_or(env.current_contract_address())
+    }
+
+    pub fn get_reserves(env: Env) -> (i128, i128) {
+        env.storage()
+            .persistent()
+            .get(&MockSoroswapKey::Reserves)
+            .unwrap_or((0i128, 0i128))
+    }
+
+    pub fn total_supply(env: Env) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&MockSoroswapKey::TotalSupply)
+            .unwrap_or(0i128)
+    }
+
+    pub fn swap_exact_tokens_for_tokens(
+        env: Env,
+        amount_in: i128,
+        amount_out_min: i128,
+        path: Vec<Address>,
+        _to: Address,
+        _deadline: u64,
+    ) -> Vec<i128> {
+        if path.len() != 2 {
+            panic!("Mock router only supports direct pairs");
+        }
+        // Simple mock: 1:1 swap with 0.3% fee
+        let amount_out = (amount_in * 997) / 1000;
+        if amount_out < amount_out_min {
+            panic!("Insufficient output amount");
+        }
+        soroban_sdk::vec![&env, amount_in, amount_out]
+    }
+
+    // The router doubles as the pair, whose LP token has 7 decimals
+    pub fn decimals(_env: Env) -> u32 {
+        7
+    }
+
+    pub fn get_lp_balance(env: Env, owner: Address) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&MockSoroswapKey::LPBalance(owner))
+            .unwrap_or(0i128)
+    }
+}
+
+fn build_soroswap_call(
     env: &Env,
     router: Address,
+    action: SmartAccExternalAction,
     token0: Symbol,
     token1: Symbol,
-    lp_amount: u128,
+    amounts: Vec<U256>,
     smart_account: Address,
 ) -> Bytes {
-    let mut tokens_out = Vec::new(env);
-    tokens_out.push_back(token0);
-    tokens_out.push_back(token1);
-
-    let mut amount_out = Vec::new(env);
-    amount_out.push_back(U256::from_u128(env, lp_amount));
-
-    let call = ExternalProtocolCall {
-        protocol_address: router,
-        type_action: SmartAccExternalAction::RemoveLiquidity,
-        tokens_out,
-        tokens_in: Vec::new(env),
-        amount_out,
-        amount_in: Vec::new(env),
-        is_token_pair: true,
-        token_pair_ratio: 0,
-        margin_account: smart_account,
-        fee_fraction: 30u32,
-        min_liquidity_out: U256::from_u128(env, 0),
-    };
-
-    call.to_xdr(env)
+    soroswap_call(env, router, action, token0, token1, amounts, smart_account).to_xdr(env)
 }
 
-fn build_aquarius_swap_call(
+fn soroswap_call(
     env: &Env,
     router: Address,
-    token_in: Symbol,
-    token_out: Symbol,
-    amount_in_wad: u128,
+    action: SmartAccExternalAction,
+    token0: Symbol,
+    token1: Symbol,
+    amounts: Vec<U256>,
     smart_account: Address,
-) -> Bytes {
+) -> ExternalProtocolCall {
     let mut tokens_out = Vec::new(env);
-    tokens_out.push_back(token_in);
-    tokens_out.push_back(token_out);
-
-    let mut amount_out = Vec::new(env);
-    amount_out.push_back(U256::from_u128(env, amount_in_wad));
+    tokens_out.push_back(token0);
+    tokens_out.push_back(token1);
 
-    let call = ExternalProtocolCall {
+    ExternalProtocolCall {
         protocol_address: router,
-        type_action: SmartAccExternalAction::Swap,
+        type_action: action,
         tokens_out,
         tokens_in: Vec::new(env),
-        amount_out,
+        amount_out: amounts,
         amount_in: Vec::new(env),
-        is_token_pair: false,
+        is_token_pair: true,
         token_pair_ratio: 0,
         margin_account: smart_account,
         fee_fraction: 30u32,
         min_liquidity_out: U256::from_u128(env, 0),
-    };
-
-    call.to_xdr(env)
+        min_amounts_out: Vec::new(env),
+    }
 }
 
-struct AquariusTestContext {
+struct SoroswapTestContext {
     env: Env,
     admin: Address,
     user: Address,
     registry: Address,
     account_manager: Address,
+    soroswap_router: Address,
     aquarius_router: Address,
     tracking_token: Address,
-    xlm: Address,
-    usdc: Address,
 }
 
-fn setup_aquarius() -> AquariusTestContext {
+fn setup_soroswap() -> SoroswapTestContext {
     let env = Env::default();
     env.mock_all_auths();
 
@@ -658,11 +3270,12 @@ fn setup_aquarius() -> AquariusTestContext {
     let xlm_token = env.register_stellar_asset_contract_v2(admin.clone());
     let usdc_token = env.register_stellar_asset_contract_v2(admin.clone());
 
+    let soroswap_router = env.register(MockSoroswapRouter, ());
+
+    // Aquarius is configured too, so routing must be decided by the target address
     let aquarius_router = env.register(MockAquariusRouter, ());
     let aquarius_router_client = MockAquariusRouterClient::new(&env, &aquarius_router);
     aquarius_router_client.init(&admin);
-
-    // Init pool
     let mut tokens = Vec::new(&env);
     tokens.push_back(xlm_token.address());
     tokens.push_back(usdc_token.address());
@@ -670,7 +3283,12 @@ fn setup_aquarius() -> AquariusTestContext {
 
     let tracking_token = env.register(TrackingToken, ());
     let tracking_client = TrackingTokenClient::new(&env, &tracking_token);
-
+    tracking_client.initialize(
+        &account_manager,
+        &Symbol::new(&env, SOROSWAP_XLM_USDC),
+        &7u32,
+        &String::from_str(&env, "Soroswap XLM-USDC LP"),
+    );
     tracking_client.initialize(
         &account_manager,
         &Symbol::new(&env, AQUARIUS_XLM_USDC),
@@ -683,312 +3301,500 @@ fn setup_aquarius() -> AquariusTestContext {
     registry_client.set_smart_account_hash(&smart_hash);
     registry_client.set_native_xlm_contract_address(&xlm_token.address());
     registry_client.set_native_usdc_contract_address(&usdc_token.address());
+    registry_client.set_soroswap_router_address(&soroswap_router);
     registry_client.set_aquarius_router_address(&aquarius_router);
     registry_client.set_aquarius_pool_index(&pool_index);
     registry_client.set_tracking_token_contract_addr(&tracking_token);
     registry_client.set_accountmanager_contract(&account_manager);
+    registry_client.set_risk_engine_address(&env.register(MockRiskEngine, ()));
+    allow_external_calls(
+        &env,
+        &account_manager,
+        &soroswap_router,
+        &[
+            SmartAccExternalAction::AddLiquidity,
+            SmartAccExternalAction::RemoveLiquidity,
+            SmartAccExternalAction::Swap,
+        ],
+        soroban_sdk::vec![&env, XLM_SYMBOL, USDC_SYMBOL],
+    );
 
-    AquariusTestContext {
+    SoroswapTestContext {
         env,
         admin,
         user,
         registry,
         account_manager,
+        soroswap_router,
         aquarius_router,
         tracking_token,
-        xlm: xlm_token.address(),
-        usdc: usdc_token.address(),
     }
 }
 
 #[test]
-fn test_aquarius_add_liquidity_mints_lp_tracking_tokens() {
-    let ctx = setup_aquarius();
-
-    let account_manager_client =
-        AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
-    let smart_account = account_manager_client.create_account(&ctx.user);
-
-    // Add liquidity: 1000 XLM + 1000 USDC
-    let xlm_amount_wad = 1000u128 * WAD_U128;
-    let usdc_amount_wad = 1000u128 * WAD_U128;
+fn test_soroswap_add_and_remove_liquidity_tracks_lp_tokens() {
+    let ctx = setup_soroswap();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
 
-    let call_bytes = build_aquarius_add_liquidity_call(
+    let add_call = build_soroswap_call(
         &ctx.env,
-        ctx.aquarius_router.clone(),
+        ctx.soroswap_router.clone(),
+        SmartAccExternalAction::AddLiquidity,
         XLM_SYMBOL,
         USDC_SYMBOL,
-        xlm_amount_wad,
-        usdc_amount_wad,
+        soroban_sdk::vec![
+            &ctx.env,
+            U256::from_u128(&ctx.env, 300u128 * WAD_U128),
+            U256::from_u128(&ctx.env, 100u128 * WAD_U128)
+        ],
         smart_account.clone(),
     );
+    am_client.execute(&smart_account, &add_call);
 
-    account_manager_client.execute(&smart_account, &call_bytes);
+    // Mock LP formula: (scaled_xlm + scaled_usdc) / 2
+    let expected_lp = (scale_wad_to_token(300u128 * WAD_U128, 7)
+        + scale_wad_to_token(100u128 * WAD_U128, 7))
+        / 2;
 
-    // Verify LP tracking tokens were minted
     let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
-    let lp_tracking_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
-    let lp_balance = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+    let lp_symbol = Symbol::new(&ctx.env, SOROSWAP_XLM_USDC);
+    assert_eq!(tracking_client.balance(&smart_account, &lp_symbol), expected_lp);
 
-    assert!(lp_balance > 0);
-    // Mock returns average of token amounts: (1000*10^7 + 1000*10^7) / 2 = 10^10
-    assert_eq!(lp_balance, 10_000_000_000); 
-}
-
-#[test]
-fn test_aquarius_remove_liquidity_burns_lp_tracking_tokens() {
-    let ctx = setup_aquarius();
+    let router_client = MockSoroswapRouterClient::new(&ctx.env, &ctx.soroswap_router);
+    assert_eq!(router_client.get_lp_balance(&smart_account), expected_lp);
 
-    let account_manager_client =
-        AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
-    let smart_account = account_manager_client.create_account(&ctx.user);
+    // Aquarius must not have been touched even though the action is shared
+    let aquarius_client = MockAquariusRouterClient::new(&ctx.env, &ctx.aquarius_router);
+    assert_eq!(aquarius_client.get_lp_balance(&smart_account), 0);
+    assert_eq!(
+        tracking_client.balance(&smart_account, &Symbol::new(&ctx.env, AQUARIUS_XLM_USDC)),
+        0
+    );
 
-    // Add liquidity first
-    let xlm_amount_wad = 2000u128 * WAD_U128;
-    let usdc_amount_wad = 2000u128 * WAD_U128;
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
+        &smart_account,
+    );
+    assert!(sa_client.get_all_collateral_tokens().contains(lp_symbol.clone()));
 
-    let add_call = build_aquarius_add_liquidity_call(
+    let remove_amount = expected_lp / 4;
+    let remove_call = build_soroswap_call(
         &ctx.env,
-        ctx.aquarius_router.clone(),
+        ctx.soroswap_router.clone(),
+        SmartAccExternalAction::RemoveLiquidity,
         XLM_SYMBOL,
         USDC_SYMBOL,
-        xlm_amount_wad,
-        usdc_amount_wad,
+        soroban_sdk::vec![&ctx.env, U256::from_u128(&ctx.env, scale_token_to_wad(remove_amount, 7))],
         smart_account.clone(),
     );
+    am_client.execute(&smart_account, &remove_call);
 
-    account_manager_client.execute(&smart_account, &add_call);
+    assert_eq!(
+        tracking_client.balance(&smart_account, &lp_symbol),
+        expected_lp - remove_amount
+    );
+    assert_eq!(
+        router_client.get_lp_balance(&smart_account),
+        expected_lp - remove_amount
+    );
+}
 
-    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
-    let lp_tracking_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
-    let initial_lp = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+#[test]
+fn test_liquidate_removes_soroswap_liquidity() {
+    let ctx = setup_soroswap();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    am_client.execute(
+        &smart_account,
+        &build_soroswap_call(
+            &ctx.env,
+            ctx.soroswap_router.clone(),
+            SmartAccExternalAction::AddLiquidity,
+            XLM_SYMBOL,
+            USDC_SYMBOL,
+            soroban_sdk::vec![
+                &ctx.env,
+                U256::from_u128(&ctx.env, 300u128 * WAD_U128),
+                U256::from_u128(&ctx.env, 100u128 * WAD_U128)
+            ],
+            smart_account.clone(),
+        ),
+    );
 
-    // Remove half the liquidity
-    let remove_amount = initial_lp / 2;
-    let remove_call = build_aquarius_remove_liquidity_call(
-        &ctx.env,
-        ctx.aquarius_router.clone(),
-        XLM_SYMBOL,
-        USDC_SYMBOL,
-        remove_amount as u128,
-        smart_account.clone(),
+    let risk_engine = ctx.env.register(MockRiskEngine, ());
+    MockRiskEngineClient::new(&ctx.env, &risk_engine).set_unhealthy(&true);
+    RegistryContractClient::new(&ctx.env, &ctx.registry).set_risk_engine_address(&risk_engine);
+    am_client.liquidate(&smart_account);
+
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    assert_eq!(
+        tracking_client.balance(&smart_account, &Symbol::new(&ctx.env, SOROSWAP_XLM_USDC)),
+        0
     );
+    let router_client = MockSoroswapRouterClient::new(&ctx.env, &ctx.soroswap_router);
+    assert_eq!(router_client.get_lp_balance(&smart_account), 0);
+}
 
-    account_manager_client.execute(&smart_account, &remove_call);
+#[contract]
+pub struct MockPriceOracle;
 
-    // Verify LP tracking tokens were burned
-    let final_lp = tracking_client.balance(&smart_account, &lp_tracking_symbol);
-    assert_eq!(final_lp, initial_lp - remove_amount);
+#[contractimpl]
+impl MockPriceOracle {
+    pub fn get_price_latest(_env: Env, _symbol: Symbol) -> (u128, u32) {
+        // 0.5 USD with 7 decimals
+        (5_000_000, 7)
+    }
 }
 
 #[test]
-fn test_aquarius_full_flow_add_swap_remove() {
-    let ctx = setup_aquarius();
-
-    let account_manager_client =
-        AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
-    let smart_account = account_manager_client.create_account(&ctx.user);
+fn test_risk_engine_values_lp_positions_at_pool_share() {
+    let ctx = setup_soroswap();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
 
-    // Step 1: Add liquidity
-    let xlm_amount_wad = 5000u128 * WAD_U128;
-    let usdc_amount_wad = 5000u128 * WAD_U128;
+    let registry_client = RegistryContractClient::new(&ctx.env, &ctx.registry);
+    registry_client.set_oracle_contract_address(&ctx.env.register(MockPriceOracle, ()));
+    let risk_engine = ctx.env.register(
+        RiskEngineContract,
+        (ctx.admin.clone(), ctx.registry.clone()),
+    );
+    let risk_engine_client = RiskEngineContractClient::new(&ctx.env, &risk_engine);
 
-    let add_call = build_aquarius_add_liquidity_call(
+    let add_soroswap_call = build_soroswap_call(
+        &ctx.env,
+        ctx.soroswap_router.clone(),
+        SmartAccExternalAction::AddLiquidity,
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        soroban_sdk::vec![
+            &ctx.env,
+            U256::from_u128(&ctx.env, 300u128 * WAD_U128),
+            U256::from_u128(&ctx.env, 100u128 * WAD_U128)
+        ],
+        smart_account.clone(),
+    );
+    am_client.execute(&smart_account, &add_soroswap_call);
+    allow_external_calls(
+        &ctx.env,
+        &ctx.account_manager,
+        &ctx.aquarius_router,
+        &[SmartAccExternalAction::AddLiquidity],
+        soroban_sdk::vec![&ctx.env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+    let add_aquarius_call = build_aquarius_add_liquidity_call(
         &ctx.env,
         ctx.aquarius_router.clone(),
         XLM_SYMBOL,
         USDC_SYMBOL,
-        xlm_amount_wad,
-        usdc_amount_wad,
+        40u128 * WAD_U128,
+        40u128 * WAD_U128,
         smart_account.clone(),
     );
+    am_client.execute(&smart_account, &add_aquarius_call);
 
-    account_manager_client.execute(&smart_account, &add_call);
+    // The account owns both pools outright: 340 XLM and 140 USDC, all at 0.5 USD
+    assert_eq!(
+        risk_engine_client.get_current_total_balance(&smart_account),
+        U256::from_u128(&ctx.env, 240u128 * WAD_U128)
+    );
 
+    // Burning a quarter of the Soroswap LP leaves 225 XLM and 75 USDC in that pool
     let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
-    let lp_tracking_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
-    let lp_after_add = tracking_client.balance(&smart_account, &lp_tracking_symbol);
-    assert!(lp_after_add > 0);
-
-    // Step 2: Execute a swap (shouldn't affect LP tracking)
-    let swap_call = build_aquarius_swap_call(
+    let lp_symbol = Symbol::new(&ctx.env, SOROSWAP_XLM_USDC);
+    let remove_amount = tracking_client.balance(&smart_account, &lp_symbol) / 4;
+    let remove_call = build_soroswap_call(
         &ctx.env,
-        ctx.aquarius_router.clone(),
+        ctx.soroswap_router.clone(),
+        SmartAccExternalAction::RemoveLiquidity,
         XLM_SYMBOL,
         USDC_SYMBOL,
-        1000u128 * WAD_U128,
+        soroban_sdk::vec![&ctx.env, U256::from_u128(&ctx.env, scale_token_to_wad(remove_amount, 7))],
         smart_account.clone(),
     );
+    am_client.execute(&smart_account, &remove_call);
 
-    account_manager_client.execute(&smart_account, &swap_call);
+    assert_eq!(
+        risk_engine_client.get_current_total_balance(&smart_account),
+        U256::from_u128(&ctx.env, 190u128 * WAD_U128)
+    );
 
-    let lp_after_swap = tracking_client.balance(&smart_account, &lp_tracking_symbol);
-    assert_eq!(lp_after_swap, lp_after_add); // LP balance unchanged after swap
+    // Each router's exposure is its own pool share
+    assert_eq!(
+        risk_engine_client.get_protocol_exposure(&smart_account, &ctx.soroswap_router),
+        U256::from_u128(&ctx.env, 150u128 * WAD_U128)
+    );
+    assert_eq!(
+        risk_engine_client.get_protocol_exposure(&smart_account, &ctx.aquarius_router),
+        U256::from_u128(&ctx.env, 40u128 * WAD_U128)
+    );
 
-    // Step 3: Remove all liquidity
-    let remove_call = build_aquarius_remove_liquidity_call(
+    // With USDC ramped out only the 265 XLM across both pools still counts
+    risk_engine_client.set_collateral_factor_ramp(&USDC_SYMBOL, &0, &0);
+    assert_eq!(
+        risk_engine_client.get_current_total_balance(&smart_account),
+        U256::from_u128(&ctx.env, 1325u128 * WAD_U128 / 10)
+    );
+}
+
+#[test]
+fn test_soroswap_swap_does_not_change_lp_tracking() {
+    let ctx = setup_soroswap();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    let swap_call = build_soroswap_call(
         &ctx.env,
-        ctx.aquarius_router.clone(),
+        ctx.soroswap_router.clone(),
+        SmartAccExternalAction::Swap,
         XLM_SYMBOL,
         USDC_SYMBOL,
-        lp_after_add as u128,
+        soroban_sdk::vec![&ctx.env, U256::from_u128(&ctx.env, 50u128 * WAD_U128)],
         smart_account.clone(),
     );
+    am_client.execute(&smart_account, &swap_call);
 
-    account_manager_client.execute(&smart_account, &remove_call);
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let lp_symbol = Symbol::new(&ctx.env, SOROSWAP_XLM_USDC);
+    assert_eq!(tracking_client.balance(&smart_account, &lp_symbol), 0);
+}
 
-    let lp_final = tracking_client.balance(&smart_account, &lp_tracking_symbol);
-    assert_eq!(lp_final, 0);
+#[test]
+fn test_soroswap_swap_respects_min_amount_out() {
+    let ctx = setup_soroswap();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+
+    // The mock router returns 49.85 USDC for 50 XLM
+    let mut swap_call = soroswap_call(
+        &ctx.env,
+        ctx.soroswap_router.clone(),
+        SmartAccExternalAction::Swap,
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        soroban_sdk::vec![&ctx.env, U256::from_u128(&ctx.env, 50u128 * WAD_U128)],
+        smart_account.clone(),
+    );
+    swap_call.min_amounts_out =
+        soroban_sdk::vec![&ctx.env, U256::from_u128(&ctx.env, 50u128 * WAD_U128)];
+    assert!(am_client
+        .try_execute(&smart_account, &swap_call.clone().to_xdr(&ctx.env))
+        .is_err());
+
+    swap_call.min_amounts_out =
+        soroban_sdk::vec![&ctx.env, U256::from_u128(&ctx.env, 49u128 * WAD_U128)];
+    am_client.execute(&smart_account, &swap_call.to_xdr(&ctx.env));
 }
 
 #[test]
-fn test_aquarius_single_token_to_liquidity_flow() {
-    let ctx = setup_aquarius();
+fn test_soroswap_liquidity_respects_min_amounts() {
+    let ctx = setup_soroswap();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
 
-    let account_manager_client =
-        AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
-    let smart_account = account_manager_client.create_account(&ctx.user);
+    // Mock LP formula: (300 + 100) / 2 = 200 LP
+    let mut add_call = soroswap_call(
+        &ctx.env,
+        ctx.soroswap_router.clone(),
+        SmartAccExternalAction::AddLiquidity,
+        XLM_SYMBOL,
+        USDC_SYMBOL,
+        soroban_sdk::vec![
+            &ctx.env,
+            U256::from_u128(&ctx.env, 300u128 * WAD_U128),
+            U256::from_u128(&ctx.env, 100u128 * WAD_U128)
+        ],
+        smart_account.clone(),
+    );
+    add_call.min_liquidity_out = U256::from_u128(&ctx.env, 201u128 * WAD_U128);
+    assert!(am_client
+        .try_execute(&smart_account, &add_call.clone().to_xdr(&ctx.env))
+        .is_err());
 
-    // User starts with only XLM (realistic scenario)
-    let initial_xlm_wad = 10000u128 * WAD_U128;
+    add_call.min_liquidity_out = U256::from_u128(&ctx.env, 200u128 * WAD_U128);
+    am_client.execute(&smart_account, &add_call.to_xdr(&ctx.env));
 
-    // Step 1: User swaps half of their XLM to get USDC
-    let xlm_to_swap_wad = initial_xlm_wad / 2; // Swap 50% of XLM
-    let swap_call = build_aquarius_swap_call(
+    // Burning half of the LP returns 150 XLM and 50 USDC
+    let mut remove_call = soroswap_call(
         &ctx.env,
-        ctx.aquarius_router.clone(),
+        ctx.soroswap_router.clone(),
+        SmartAccExternalAction::RemoveLiquidity,
         XLM_SYMBOL,
         USDC_SYMBOL,
-        xlm_to_swap_wad,
+        soroban_sdk::vec![&ctx.env, U256::from_u128(&ctx.env, 100u128 * WAD_U128)],
         smart_account.clone(),
     );
+    remove_call.min_amounts_out = soroban_sdk::vec![
+        &ctx.env,
+        U256::from_u128(&ctx.env, 150u128 * WAD_U128),
+        U256::from_u128(&ctx.env, 51u128 * WAD_U128)
+    ];
+    assert!(am_client
+        .try_execute(&smart_account, &remove_call.clone().to_xdr(&ctx.env))
+        .is_err());
+
+    remove_call.min_amounts_out = soroban_sdk::vec![
+        &ctx.env,
+        U256::from_u128(&ctx.env, 150u128 * WAD_U128),
+        U256::from_u128(&ctx.env, 50u128 * WAD_U128)
+    ];
+    am_client.execute(&smart_account, &remove_call.to_xdr(&ctx.env));
 
-    account_manager_client.execute(&smart_account, &swap_call);
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let lp_symbol = Symbol::new(&ctx.env, SOROSWAP_XLM_USDC);
+    assert_eq!(
+        tracking_client.balance(&smart_account, &lp_symbol),
+        scale_wad_to_token(100u128 * WAD_U128, 7)
+    );
+}
 
-    // Step 2: Now user has both XLM and USDC, add liquidity
-    let remaining_xlm_wad = initial_xlm_wad - xlm_to_swap_wad;
-    // Assuming 1:1 swap ratio for simplicity (in real scenario would query pool)
-    let received_usdc_wad = xlm_to_swap_wad;
+#[test]
+fn test_liquidity_call_to_unknown_venue_is_rejected() {
+    let ctx = setup_soroswap();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
 
-    let add_liquidity_call = build_aquarius_add_liquidity_call(
+    let unknown_venue = Address::generate(&ctx.env);
+    allow_external_calls(
         &ctx.env,
-        ctx.aquarius_router.clone(),
+        &ctx.account_manager,
+        &unknown_venue,
+        &[SmartAccExternalAction::AddLiquidity],
+        soroban_sdk::vec![&ctx.env, XLM_SYMBOL, USDC_SYMBOL],
+    );
+    let add_call = build_soroswap_call(
+        &ctx.env,
+        unknown_venue,
+        SmartAccExternalAction::AddLiquidity,
         XLM_SYMBOL,
-        USDC_SYMBOL,
-        remaining_xlm_wad,
-        received_usdc_wad,
+        USDC_SYMBOL,
+        soroban_sdk::vec![
+            &ctx.env,
+            U256::from_u128(&ctx.env, 10u128 * WAD_U128),
+            U256::from_u128(&ctx.env, 10u128 * WAD_U128)
+        ],
         smart_account.clone(),
     );
+    assert!(am_client.try_execute(&smart_account, &add_call).is_err());
 
-    account_manager_client.execute(&smart_account, &add_liquidity_call);
-
-    // Verify LP tokens were minted and tracked
-    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
-    let lp_tracking_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
-    let lp_balance = tracking_client.balance(&smart_account, &lp_tracking_symbol);
+    // Liquidity calls are routed by address, never to Aquarius by default
+    let aquarius_client = MockAquariusRouterClient::new(&ctx.env, &ctx.aquarius_router);
+    assert_eq!(aquarius_client.get_lp_balance(&smart_account), 0);
+}
 
-    assert!(lp_balance > 0, "LP tracking tokens should be minted");
+#[test]
+#[should_panic]
+fn test_soroswap_rejects_blend_only_action() {
+    let ctx = setup_soroswap();
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
 
-    // Step 3: Remove liquidity to complete the flow
-    let remove_call = build_aquarius_remove_liquidity_call(
+    let deposit_call = build_soroswap_call(
         &ctx.env,
-        ctx.aquarius_router.clone(),
+        ctx.soroswap_router.clone(),
+        SmartAccExternalAction::Deposit,
         XLM_SYMBOL,
         USDC_SYMBOL,
-        lp_balance as u128,
+        soroban_sdk::vec![&ctx.env, U256::from_u128(&ctx.env, WAD_U128)],
         smart_account.clone(),
     );
-
-    account_manager_client.execute(&smart_account, &remove_call);
-
-    let final_lp_balance = tracking_client.balance(&smart_account, &lp_tracking_symbol);
-    assert_eq!(final_lp_balance, 0, "All LP tokens should be burned");
+    am_client.execute(&smart_account, &deposit_call);
 }
 
 // ============================================================================
-// Full Cycle: Create Account → Deposit Collateral → Borrow → Open Aquarius Position
+// Protocol Adapter Integration Tests
 // ============================================================================
 
+const VAULT_XLM: &str = "VAULT_XLM";
+
 #[derive(Clone)]
 #[contracttype]
-enum MockLendingPoolKey {
-    BorrowBalance(Address),
+enum MockAdapterKey {
+    Token,
+    ReportedSymbol,
+    Shares(Address),
 }
 
+/// Single-asset vault adapter: custodies XLM for each smart account and reports
+/// the position 1:1 in `ReportedSymbol` tracking tokens.
 #[contract]
-pub struct MockLendingPool;
+pub struct MockVaultAdapter;
 
 #[contractimpl]
-impl MockLendingPool {
-    pub fn lend_to(env: Env, borrower: Address, amount_wad: U256) -> bool {
-        let current: U256 = env
-            .storage()
-            .persistent()
-            .get(&MockLendingPoolKey::BorrowBalance(borrower.clone()))
-            .unwrap_or(U256::from_u128(&env, 0));
-        env.storage()
-            .persistent()
-            .set(&MockLendingPoolKey::BorrowBalance(borrower), &current.add(&amount_wad));
-        true
-    }
-
-    pub fn get_borrow_balance(env: Env, borrower: Address) -> U256 {
-        env.storage()
-            .persistent()
-            .get(&MockLendingPoolKey::BorrowBalance(borrower))
-            .unwrap_or(U256::from_u128(&env, 0))
-    }
-
-    pub fn get_user_borrow_shares(env: Env, borrower: Address) -> U256 {
+impl MockVaultAdapter {
+    pub fn init(env: Env, token: Address, reported_symbol: Symbol) {
+        env.storage().persistent().set(&MockAdapterKey::Token, &token);
         env.storage()
             .persistent()
-            .get(&MockLendingPoolKey::BorrowBalance(borrower))
-            .unwrap_or(U256::from_u128(&env, 0))
+            .set(&MockAdapterKey::ReportedSymbol, &reported_symbol);
     }
 
-    pub fn collect_from(env: Env, amount_wad: U256, borrower: Address) -> bool {
-        let current: U256 = env
+    pub fn execute(
+        env: Env,
+        smart_account: Address,
+        action: SmartAccExternalAction,
+        _tokens: Vec<Address>,
+        amounts: Vec<i128>,
+    ) -> Vec<(Symbol, i128)> {
+        smart_account.require_auth();
+
+        let token: Address = env.storage().persistent().get(&MockAdapterKey::Token).unwrap();
+        let symbol: Symbol = env
             .storage()
             .persistent()
-            .get(&MockLendingPoolKey::BorrowBalance(borrower.clone()))
-            .unwrap_or(U256::from_u128(&env, 0));
-        let remaining = if amount_wad >= current {
-            U256::from_u128(&env, 0)
-        } else {
-            current.sub(&amount_wad)
+            .get(&MockAdapterKey::ReportedSymbol)
+            .unwrap();
+        let amount = amounts.get(0).unwrap();
+        let shares = Self::get_shares(env.clone(), smart_account.clone());
+
+        let delta = match action {
+            SmartAccExternalAction::Deposit => amount,
+            SmartAccExternalAction::Withdraw => {
+                if amount > shares {
+                    panic!("Insufficient vault shares");
+                }
+                soroban_sdk::token::Client::new(&env, &token).transfer(
+                    &env.current_contract_address(),
+                    &smart_account,
+                    &amount,
+                );
+                -amount
+            }
+            _ => panic!("Unsupported vault action"),
         };
+
         env.storage()
             .persistent()
-            .set(&MockLendingPoolKey::BorrowBalance(borrower), &remaining);
-        remaining == U256::from_u128(&env, 0)
-    }
-}
+            .set(&MockAdapterKey::Shares(smart_account), &(shares + delta));
 
-#[contract]
-pub struct MockRiskEngine;
+        soroban_sdk::vec![&env, (symbol, delta)]
+    }
 
-#[contractimpl]
-impl MockRiskEngine {
-    pub fn is_borrow_allowed(_env: Env, _symbol: Symbol, _amount: U256, _account: Address) -> bool {
-        true
+    pub fn value_position(env: Env, smart_account: Address) -> Vec<(Symbol, U256)> {
+        let shares = Self::get_shares(env.clone(), smart_account);
+        let amount_wad = (shares as u128) * (WAD_U128 / 10u128.pow(7));
+        soroban_sdk::vec![&env, (XLM_SYMBOL, U256::from_u128(&env, amount_wad))]
     }
 
-    pub fn is_withdraw_allowed(_env: Env, _symbol: Symbol, _amount: U256, _account: Address) -> bool {
-        true
+    pub fn get_shares(env: Env, smart_account: Address) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&MockAdapterKey::Shares(smart_account))
+            .unwrap_or(0)
     }
 }
 
-struct FullCycleTestContext {
+struct AdapterTestContext {
     env: Env,
     user: Address,
+    registry: Address,
     account_manager: Address,
-    aquarius_router: Address,
+    adapter: Address,
     tracking_token: Address,
     xlm: Address,
-    usdc: Address,
 }
 
-fn setup_full_cycle() -> FullCycleTestContext {
+fn setup_adapter(reported_symbol: &str) -> AdapterTestContext {
     let env = Env::default();
     env.mock_all_auths();
 
@@ -1005,136 +3811,260 @@ fn setup_full_cycle() -> FullCycleTestContext {
     );
 
     let xlm_token = env.register_stellar_asset_contract_v2(admin.clone());
-    let usdc_token = env.register_stellar_asset_contract_v2(admin.clone());
-
-    // Mint XLM to user so deposit_collateral_tokens can transfer it (200 XLM, 7 decimals)
-    StellarAssetClient::new(&env, &xlm_token.address()).mint(&user, &2_000_000_000i128);
-
-    // Register Aquarius router and init XLM-USDC pool
-    let aquarius_router = env.register(MockAquariusRouter, ());
-    let aquarius_router_client = MockAquariusRouterClient::new(&env, &aquarius_router);
-    aquarius_router_client.init(&admin);
-    let mut tokens = Vec::new(&env);
-    tokens.push_back(xlm_token.address());
-    tokens.push_back(usdc_token.address());
-    let (pool_index, _) = aquarius_router_client.init_standard_pool(&admin, &tokens, &30u32);
 
-    // Register mock USDC lending pool and mock risk engine
-    let lending_pool_usdc = env.register(MockLendingPool, ());
-    let risk_engine = env.register(MockRiskEngine, ());
+    let adapter = env.register(MockVaultAdapter, ());
+    MockVaultAdapterClient::new(&env, &adapter)
+        .init(&xlm_token.address(), &Symbol::new(&env, reported_symbol));
 
-    // Register tracking token and initialise AQ_XLM_USDC symbol
     let tracking_token = env.register(TrackingToken, ());
     let tracking_client = TrackingTokenClient::new(&env, &tracking_token);
     tracking_client.initialize(
         &account_manager,
-        &Symbol::new(&env, AQUARIUS_XLM_USDC),
+        &Symbol::new(&env, VAULT_XLM),
         &7u32,
-        &String::from_str(&env, "Aquarius XLM-USDC LP"),
+        &String::from_str(&env, "Vault XLM"),
     );
 
-    // Configure registry
+    let oracle = env.register(MockPriceOracle, ());
+    let risk_engine = env.register(RiskEngineContract, (admin.clone(), registry.clone()));
+
     let registry_client = RegistryContractClient::new(&env, &registry);
     let smart_hash = env.deployer().upload_contract_wasm(SMART_ACCOUNT_WASM);
     registry_client.set_smart_account_hash(&smart_hash);
     registry_client.set_native_xlm_contract_address(&xlm_token.address());
-    registry_client.set_native_usdc_contract_address(&usdc_token.address());
-    registry_client.set_lendingpool_usdc(&lending_pool_usdc);
-    registry_client.set_risk_engine_address(&risk_engine);
-    registry_client.set_aquarius_router_address(&aquarius_router);
-    registry_client.set_aquarius_pool_index(&pool_index);
     registry_client.set_tracking_token_contract_addr(&tracking_token);
+    registry_client.set_oracle_contract_address(&oracle);
+    registry_client.set_risk_engine_address(&risk_engine);
     registry_client.set_accountmanager_contract(&account_manager);
+    registry_client.set_protocol_adapter(
+        &adapter,
+        &soroban_sdk::vec![&env, Symbol::new(&env, VAULT_XLM)],
+    );
+    allow_external_calls(
+        &env,
+        &account_manager,
+        &adapter,
+        &[SmartAccExternalAction::Deposit, SmartAccExternalAction::Withdraw],
+        soroban_sdk::vec![&env, XLM_SYMBOL],
+    );
 
-    // Allow XLM as collateral and set asset cap on account manager
-    let am_client = AccountManagerContractClient::new(&env, &account_manager);
-    am_client.set_max_asset_cap(&U256::from_u128(&env, 10));
-    am_client.set_iscollateral_allowed(&XLM_SYMBOL);
-
-    FullCycleTestContext {
+    AdapterTestContext {
         env,
         user,
+        registry,
         account_manager,
-        aquarius_router,
+        adapter,
         tracking_token,
         xlm: xlm_token.address(),
-        usdc: usdc_token.address(),
     }
 }
 
 #[test]
-fn test_full_cycle_deposit_collateral_borrow_open_aquarius_position() {
-    let ctx = setup_full_cycle();
+fn test_adapter_deposit_and_withdraw_tracks_position() {
+    let ctx = setup_adapter(VAULT_XLM);
     let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
-
-    // Step 1: Create margin account
     let smart_account = am_client.create_account(&ctx.user);
 
-    // Step 2: Deposit 100 XLM as collateral
-    let collateral_wad = 100u128 * WAD_U128;
-    am_client.deposit_collateral_tokens(
+    // 100 XLM sitting in the smart account
+    StellarAssetClient::new(&ctx.env, &ctx.xlm).mint(&smart_account, &1_000_000_000i128);
+
+    let deposit_call = build_external_call(
+        &ctx.env,
+        ctx.adapter.clone(),
+        SmartAccExternalAction::Deposit,
+        XLM_SYMBOL,
+        60u128 * WAD_U128,
+        smart_account.clone(),
+    );
+    am_client.execute(&smart_account, &deposit_call);
+
+    let deposited = scale_wad_to_token(60u128 * WAD_U128, 7);
+    let xlm_client = soroban_sdk::token::Client::new(&ctx.env, &ctx.xlm);
+    assert_eq!(xlm_client.balance(&ctx.adapter), deposited);
+    assert_eq!(xlm_client.balance(&smart_account), 1_000_000_000i128 - deposited);
+
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    let vault_symbol = Symbol::new(&ctx.env, VAULT_XLM);
+    assert_eq!(tracking_client.balance(&smart_account, &vault_symbol), deposited);
+
+    let sa_client = account_manager_contract::account_manager::smart_account_contract::Client::new(
+        &ctx.env,
         &smart_account,
-        &XLM_SYMBOL,
-        &U256::from_u128(&ctx.env, collateral_wad),
     );
+    assert!(sa_client.get_all_collateral_tokens().contains(vault_symbol.clone()));
 
-    // Verify collateral is recorded in the smart account (in WAD)
-    let sa_client =
-        account_manager_contract::account_manager::smart_account_contract::Client::new(
-            &ctx.env,
-            &smart_account,
-        );
+    let withdraw_call = build_external_call(
+        &ctx.env,
+        ctx.adapter.clone(),
+        SmartAccExternalAction::Withdraw,
+        XLM_SYMBOL,
+        20u128 * WAD_U128,
+        smart_account.clone(),
+    );
+    am_client.execute(&smart_account, &withdraw_call);
+
+    let withdrawn = scale_wad_to_token(20u128 * WAD_U128, 7);
     assert_eq!(
-        sa_client.get_collateral_token_balance(&XLM_SYMBOL),
-        U256::from_u128(&ctx.env, collateral_wad),
-        "Collateral balance must be recorded in WAD"
+        tracking_client.balance(&smart_account, &vault_symbol),
+        deposited - withdrawn
     );
+    assert_eq!(xlm_client.balance(&ctx.adapter), deposited - withdrawn);
+}
 
-    // Step 3: Borrow 50 USDC against the XLM collateral
-    let borrow_wad = 50u128 * WAD_U128;
-    am_client.borrow(
-        &smart_account,
-        &U256::from_u128(&ctx.env, borrow_wad),
-        &USDC_SYMBOL,
+#[test]
+fn test_liquidate_withdraws_adapter_positions() {
+    let ctx = setup_adapter(VAULT_XLM);
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    StellarAssetClient::new(&ctx.env, &ctx.xlm).mint(&smart_account, &1_000_000_000i128);
+
+    let deposit_call = build_external_call(
+        &ctx.env,
+        ctx.adapter.clone(),
+        SmartAccExternalAction::Deposit,
+        XLM_SYMBOL,
+        80u128 * WAD_U128,
+        smart_account.clone(),
     );
+    am_client.execute(&smart_account, &deposit_call);
 
-    // Verify the smart account now carries debt
-    assert!(sa_client.has_debt(), "Smart account must have debt after borrowing");
-    assert!(
-        sa_client.get_all_borrowed_tokens().contains(USDC_SYMBOL),
-        "USDC must appear in borrowed tokens list"
+    let risk_engine = ctx.env.register(MockRiskEngine, ());
+    MockRiskEngineClient::new(&ctx.env, &risk_engine).set_unhealthy(&true);
+    RegistryContractClient::new(&ctx.env, &ctx.registry).set_risk_engine_address(&risk_engine);
+    am_client.liquidate(&smart_account);
+
+    // The vault is emptied and the 80 XLM it held reaches the trader
+    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
+    assert_eq!(tracking_client.balance(&smart_account, &Symbol::new(&ctx.env, VAULT_XLM)), 0);
+    assert_eq!(MockVaultAdapterClient::new(&ctx.env, &ctx.adapter).get_shares(&smart_account), 0);
+    let xlm_client = soroban_sdk::token::Client::new(&ctx.env, &ctx.xlm);
+    assert_eq!(xlm_client.balance(&ctx.adapter), 0);
+    assert_eq!(xlm_client.balance(&ctx.user), 800_000_000i128);
+}
+
+#[test]
+fn test_risk_engine_values_adapter_positions() {
+    let ctx = setup_adapter(VAULT_XLM);
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    StellarAssetClient::new(&ctx.env, &ctx.xlm).mint(&smart_account, &1_000_000_000i128);
+
+    let deposit_call = build_external_call(
+        &ctx.env,
+        ctx.adapter.clone(),
+        SmartAccExternalAction::Deposit,
+        XLM_SYMBOL,
+        80u128 * WAD_U128,
+        smart_account.clone(),
     );
+    am_client.execute(&smart_account, &deposit_call);
 
-    // Step 4: Open Aquarius position — add 40 XLM + 40 USDC liquidity
-    let xlm_lp_wad = 40u128 * WAD_U128;
-    let usdc_lp_wad = 40u128 * WAD_U128;
-    let open_position_call = build_aquarius_add_liquidity_call(
+    let registry_client = RegistryContractClient::new(&ctx.env, &ctx.registry);
+    let risk_engine_client =
+        RiskEngineContractClient::new(&ctx.env, &registry_client.get_risk_engine_address());
+
+    // 80 XLM at 0.5 USD
+    assert_eq!(
+        risk_engine_client.get_current_total_balance(&smart_account),
+        U256::from_u128(&ctx.env, 40u128 * WAD_U128)
+    );
+}
+
+#[test]
+fn test_adapter_positions_follow_collateral_factor_ramp() {
+    let ctx = setup_adapter(VAULT_XLM);
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    StellarAssetClient::new(&ctx.env, &ctx.xlm).mint(&smart_account, &1_000_000_000i128);
+
+    let deposit_call = build_external_call(
         &ctx.env,
-        ctx.aquarius_router.clone(),
+        ctx.adapter.clone(),
+        SmartAccExternalAction::Deposit,
         XLM_SYMBOL,
-        USDC_SYMBOL,
-        xlm_lp_wad,
-        usdc_lp_wad,
+        80u128 * WAD_U128,
         smart_account.clone(),
     );
-    am_client.execute(&smart_account, &open_position_call);
+    am_client.execute(&smart_account, &deposit_call);
 
-    // Verify LP tracking tokens were minted
-    // Mock LP formula: (scaled_xlm + scaled_usdc) / 2
-    let xlm_scaled = scale_wad_to_token(xlm_lp_wad, 7);
-    let usdc_scaled = scale_wad_to_token(usdc_lp_wad, 7);
-    let expected_lp = (xlm_scaled + usdc_scaled) / 2;
+    let registry_client = RegistryContractClient::new(&ctx.env, &ctx.registry);
+    let risk_engine_client =
+        RiskEngineContractClient::new(&ctx.env, &registry_client.get_risk_engine_address());
+    risk_engine_client.set_collateral_factor_ramp(&XLM_SYMBOL, &5_000, &0);
 
-    let tracking_client = TrackingTokenClient::new(&ctx.env, &ctx.tracking_token);
-    let lp_symbol = Symbol::new(&ctx.env, AQUARIUS_XLM_USDC);
-    let lp_balance = tracking_client.balance(&smart_account, &lp_symbol);
+    // The vault position takes the XLM factor: half of 80 XLM at 0.5 USD counts toward health
+    assert_eq!(
+        risk_engine_client.get_current_total_balance(&smart_account),
+        U256::from_u128(&ctx.env, 20u128 * WAD_U128)
+    );
+    let breakdown = risk_engine_client.get_account_breakdown(&smart_account);
+    assert_eq!(
+        breakdown.total_collateral_value,
+        U256::from_u128(&ctx.env, 20u128 * WAD_U128)
+    );
+    assert_eq!(
+        breakdown.assets.get(0).unwrap().value,
+        U256::from_u128(&ctx.env, 40u128 * WAD_U128)
+    );
+}
 
-    assert!(lp_balance > 0, "LP tracking tokens must be minted after opening position");
-    assert_eq!(lp_balance, expected_lp, "LP balance must match mock pool formula");
+#[test]
+fn test_execute_respects_protocol_exposure_limit() {
+    let ctx = setup_adapter(VAULT_XLM);
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    StellarAssetClient::new(&ctx.env, &ctx.xlm).mint(&smart_account, &1_000_000_000i128);
 
-    // Verify the LP symbol is now tracked as collateral in the smart account
-    assert!(
-        sa_client.get_all_collateral_tokens().contains(lp_symbol),
-        "Aquarius LP tracking symbol must be added to smart account collateral"
+    let registry_client = RegistryContractClient::new(&ctx.env, &ctx.registry);
+    let risk_engine_client =
+        RiskEngineContractClient::new(&ctx.env, &registry_client.get_risk_engine_address());
+    risk_engine_client
+        .set_protocol_exposure_limit(&ctx.adapter, &U256::from_u128(&ctx.env, 50u128 * WAD_U128));
+
+    let deposit_call = build_external_call(
+        &ctx.env,
+        ctx.adapter.clone(),
+        SmartAccExternalAction::Deposit,
+        XLM_SYMBOL,
+        80u128 * WAD_U128,
+        smart_account.clone(),
+    );
+    am_client.execute(&smart_account, &deposit_call);
+    // 80 XLM at 0.5 USD
+    assert_eq!(
+        risk_engine_client.get_protocol_exposure(&smart_account, &ctx.adapter),
+        U256::from_u128(&ctx.env, 40u128 * WAD_U128)
+    );
+
+    // Another 20 XLM would put 50 USD in the vault, over a 45 USD limit
+    risk_engine_client
+        .set_protocol_exposure_limit(&ctx.adapter, &U256::from_u128(&ctx.env, 45u128 * WAD_U128));
+    let deposit_call = build_external_call(
+        &ctx.env,
+        ctx.adapter.clone(),
+        SmartAccExternalAction::Deposit,
+        XLM_SYMBOL,
+        20u128 * WAD_U128,
+        smart_account.clone(),
+    );
+    assert!(am_client.try_execute(&smart_account, &deposit_call).is_err());
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #41)")]
+fn test_adapter_cannot_mint_unregistered_tracking_symbol() {
+    let ctx = setup_adapter("BLEND_XLM");
+    let am_client = AccountManagerContractClient::new(&ctx.env, &ctx.account_manager);
+    let smart_account = am_client.create_account(&ctx.user);
+    StellarAssetClient::new(&ctx.env, &ctx.xlm).mint(&smart_account, &1_000_000_000i128);
+
+    let deposit_call = build_external_call(
+        &ctx.env,
+        ctx.adapter.clone(),
+        SmartAccExternalAction::Deposit,
+        XLM_SYMBOL,
+        10u128 * WAD_U128,
+        smart_account.clone(),
     );
+    am_client.execute(&smart_account, &deposit_call);
 }
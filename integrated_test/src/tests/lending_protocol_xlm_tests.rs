@@ -355,7 +355,7 @@ fn initialize_pool_requires_admin_and_marks_initialized() {
 }
 
 #[test]
-#[should_panic(expected = "Lending pool not initialised")]
+#[should_panic(expected = "Error(Contract, #11)")]
 fn deposit_panics_if_not_initialized() {
     let env = Env::default();
 
@@ -369,7 +369,7 @@ fn deposit_panics_if_not_initialized() {
 }
 
 #[test]
-#[should_panic(expected = "Deposit amount must be positive")]
+#[should_panic(expected = "Error(Contract, #15)")]
 fn deposit_panics_zero_amount() {
     let env = Env::default();
 
@@ -524,7 +524,7 @@ fn redeem_auth_logic_success() {
 }
 
 #[test]
-#[should_panic(expected = "Insufficient Token Balance to redeem")]
+#[should_panic(expected = "Error(Contract, #16)")]
 fn redeem_panics_if_over_balance() {
     let env = Env::default();
 
@@ -802,7 +802,7 @@ fn collect_from_reduces_debt_and_returns_zeroed_flag() {
 }
 
 #[test]
-#[should_panic(expected = "Zero borrow shares")]
+#[should_panic(expected = "Error(Contract, #17)")]
 fn collect_from_panics_zero_shares_amount() {
     let env = Env::default();
 
@@ -880,7 +880,7 @@ fn state_updates_once_per_timestamp_and_accrues_interest() {
 // }
 
 #[test]
-#[should_panic(expected = "Lending pool not initialised")]
+#[should_panic(expected = "Error(Contract, #11)")]
 fn is_xlm_pool_initialised_panics_if_missing_flag() {
     let env = Env::default();
 
@@ -1135,3 +1135,43 @@ fn current_total_vxlm_balance_reflects_mint_and_burn() {
     let total2 = xlm_pool_client.get_current_total_vxlm_balance();
     assert!(total2 < total1);
 }
+
+#[test]
+fn lender_position_tracks_deposits_withdrawals_and_interest() {
+    let env = Env::default();
+    let ctx = test_initiation(&env);
+    let xlm_pool_client = pool_client(&env, &ctx);
+    env.mock_all_auths();
+    xlm_pool_client.initialize_pool_xlm(&ctx.vxlm_token_contract);
+
+    let stellar_asset_xlm = StellarAssetClient::new(&env, &ctx.xlm_address);
+    stellar_asset_xlm.mint(&ctx.user, &(100000 * WAD7));
+
+    xlm_pool_client.deposit_xlm(&ctx.user, &U256::from_u128(&env, 100_000 * WAD_U128));
+    xlm_pool_client.redeem_vxlm(&ctx.user, &U256::from_u128(&env, 50_000 * WAD_U128));
+
+    // Repaid interest lands in the pool balance
+    stellar_asset_xlm.mint(&ctx.liquidity_pool_xlm, &(1000 * WAD7));
+
+    let position = xlm_pool_client.get_lender_position(&ctx.user);
+    assert_eq!(position.vtoken_balance_wad, U256::from_u128(&env, 50_000 * WAD_U128));
+    assert_eq!(position.underlying_value_wad, U256::from_u128(&env, 51_000 * WAD_U128));
+    assert_eq!(position.deposited_wad, U256::from_u128(&env, 100_000 * WAD_U128));
+    assert_eq!(position.withdrawn_wad, U256::from_u128(&env, 50_000 * WAD_U128));
+    assert_eq!(position.interest_earned_wad, (1000 * WAD_U128) as i128);
+
+    // Addresses that never lent have an empty position
+    let stranger = xlm_pool_client.get_lender_position(&Addr::generate(&env));
+    assert_eq!(stranger.underlying_value_wad, U256::from_u32(&env, 0));
+    assert_eq!(stranger.interest_earned_wad, 0);
+
+    // Interest owed on open borrows counts before anything calls update_state
+    xlm_pool_client.lend_to(
+        &ctx.smart_account_contract.unwrap(),
+        &U256::from_u128(&env, 10_000 * WAD_U128),
+    );
+    let before = xlm_pool_client.get_lender_position(&ctx.user);
+    env.ledger().set_timestamp(env.ledger().timestamp() + 10);
+    let after = xlm_pool_client.get_lender_position(&ctx.user);
+    assert!(after.interest_earned_wad > before.interest_earned_wad);
+}
@@ -6,7 +6,7 @@ use soroban_sdk::{
 };
 
 use registry_contract::registry::{RegistryContract, RegistryContractClient};
-use registry_contract::types::{RegistryContractError, RegistryKey};
+use registry_contract::types::{BlendPositionKind, RegistryContractError, RegistryKey};
 use soroban_sdk::symbol_short;
 const XLM_SYMBOL: Symbol = symbol_short!("XLM");
 const USDC_SYMBOL: Symbol = symbol_short!("USDC");
@@ -132,6 +132,24 @@ fn test_all_setters_and_getters() {
     assert_eq!(client.get_eurc_contract_address(), addr3);
 }
 
+#[test]
+fn test_aquarius_pair_pool_index_falls_back_to_default_pool() {
+    let (env, _, client) = setup();
+    env.mock_all_auths();
+    let default_index = BytesN::random(&env);
+    let eurc_index = BytesN::random(&env);
+
+    client.set_aquarius_pool_index(&default_index);
+    client.set_aquarius_pair_pool_index(&EURC_SYMBOL, &USDC_SYMBOL, &eurc_index);
+
+    assert_eq!(client.get_aquarius_pair_pool_index(&USDC_SYMBOL, &EURC_SYMBOL), eurc_index);
+    assert_eq!(client.get_aquarius_pair_pool_index(&EURC_SYMBOL, &USDC_SYMBOL), eurc_index);
+    assert_eq!(
+        client.get_aquarius_pair_pool_index(&symbol_short!("XLM"), &USDC_SYMBOL),
+        default_index
+    );
+}
+
 #[test]
 fn test_account_lifecycle_add_update_close_with_auth() {
     let (env, admin, client) = setup();
@@ -320,7 +338,7 @@ fn test_duplicate_add_account_does_not_duplicate_list() {
 }
 
 #[test]
-#[should_panic(expected = "Failed to get lendingpool_xlm address")]
+#[should_panic(expected = "Error(Contract, #8)")]
 fn test_get_unset_address_panics() {
     let env = Env::default();
     let admin = Address::generate(&env);
@@ -328,3 +346,142 @@ fn test_get_unset_address_panics() {
     let client = RegistryContractClient::new(&env, &contract_id);
     let _ = client.get_lendingpool_xlm();
 }
+
+#[test]
+fn test_set_and_remove_protocol_adapter() {
+    let (env, _admin, client) = setup();
+    env.mock_all_auths();
+
+    let adapter = Address::generate(&env);
+    let vault_xlm = Symbol::new(&env, "VAULT_XLM");
+    let vault_usdc = Symbol::new(&env, "VAULT_USDC");
+    let symbols = soroban_sdk::vec![&env, vault_xlm.clone(), vault_usdc.clone()];
+
+    client.set_protocol_adapter(&adapter, &symbols);
+    assert!(client.is_protocol_adapter(&adapter));
+    assert_eq!(client.get_adapter_tracking_symbols(&adapter), symbols);
+    assert_eq!(client.get_tracking_symbol_adapter(&vault_usdc), adapter);
+    assert_eq!(client.get_protocol_adapters().len(), 1);
+
+    // Re-registering replaces the symbol set without duplicating the adapter
+    client.set_protocol_adapter(&adapter, &soroban_sdk::vec![&env, vault_xlm.clone()]);
+    assert!(!client.has_tracking_symbol_adapter(&vault_usdc));
+    assert_eq!(client.get_protocol_adapters().len(), 1);
+
+    client.remove_protocol_adapter(&adapter);
+    assert!(!client.is_protocol_adapter(&adapter));
+    assert!(!client.has_tracking_symbol_adapter(&vault_xlm));
+    assert_eq!(client.get_protocol_adapters().len(), 0);
+}
+
+#[test]
+fn test_protocol_adapter_rejects_symbol_owned_by_other_adapter() {
+    let (env, _admin, client) = setup();
+    env.mock_all_auths();
+
+    let adapter_a = Address::generate(&env);
+    let adapter_b = Address::generate(&env);
+    let vault_xlm = Symbol::new(&env, "VAULT_XLM");
+
+    client.set_protocol_adapter(&adapter_a, &soroban_sdk::vec![&env, vault_xlm.clone()]);
+
+    let res = client.try_set_protocol_adapter(&adapter_b, &soroban_sdk::vec![&env, vault_xlm]);
+    assert_eq!(
+        res,
+        Err(Ok(RegistryContractError::TrackingSymbolAlreadyMapped))
+    );
+    assert!(!client.is_protocol_adapter(&adapter_b));
+}
+
+#[test]
+fn test_blend_pools_get_namespaced_tracking_symbols() {
+    let (env, _admin, client) = setup();
+    env.mock_all_auths();
+
+    let primary_pool = Address::generate(&env);
+    let stable_pool = Address::generate(&env);
+    client.set_blend_pool_address(&primary_pool);
+    assert_eq!(client.add_blend_pool(&stable_pool), 1);
+
+    assert!(client.is_blend_pool(&primary_pool));
+    assert!(client.is_blend_pool(&stable_pool));
+    assert_eq!(
+        client.get_blend_pools(),
+        soroban_sdk::vec![&env, primary_pool.clone(), stable_pool.clone()]
+    );
+
+    // The primary pool keeps the original symbols, added pools are numbered
+    assert_eq!(
+        client.get_blend_tracking_symbol(&primary_pool, &USDC_SYMBOL, &BlendPositionKind::Supply),
+        Symbol::new(&env, "BLEND_USDC")
+    );
+    assert_eq!(
+        client.get_blend_tracking_symbol(&stable_pool, &USDC_SYMBOL, &BlendPositionKind::Supply),
+        Symbol::new(&env, "BLEND1_USDC")
+    );
+    assert_eq!(
+        client.get_blend_tracking_symbol(&stable_pool, &EURC_SYMBOL, &BlendPositionKind::Liability),
+        Symbol::new(&env, "BLEND1_DEBT_EURC")
+    );
+
+    let info = client
+        .get_blend_tracking_info(&Symbol::new(&env, "BLEND1_COLL_XLM"))
+        .unwrap();
+    assert_eq!(info.pool, stable_pool);
+    assert_eq!(info.underlying, XLM_SYMBOL);
+    assert_eq!(info.kind, BlendPositionKind::Collateral);
+    assert!(client.get_blend_tracking_info(&XLM_SYMBOL).is_none());
+
+    // Removed pools stop being routed to but keep their symbol mapping
+    client.remove_blend_pool(&stable_pool);
+    assert!(!client.is_blend_pool(&stable_pool));
+    assert!(client.is_delisted_blend_pool(&stable_pool));
+    assert!(client
+        .get_blend_tracking_info(&Symbol::new(&env, "BLEND1_USDC"))
+        .is_some());
+
+    // Relisting keeps the original id
+    assert_eq!(client.add_blend_pool(&stable_pool), 1);
+    assert!(!client.is_delisted_blend_pool(&stable_pool));
+}
+
+#[test]
+fn test_replacing_primary_blend_pool_keeps_existing_symbols() {
+    let (env, _admin, client) = setup();
+    env.mock_all_auths();
+
+    let old_pool = Address::generate(&env);
+    let new_pool = Address::generate(&env);
+    client.set_blend_pool_address(&old_pool);
+    client.set_blend_pool_address(&new_pool);
+
+    assert_eq!(client.get_blend_pools(), soroban_sdk::vec![&env, new_pool.clone()]);
+    assert!(client.is_delisted_blend_pool(&old_pool));
+
+    // BLEND_* still belongs to the replaced pool, the new one gets fresh symbols
+    let info = client
+        .get_blend_tracking_info(&Symbol::new(&env, "BLEND_USDC"))
+        .unwrap();
+    assert_eq!(info.pool, old_pool);
+    assert_eq!(
+        client.get_blend_tracking_symbol(&new_pool, &USDC_SYMBOL, &BlendPositionKind::Supply),
+        Symbol::new(&env, "BLEND1_USDC")
+    );
+}
+
+#[test]
+fn test_add_blend_pool_rejects_duplicates() {
+    let (env, _admin, client) = setup();
+    env.mock_all_auths();
+
+    let pool = Address::generate(&env);
+    client.add_blend_pool(&pool);
+    assert_eq!(
+        client.try_add_blend_pool(&pool),
+        Err(Ok(RegistryContractError::BlendPoolAlreadyListed))
+    );
+    assert_eq!(
+        client.try_remove_blend_pool(&Address::generate(&env)),
+        Err(Ok(RegistryContractError::BlendPoolNotFound))
+    );
+}
@@ -3,16 +3,20 @@
 
 use lending_protocol_eurc::liquidity_pool_eurc::{LiquidityPoolEURC, LiquidityPoolEURCClient};
 use lending_protocol_usdc::liquidity_pool_usdc::{LiquidityPoolUSDC, LiquidityPoolUSDCClient};
-use risk_engine_contract::types::RiskEngineKey;
+use risk_engine_contract::types::{AccountLimits, RiskEngineError, RiskEngineKey, SimulatedAction};
+use soroban_sdk::testutils::Ledger;
 use soroban_sdk::testutils::storage::Persistent;
 use soroban_sdk::{Address, BytesN, Env, Symbol, U256, Vec, testutils::Address as _};
 
 // --- Bring the contract under test into scope
 use account_manager_contract::account_manager::AccountManagerContractClient;
 use account_manager_contract::account_manager::{self, AccountManagerContract};
+use account_manager_contract::types::{AccountManagerError, BatchCall};
+use soroban_sdk::xdr::ToXdr;
 use lending_protocol_xlm::liquidity_pool_xlm::{self, LiquidityPoolXLM, LiquidityPoolXLMClient};
 use oracle_contract::oracle_service::{OracleContract, OracleContractClient};
 use registry_contract::registry::{RegistryContract, RegistryContractClient};
+use registry_contract::types::RegistryContractError;
 use risk_engine_contract::risk_engine::{
     BALANCE_TO_BORROW_THRESHOLD, RiskEngineContract, RiskEngineContractClient, WAD_U128,
 };
@@ -571,7 +575,7 @@ fn borrow_disallowed_when_health_drops_below_threshold() {
 }
 
 #[test]
-#[should_panic(expected = "Cannot withdraw more value than the current collateral value")]
+#[should_panic(expected = "Error(Contract, #2)")]
 fn withdraw_panics_when_exceeding_collateral() {
     let env = Env::default();
     let ctx = test_initiation(&env);
@@ -647,6 +651,550 @@ fn is_account_healthy_handles_zero_debt_and_threshold_logic() {
     assert!(!risk.is_account_healthy(&bal2, &debt2));
 }
 
+#[test]
+fn is_deleverage_allowed_only_inside_soft_zone() {
+    let env = Env::default();
+    let ctx = test_initiation(&env);
+
+    let risk = RiskEngineContractClient::new(&env, &ctx.risk_engine_contract);
+    let debt = U256::from_u128(&env, WAD_U128);
+
+    // No debt, healthy above the zone, in the zone, and liquidatable below it
+    assert!(!risk.is_deleverage_allowed(&U256::from_u128(&env, WAD_U128), &U256::from_u32(&env, 0)));
+    assert!(!risk.is_deleverage_allowed(&U256::from_u128(&env, 13 * WAD_U128 / 10), &debt));
+    assert!(risk.is_deleverage_allowed(&U256::from_u128(&env, 12 * WAD_U128 / 10), &debt));
+    assert!(!risk.is_deleverage_allowed(&U256::from_u128(&env, BALANCE_TO_BORROW_THRESHOLD), &debt));
+
+    env.mock_all_auths();
+    risk.set_deleverage_threshold(&U256::from_u128(&env, 15 * WAD_U128 / 10));
+    assert!(risk.is_deleverage_allowed(&U256::from_u128(&env, 13 * WAD_U128 / 10), &debt));
+    assert!(risk
+        .try_set_deleverage_threshold(&U256::from_u128(&env, BALANCE_TO_BORROW_THRESHOLD))
+        .is_err());
+}
+
+#[test]
+fn account_breakdown_and_simulation_report_health_factor() {
+    let env = Env::default();
+    let ctx = test_initiation(&env);
+    env.mock_all_auths();
+    initialise_lenders(&env, &ctx);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &ctx.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+
+    let trader = Addr::generate(&env);
+    let usdc_token = StellarAssetClient::new(&env, &ctx.usdc_address);
+    usdc_token.mint(&trader, &LARGE_AMOUNT);
+
+    let smart_acc = account_manager_client.create_account(&trader);
+    let risk = RiskEngineContractClient::new(&env, &ctx.risk_engine_contract);
+    assert_eq!(
+        risk.get_health_factor(&smart_acc),
+        U256::from_u128(&env, u128::MAX)
+    );
+
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &USDC_SYMBOL,
+        &U256::from_u128(&env, 10 * WAD_U128),
+    );
+    account_manager_client.borrow(
+        &smart_acc,
+        &U256::from_u128(&env, 10 * WAD_U128),
+        &XLM_SYMBOL,
+    );
+
+    // 10 USDC at 0.999 against 10 XLM of debt at 0.4
+    let breakdown = risk.get_account_breakdown(&smart_acc);
+    assert_eq!(breakdown.assets.len(), 2);
+    let usdc = breakdown.assets.get(0).unwrap();
+    assert_eq!(usdc.symbol, USDC_SYMBOL);
+    assert!(!usdc.is_debt);
+    assert_eq!(usdc.price, U256::from_u128(&env, 999 * WAD_U128 / 1000));
+    assert_eq!(risk.get_asset_price(&USDC_SYMBOL), usdc.price);
+    let xlm = breakdown.assets.get(1).unwrap();
+    assert_eq!(xlm.symbol, XLM_SYMBOL);
+    assert!(xlm.is_debt);
+    assert_eq!(xlm.amount, U256::from_u128(&env, 10 * WAD_U128));
+    assert_eq!(
+        breakdown.total_collateral_value,
+        U256::from_u128(&env, 999 * WAD_U128 / 100)
+    );
+    assert_eq!(breakdown.total_debt_value, U256::from_u128(&env, 4 * WAD_U128));
+    assert_eq!(
+        breakdown.health_factor,
+        U256::from_u128(&env, 24975 * WAD_U128 / 10000)
+    );
+    assert_eq!(risk.get_health_factor(&smart_acc), breakdown.health_factor);
+
+    // Borrowing 10 more XLM adds 4 to both sides
+    let mut actions = Vec::new(&env);
+    actions.push_back(SimulatedAction::Borrow(
+        XLM_SYMBOL,
+        U256::from_u128(&env, 10 * WAD_U128),
+    ));
+    assert_eq!(
+        risk.simulate(&smart_acc, &actions),
+        U256::from_u128(&env, 174875 * WAD_U128 / 100000)
+    );
+
+    // Withdrawing half the USDC leaves 4.995 against 4
+    let mut actions = Vec::new(&env);
+    actions.push_back(SimulatedAction::WithdrawCollateral(
+        USDC_SYMBOL,
+        U256::from_u128(&env, 5 * WAD_U128),
+    ));
+    assert_eq!(
+        risk.simulate(&smart_acc, &actions),
+        U256::from_u128(&env, 124875 * WAD_U128 / 100000)
+    );
+}
+
+#[test]
+fn liquidation_price_for_collateral_and_debt_symbols() {
+    let env = Env::default();
+    let ctx = test_initiation(&env);
+    env.mock_all_auths();
+    initialise_lenders(&env, &ctx);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &ctx.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+
+    let trader = Addr::generate(&env);
+    let usdc_token = StellarAssetClient::new(&env, &ctx.usdc_address);
+    usdc_token.mint(&trader, &LARGE_AMOUNT);
+
+    let smart_acc = account_manager_client.create_account(&trader);
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &USDC_SYMBOL,
+        &U256::from_u128(&env, 10 * WAD_U128),
+    );
+    account_manager_client.borrow(
+        &smart_acc,
+        &U256::from_u128(&env, 10 * WAD_U128),
+        &XLM_SYMBOL,
+    );
+
+    let risk = RiskEngineContractClient::new(&env, &ctx.risk_engine_contract);
+    // USDC falling to 0.44 leaves 4.4 of collateral against 4 of debt
+    assert_eq!(
+        risk.get_liquidation_price(&smart_acc, &USDC_SYMBOL),
+        Some(U256::from_u128(&env, 44 * WAD_U128 / 100))
+    );
+    // XLM rising to 9.99 / 11 puts the debt at the threshold
+    assert_eq!(
+        risk.get_liquidation_price(&smart_acc, &XLM_SYMBOL),
+        Some(U256::from_u128(&env, 999 * WAD_U128 / 1100))
+    );
+    // EURC is not held, so its price cannot trigger liquidation
+    assert_eq!(risk.get_liquidation_price(&smart_acc, &EURC_SYMBOL), None);
+}
+
+#[test]
+fn listed_collateral_asset_is_priced_through_its_oracle_ticker() {
+    let env = Env::default();
+    let ctx = test_initiation(&env);
+    env.mock_all_auths();
+    initialise_lenders(&env, &ctx);
+
+    let yusdc_symbol = symbol_short!("YUSDC");
+    let yusdc_address = env
+        .register_stellar_asset_contract_v2(ctx.admin.clone())
+        .address();
+    let registry_client = RegistryContractClient::new(&env, &ctx.registry_contract);
+    registry_client.list_collateral_asset(&yusdc_symbol, &yusdc_address, &USDC_SYMBOL);
+    assert_eq!(registry_client.get_collateral_assets().len(), 1);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &ctx.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&yusdc_symbol);
+
+    let trader = Addr::generate(&env);
+    StellarAssetClient::new(&env, &yusdc_address).mint(&trader, &(1000 * WAD7));
+
+    let smart_acc = account_manager_client.create_account(&trader);
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &yusdc_symbol,
+        &U256::from_u128(&env, 100 * WAD_U128),
+    );
+
+    let yusdc_token = token::TokenClient::new(&env, &yusdc_address);
+    assert_eq!(yusdc_token.balance(&smart_acc), 100 * WAD7);
+    let smart_account_client = SmartAccountContractClient::new(&env, &smart_acc);
+    assert_eq!(
+        smart_account_client.get_collateral_token_balance(&yusdc_symbol),
+        U256::from_u128(&env, 100 * WAD_U128)
+    );
+
+    // Priced at the USDC feed of 0.999
+    let risk = RiskEngineContractClient::new(&env, &ctx.risk_engine_contract);
+    assert_eq!(
+        risk.get_current_total_balance(&smart_acc),
+        U256::from_u128(&env, 999 * WAD_U128 / 10)
+    );
+
+    account_manager_client.withdraw_collateral_balance(
+        &smart_acc,
+        &yusdc_symbol,
+        &U256::from_u128(&env, 40 * WAD_U128),
+    );
+    assert_eq!(yusdc_token.balance(&trader), 940 * WAD7);
+    assert_eq!(yusdc_token.balance(&smart_acc), 60 * WAD7);
+
+    // Delisting waits until no account holds the asset any more
+    assert_eq!(
+        registry_client.try_delist_collateral_asset(&yusdc_symbol),
+        Err(Ok(RegistryContractError::CollateralAssetInUse))
+    );
+    account_manager_client.withdraw_collateral_balance(
+        &smart_acc,
+        &yusdc_symbol,
+        &U256::from_u128(&env, 60 * WAD_U128),
+    );
+    registry_client.delist_collateral_asset(&yusdc_symbol);
+    assert_eq!(registry_client.get_collateral_asset(&yusdc_symbol), None);
+    assert!(registry_client.try_delist_collateral_asset(&yusdc_symbol).is_err());
+}
+
+#[test]
+fn disabled_collateral_is_wound_down_over_the_ramp() {
+    let env = Env::default();
+    let ctx = test_initiation(&env);
+    env.mock_all_auths();
+    initialise_lenders(&env, &ctx);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &ctx.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+
+    let trader = Addr::generate(&env);
+    let usdc_token = StellarAssetClient::new(&env, &ctx.usdc_address);
+    usdc_token.mint(&trader, &LARGE_AMOUNT);
+
+    let smart_acc = account_manager_client.create_account(&trader);
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &USDC_SYMBOL,
+        &U256::from_u128(&env, 10 * WAD_U128),
+    );
+    account_manager_client.borrow(
+        &smart_acc,
+        &U256::from_u128(&env, 10 * WAD_U128),
+        &XLM_SYMBOL,
+    );
+
+    account_manager_client.set_iscollateral_disallowed(&USDC_SYMBOL);
+    assert_eq!(
+        account_manager_client.try_deposit_collateral_tokens(
+            &smart_acc,
+            &USDC_SYMBOL,
+            &U256::from_u128(&env, WAD_U128),
+        ),
+        Err(Ok(AccountManagerError::CollateralNotAllowed))
+    );
+    let holders = account_manager_client.get_collateral_holders(&USDC_SYMBOL);
+    assert_eq!(holders.len(), 1);
+    assert_eq!(holders.get(0).unwrap(), smart_acc);
+    assert_eq!(account_manager_client.get_collateral_holders(&EURC_SYMBOL).len(), 0);
+
+    let risk = RiskEngineContractClient::new(&env, &ctx.risk_engine_contract);
+    assert_eq!(risk.get_collateral_factor_bps(&USDC_SYMBOL), 10_000);
+    risk.set_collateral_factor_ramp(&USDC_SYMBOL, &0, &1_000);
+
+    // Half way through the ramp 4.995 of the 9.99 USDC counts against 4 of debt
+    env.ledger().with_mut(|li| li.timestamp += 500);
+    assert_eq!(risk.get_collateral_factor_bps(&USDC_SYMBOL), 5_000);
+    assert_eq!(
+        risk.get_health_factor(&smart_acc),
+        U256::from_u128(&env, 124875 * WAD_U128 / 100000)
+    );
+
+    env.ledger().with_mut(|li| li.timestamp += 500);
+    assert_eq!(risk.get_collateral_factor_bps(&USDC_SYMBOL), 0);
+    assert_eq!(risk.get_health_factor(&smart_acc), U256::from_u128(&env, 0));
+    assert!(
+        !risk.is_account_healthy(
+            &risk.get_current_total_balance(&smart_acc),
+            &risk.get_current_total_borrows(&smart_acc),
+        )
+    );
+}
+
+#[test]
+fn isolated_collateral_borrows_stablecoins_up_to_the_ceiling() {
+    let env = Env::default();
+    let ctx = test_initiation(&env);
+    env.mock_all_auths();
+    initialise_lenders(&env, &ctx);
+
+    let yusdc_symbol = symbol_short!("YUSDC");
+    let yusdc_address = env
+        .register_stellar_asset_contract_v2(ctx.admin.clone())
+        .address();
+    let registry_client = RegistryContractClient::new(&env, &ctx.registry_contract);
+    registry_client.list_collateral_asset(&yusdc_symbol, &yusdc_address, &USDC_SYMBOL);
+    registry_client.set_isolated_asset(&yusdc_symbol, &U256::from_u128(&env, 50 * WAD_U128));
+    registry_client.set_isolation_borrowable(&USDC_SYMBOL, &true);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &ctx.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&yusdc_symbol);
+
+    let trader = Addr::generate(&env);
+    StellarAssetClient::new(&env, &yusdc_address).mint(&trader, &(1000 * WAD7));
+    let smart_acc = account_manager_client.create_account(&trader);
+    let risk = RiskEngineContractClient::new(&env, &ctx.risk_engine_contract);
+    assert_eq!(risk.get_isolated_collateral(&smart_acc), None);
+
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &yusdc_symbol,
+        &U256::from_u128(&env, 100 * WAD_U128),
+    );
+    assert_eq!(risk.get_isolated_collateral(&smart_acc), Some(yusdc_symbol.clone()));
+
+    // Only the designated stablecoins may be borrowed against isolated collateral
+    let ten = U256::from_u128(&env, 10 * WAD_U128);
+    assert!(risk.try_is_borrow_allowed(&XLM_SYMBOL, &ten, &smart_acc).is_err());
+    assert_eq!(
+        account_manager_client.try_borrow(&smart_acc, &ten, &XLM_SYMBOL),
+        Err(Ok(AccountManagerError::IsolationBorrowNotAllowed))
+    );
+
+    account_manager_client.borrow(
+        &smart_acc,
+        &U256::from_u128(&env, 40 * WAD_U128),
+        &USDC_SYMBOL,
+    );
+    assert_eq!(
+        registry_client.get_isolated_debt(&yusdc_symbol),
+        U256::from_u128(&env, 40 * WAD_U128)
+    );
+    assert!(
+        risk.try_is_borrow_allowed(&USDC_SYMBOL, &U256::from_u128(&env, 20 * WAD_U128), &smart_acc)
+            .is_err()
+    );
+    assert_eq!(
+        account_manager_client.try_borrow(
+            &smart_acc,
+            &U256::from_u128(&env, 20 * WAD_U128),
+            &USDC_SYMBOL,
+        ),
+        Err(Ok(AccountManagerError::IsolatedDebtCeilingExceeded))
+    );
+
+    // Repaying frees room under the ceiling
+    account_manager_client.repay(
+        &U256::from_u128(&env, 15 * WAD_U128),
+        &USDC_SYMBOL,
+        &smart_acc,
+    );
+    assert_eq!(
+        registry_client.get_isolated_debt(&yusdc_symbol),
+        U256::from_u128(&env, 25 * WAD_U128)
+    );
+    let entries = account_manager_client.get_account_isolated_debt(&smart_acc);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries.get(0).unwrap().amount_wad, U256::from_u128(&env, 25 * WAD_U128));
+}
+
+#[test]
+fn liquidating_an_isolated_account_releases_its_debt_ceiling() {
+    let env = Env::default();
+    let ctx = test_initiation(&env);
+    env.mock_all_auths();
+    initialise_lenders(&env, &ctx);
+
+    let yusdc_symbol = symbol_short!("YUSDC");
+    let yusdc_address = env
+        .register_stellar_asset_contract_v2(ctx.admin.clone())
+        .address();
+    let registry_client = RegistryContractClient::new(&env, &ctx.registry_contract);
+    registry_client.list_collateral_asset(&yusdc_symbol, &yusdc_address, &USDC_SYMBOL);
+    registry_client.set_isolated_asset(&yusdc_symbol, &U256::from_u128(&env, 50 * WAD_U128));
+    registry_client.set_isolation_borrowable(&USDC_SYMBOL, &true);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &ctx.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&yusdc_symbol);
+
+    let trader = Addr::generate(&env);
+    StellarAssetClient::new(&env, &yusdc_address).mint(&trader, &(1000 * WAD7));
+    let smart_acc = account_manager_client.create_account(&trader);
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &yusdc_symbol,
+        &U256::from_u128(&env, 100 * WAD_U128),
+    );
+    account_manager_client.borrow(
+        &smart_acc,
+        &U256::from_u128(&env, 40 * WAD_U128),
+        &USDC_SYMBOL,
+    );
+    assert_eq!(
+        registry_client.get_isolated_debt(&yusdc_symbol),
+        U256::from_u128(&env, 40 * WAD_U128)
+    );
+
+    // Winding the collateral factor down leaves the account unhealthy
+    let risk = RiskEngineContractClient::new(&env, &ctx.risk_engine_contract);
+    risk.set_collateral_factor_ramp(&yusdc_symbol, &0, &1_000);
+    env.ledger().with_mut(|li| li.timestamp += 1_000);
+    // Covers the interest accrued on top of the borrowed USDC
+    StellarAssetClient::new(&env, &ctx.usdc_address).mint(&smart_acc, &WAD7);
+    account_manager_client.liquidate(&smart_acc);
+
+    assert_eq!(
+        registry_client.get_isolated_debt(&yusdc_symbol),
+        U256::from_u128(&env, 0)
+    );
+    assert!(account_manager_client.get_account_isolated_debt(&smart_acc).is_empty());
+}
+
+#[test]
+fn emode_category_raises_borrowing_power_for_correlated_assets() {
+    let env = Env::default();
+    let ctx = test_initiation(&env);
+    env.mock_all_auths();
+    initialise_lenders(&env, &ctx);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &ctx.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+
+    let trader = Addr::generate(&env);
+    let usdc_token = StellarAssetClient::new(&env, &ctx.usdc_address);
+    usdc_token.mint(&trader, &LARGE_AMOUNT);
+
+    let smart_acc = account_manager_client.create_account(&trader);
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &USDC_SYMBOL,
+        &U256::from_u128(&env, 10 * WAD_U128),
+    );
+
+    // 82 EURC is worth about 100.55, above what 9.99 of USDC supports globally
+    let risk = RiskEngineContractClient::new(&env, &ctx.risk_engine_contract);
+    let eurc_amount = U256::from_u128(&env, 82 * WAD_U128);
+    assert!(!risk.is_borrow_allowed(&EURC_SYMBOL, &eurc_amount, &smart_acc));
+    assert_eq!(risk.get_account_emode(&smart_acc), None);
+
+    let mut stablecoins = Vec::new(&env);
+    stablecoins.push_back(USDC_SYMBOL);
+    stablecoins.push_back(EURC_SYMBOL);
+    risk.set_emode_category(&1, &9_500, &stablecoins, &0);
+    assert_eq!(risk.get_account_emode(&smart_acc), Some(1));
+    assert!(risk.is_borrow_allowed(&EURC_SYMBOL, &eurc_amount, &smart_acc));
+
+    // 9.99 of USDC scaled so the threshold sits at 95% of its value
+    let breakdown = risk.get_account_breakdown(&smart_acc);
+    assert_eq!(
+        breakdown.total_collateral_value,
+        U256::from_u128(&env, 1043955 * WAD_U128 / 100000)
+    );
+
+    // Borrowing outside the category falls back to the global parameters
+    assert!(!risk.is_borrow_allowed(
+        &XLM_SYMBOL,
+        &U256::from_u128(&env, 251 * WAD_U128),
+        &smart_acc,
+    ));
+
+    // A tighter category phases in from the global parameters and wins once it is higher
+    risk.set_emode_category(&2, &9_800, &stablecoins, &1_000);
+    assert_eq!(risk.get_account_emode(&smart_acc), Some(1));
+    env.ledger().with_mut(|li| li.timestamp += 1_000);
+    assert_eq!(risk.get_emode_factor_bps(&2), Some(9_800));
+    assert_eq!(risk.get_account_emode(&smart_acc), Some(2));
+    assert_eq!(
+        risk.get_account_breakdown(&smart_acc).total_collateral_value,
+        U256::from_u128(&env, 1076922 * WAD_U128 / 100000)
+    );
+
+    // Removal winds the factor down, handing the account back to the next best category
+    risk.remove_emode_category(&2, &1_000);
+    assert_eq!(risk.get_account_emode(&smart_acc), Some(2));
+    env.ledger().with_mut(|li| li.timestamp += 500);
+    assert_eq!(risk.get_emode_factor_bps(&2), Some(4_900));
+    assert_eq!(risk.get_account_emode(&smart_acc), Some(1));
+
+    risk.remove_emode_category(&1, &0);
+    assert_eq!(risk.get_account_emode(&smart_acc), None);
+    assert!(risk.try_remove_emode_category(&1, &0).is_err());
+}
+
+#[test]
+fn account_limits_cap_borrow_value_and_leverage() {
+    let env = Env::default();
+    let ctx = test_initiation(&env);
+    env.mock_all_auths();
+    initialise_lenders(&env, &ctx);
+
+    let account_manager_client =
+        AccountManagerContractClient::new(&env, &ctx.account_manager_contract);
+    account_manager_client.set_max_asset_cap(&U256::from_u32(&env, 10));
+    account_manager_client.set_iscollateral_allowed(&USDC_SYMBOL);
+
+    let trader = Addr::generate(&env);
+    let usdc_token = StellarAssetClient::new(&env, &ctx.usdc_address);
+    usdc_token.mint(&trader, &LARGE_AMOUNT);
+
+    let smart_acc = account_manager_client.create_account(&trader);
+    account_manager_client.deposit_collateral_tokens(
+        &smart_acc,
+        &USDC_SYMBOL,
+        &U256::from_u128(&env, 10 * WAD_U128),
+    );
+
+    // 10 XLM is 4 of debt, 11 XLM goes over a borrow limit of 4
+    let risk = RiskEngineContractClient::new(&env, &ctx.risk_engine_contract);
+    risk.set_account_limits(&AccountLimits {
+        max_leverage_wad: U256::from_u128(&env, 0),
+        max_borrow_value_wad: U256::from_u128(&env, 4 * WAD_U128),
+    });
+    assert!(risk.is_borrow_allowed(&XLM_SYMBOL, &U256::from_u128(&env, 10 * WAD_U128), &smart_acc));
+    assert_eq!(
+        risk.try_is_borrow_allowed(&XLM_SYMBOL, &U256::from_u128(&env, 11 * WAD_U128), &smart_acc),
+        Err(Ok(RiskEngineError::BorrowLimitExceeded))
+    );
+    assert!(account_manager_client
+        .try_borrow(&smart_acc, &U256::from_u128(&env, 11 * WAD_U128), &XLM_SYMBOL)
+        .is_err());
+    // A batch without external steps is held to the same limit
+    let batch = soroban_sdk::vec![
+        &env,
+        BatchCall::Borrow(XLM_SYMBOL, U256::from_u128(&env, 11 * WAD_U128)).to_xdr(&env),
+    ];
+    assert!(account_manager_client.try_execute_batch(&smart_acc, &batch).is_err());
+
+    // 13.99 against 9.99 of equity stays under 1.5x, 17.99 does not
+    risk.set_account_limits(&AccountLimits {
+        max_leverage_wad: U256::from_u128(&env, 15 * WAD_U128 / 10),
+        max_borrow_value_wad: U256::from_u128(&env, 0),
+    });
+    assert!(risk.is_borrow_allowed(&XLM_SYMBOL, &U256::from_u128(&env, 10 * WAD_U128), &smart_acc));
+    assert_eq!(
+        risk.try_is_borrow_allowed(&XLM_SYMBOL, &U256::from_u128(&env, 20 * WAD_U128), &smart_acc),
+        Err(Ok(RiskEngineError::LeverageLimitExceeded))
+    );
+
+    // Leverage is measured on market value, so a lower collateral factor leaves it at 1.4x
+    risk.set_collateral_factor_ramp(&USDC_SYMBOL, &5_000, &0);
+    assert!(risk.is_borrow_allowed(&XLM_SYMBOL, &U256::from_u128(&env, 10 * WAD_U128), &smart_acc));
+}
+
 #[test]
 fn mul_wad_down_basic_properties() {
     let env = Env::default();